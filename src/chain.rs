@@ -0,0 +1,147 @@
+use std::path::PathBuf;
+
+use url::Url;
+
+use crate::{
+    engine::{EngineOptions, TriggerContext},
+    scans::{
+        feroxbuster::FeroxbusterScanResult,
+        headers::{HeaderCheck, SECURITY_HEADERS},
+        nmap::{NmapHost, PortState},
+        nuclei::{NucleiScan, NucleiScanResult},
+    },
+    scheduler::{Job, JobResult, ScanOutput},
+};
+
+///Turns scan results into follow-up scans, e.g. a WordPress detection into a WordPress-specific scan.
+///Loop protection and the maximum chain depth are enforced by the scheduler.
+pub struct Chainer<'a> {
+    output_dir: PathBuf,
+    target: String,
+    options: &'a EngineOptions,
+}
+
+impl<'a> Chainer<'a> {
+    pub fn new(output_dir: PathBuf, target: String, options: &'a EngineOptions) -> Chainer<'a> {
+        Chainer {
+            output_dir,
+            target,
+            options,
+        }
+    }
+
+    ///Follow-up scans for a finished job.
+    pub fn follow_ups(&self, job_result: &JobResult) -> Vec<Job> {
+        match &job_result.result {
+            Ok(ScanOutput::Nuclei(results)) => self.nuclei_follow_ups(results),
+            Ok(ScanOutput::Feroxbuster(results)) => self.feroxbuster_follow_ups(results),
+            Ok(ScanOutput::Nmap(_))
+            | Ok(ScanOutput::Smb(_))
+            | Ok(ScanOutput::Headers(_))
+            | Ok(ScanOutput::Cve(_))
+            | Err(_) => Vec::new(),
+        }
+    }
+
+    ///New hostnames from TLS certificates get the same scans as the service the certificate was found on.
+    pub fn nmap_follow_ups(&self, hosts: &[NmapHost]) -> Vec<Job> {
+        let mut jobs = Vec::new();
+        for result in hosts
+            .iter()
+            .flat_map(|host| &host.ports)
+            .filter(|result| matches!(result.port.state, PortState::Open))
+        {
+            for hostname in result.certificate_dns_names() {
+                //Wildcard names can't be scanned and the target itself is already being scanned.
+                if hostname.contains('*') || hostname.eq_ignore_ascii_case(&self.target) {
+                    continue;
+                }
+
+                let context = TriggerContext {
+                    output_dir: self.output_dir.clone(),
+                    target: hostname,
+                    port: result.port.num,
+                    tls: result.is_tls(),
+                    base_path: String::new(),
+                    options: self.options,
+                };
+                self.options
+                    .scan_triggers
+                    .triggers
+                    .iter()
+                    .filter(|trigger| trigger.matches(result))
+                    .for_each(|trigger| jobs.extend(trigger.jobs(&context)));
+            }
+        }
+
+        jobs
+    }
+
+    fn nuclei_follow_ups(&self, results: &[NucleiScanResult]) -> Vec<Job> {
+        let mut jobs = Vec::new();
+        //Nuclei reports every missing header separately. They're confirmed together, per web service.
+        let mut missing_headers: Vec<(String, Vec<&NucleiScanResult>)> = Vec::new();
+        for result in results {
+            if result.template_id == "http-missing-security-headers"
+                && SECURITY_HEADERS.contains(&result.matcher_name.as_str())
+            {
+                let Ok(url) = Url::parse(result.matched_at()) else {
+                    continue;
+                };
+                let service = url.origin().ascii_serialization();
+                match missing_headers
+                    .iter_mut()
+                    .find(|(known, _)| *known == service)
+                {
+                    Some((_, service_results)) => service_results.push(result),
+                    None => missing_headers.push((service, vec![result])),
+                }
+            }
+        }
+        for (service, service_results) in missing_headers {
+            let header_check =
+                HeaderCheck::new(self.output_dir.clone(), &service, &service_results)
+                    .with_timeout(self.options.timeout);
+            jobs.push(Job::new(
+                "headers",
+                &service,
+                header_check,
+                ScanOutput::Headers,
+            ));
+        }
+
+        for result in results {
+            //tech-detect reports the detected technology as the matcher name.
+            if result.template_id == "tech-detect"
+                && result.matcher_name == "wordpress"
+                && !result.host.is_empty()
+            {
+                jobs.push(self.nuclei_job(&result.host, &["wordpress", "wp-plugin"]));
+            }
+        }
+
+        jobs
+    }
+
+    fn feroxbuster_follow_ups(&self, results: &[FeroxbusterScanResult]) -> Vec<Job> {
+        let mut jobs = Vec::new();
+        for result in results {
+            let Ok(url) = Url::parse(&result.url) else {
+                continue;
+            };
+            if url.path().starts_with("/.git") {
+                let base_url = url.origin().ascii_serialization();
+                jobs.push(self.nuclei_job(&base_url, &["git"]));
+            }
+        }
+
+        jobs
+    }
+
+    fn nuclei_job(&self, url: &str, tags: &[&str]) -> Job {
+        let tags: Vec<String> = tags.iter().map(|tag| tag.to_string()).collect();
+        let nuclei_scan = NucleiScan::new(self.output_dir.clone(), url.to_string(), &tags)
+            .with_timeout(self.options.timeout);
+        Job::new("nuclei", url, nuclei_scan, ScanOutput::Nuclei).with_variant(&tags.join(","))
+    }
+}
@@ -0,0 +1,167 @@
+use std::path::PathBuf;
+
+use clap::{ArgAction, Args, CommandFactory, ErrorKind, Parser, Subcommand, ValueEnum};
+
+use crate::config::PnMode;
+
+///Reconnaissance automation. Runs Nmap first and fires the configured scans for every service it finds.
+#[derive(Parser, Debug)]
+#[clap(name = "tartaros", version, about)]
+pub struct Cli {
+    ///Increase verbosity, can be repeated
+    #[clap(short, long, global = true, action = ArgAction::Count)]
+    pub verbose: u8,
+
+    ///Only print errors and results
+    #[clap(short, long, global = true, conflicts_with = "verbose")]
+    pub quiet: bool,
+
+    #[clap(subcommand)]
+    pub command: Command,
+}
+
+#[derive(Subcommand, Debug)]
+pub enum Command {
+    ///Scan one or more targets
+    Scan(Box<ScanArgs>),
+    ///Print the results of a previous run
+    Report(ReportArgs),
+    ///Compare the findings of two runs
+    Diff(DiffArgs),
+    ///Check whether all tools and the config are in order
+    Doctor,
+    ///Import NVD JSON feeds into the local index used for offline CVE matching
+    NvdImport(NvdImportArgs),
+}
+
+#[derive(Args, Debug)]
+pub struct ScanArgs {
+    ///IP addresses, hostnames, host:port pairs, http(s) URLs or CIDR ranges to scan
+    #[clap(required_unless_present = "target-files")]
+    pub targets: Vec<String>,
+
+    ///Read targets from a file, separated by whitespace or newlines (can be repeated)
+    #[clap(long = "iL", value_name = "FILE")]
+    pub target_files: Vec<PathBuf>,
+
+    ///TOML file with the allowed and denied targets. Anything out of scope is never scanned
+    #[clap(long, value_name = "FILE")]
+    pub scope: Option<PathBuf>,
+
+    ///Directory in which the results are stored
+    #[clap(short, long)]
+    pub output_dir: Option<PathBuf>,
+
+    ///Only run these scanners (comma separated, e.g. nuclei,feroxbuster)
+    #[clap(long, use_value_delimiter = true, conflicts_with = "disable")]
+    pub enable: Vec<String>,
+
+    ///Don't run these scanners (comma separated)
+    #[clap(long, use_value_delimiter = true)]
+    pub disable: Vec<String>,
+
+    ///Profile from the config file to use
+    #[clap(short, long)]
+    pub profile: Option<String>,
+
+    ///Maximum number of scans running at the same time
+    #[clap(short, long)]
+    pub concurrency: Option<usize>,
+
+    ///Maximum number of scans of a tool running at the same time, e.g. nmap=2 (can be repeated)
+    #[clap(long, value_name = "TOOL=N", value_parser = parse_tool_limit)]
+    pub tool_limit: Vec<(String, usize)>,
+
+    ///Maximum number of hosts scanned at the same time [default: 1]
+    #[clap(long, value_name = "N")]
+    pub host_concurrency: Option<usize>,
+
+    ///Kill any single scan that runs longer than this many seconds
+    #[clap(short, long)]
+    pub timeout: Option<u64>,
+
+    ///When to skip Nmap's host discovery (passes -Pn to Nmap) [default: auto]
+    #[clap(long, value_enum, value_name = "MODE")]
+    pub pn_mode: Option<PnMode>,
+
+    ///Treat the host as online, skipping Nmap's host discovery. Same as --pn-mode always
+    #[clap(long = "Pn", conflicts_with = "pn-mode")]
+    pub no_ping: bool,
+
+    ///Also scan this many of the most common UDP ports (requires root)
+    #[clap(long, value_name = "N")]
+    pub udp_top_ports: Option<u16>,
+
+    ///NVD index to match service versions against [default: nvd_index.json in the data directory, if it exists]
+    #[clap(long, value_name = "FILE")]
+    pub nvd_index: Option<PathBuf>,
+}
+
+#[derive(Args, Debug)]
+pub struct ReportArgs {
+    ///Output directory of the run
+    pub run_dir: PathBuf,
+
+    ///How to render the report
+    #[clap(short, long, value_enum, default_value_t = ReportFormat::Table)]
+    pub format: ReportFormat,
+
+    ///Where to write the Markdown notes or HTML file [default: <RUN_DIR>/notes or <RUN_DIR>/report.html]
+    #[clap(short, long)]
+    pub output: Option<PathBuf>,
+}
+
+#[derive(ValueEnum, Clone, Copy, Debug)]
+pub enum ReportFormat {
+    ///Print tables to the terminal
+    Table,
+    ///Write one Markdown note per host
+    Markdown,
+    ///Write a single self-contained HTML file
+    Html,
+}
+
+#[derive(Args, Debug)]
+pub struct DiffArgs {
+    ///Output directory of the older run
+    pub old_run_dir: PathBuf,
+    ///Output directory of the newer run
+    pub new_run_dir: PathBuf,
+}
+
+#[derive(Args, Debug)]
+pub struct NvdImportArgs {
+    ///Uncompressed NVD JSON feeds, e.g. nvdcve-2.0-2024.json
+    #[clap(required = true)]
+    pub feeds: Vec<PathBuf>,
+
+    ///Index to add the feeds to [default: nvd_index.json in the data directory]
+    #[clap(long, value_name = "FILE")]
+    pub index: Option<PathBuf>,
+}
+
+impl Cli {
+    ///Parses the command line. Nmap users type -Pn and -iL out of habit, which clap can't express as short flags, so they're rewritten to --Pn and --iL first.
+    pub fn parse_args() -> Cli {
+        Cli::parse_from(std::env::args().map(|arg| match arg.as_str() {
+            "-Pn" | "-iL" => format!("-{}", arg),
+            _ => arg,
+        }))
+    }
+
+    ///Prints a usage error the same way clap does for its own validation, and exits.
+    pub fn command_error(kind: ErrorKind, message: &str) -> ! {
+        Cli::command().error(kind, message).exit()
+    }
+}
+
+///Parses 'nmap=2' into the tool and its limit. Whether the tool exists is checked with the other flags.
+fn parse_tool_limit(arg: &str) -> Result<(String, usize), String> {
+    let (tool, limit) = arg
+        .split_once('=')
+        .ok_or_else(|| format!("expected TOOL=N, got '{}'", arg))?;
+    let limit = limit
+        .parse()
+        .map_err(|_| format!("'{}' is not a valid number", limit))?;
+    Ok((tool.to_string(), limit))
+}
@@ -0,0 +1,375 @@
+use clap::ValueEnum;
+use serde::{Deserialize, Serialize};
+use std::{
+    collections::{HashMap, HashSet},
+    fmt, fs, io,
+    path::{Path, PathBuf},
+};
+
+use crate::{output, scans::nmap::Protocol};
+
+///Trigger rules that ship with Tartaros. Used when the user has no config file of their own.
+const DEFAULT_CONFIG: &str = include_str!("../config/default.toml");
+///The wordlist that ships with Tartaros. It's installed in the data directory the first time a rule uses it.
+pub const SHIPPED_WORDLIST: &str = "wordlists/feroxbuster-dir.txt";
+const SHIPPED_WORDLIST_CONTENTS: &[u8] = include_bytes!("../wordlists/feroxbuster-dir.txt");
+
+///Names of the scanners that can be enabled/disabled. Nmap is required and can't be disabled.
+pub const SCANNERS: &[&str] = &[
+    "nuclei",
+    "feroxbuster",
+    "smb",
+    "snmp",
+    "tls",
+    "headers",
+    "cve",
+    "nvd",
+];
+
+///Tools that can be given a limit of their own. smb, snmp, tls and cve run Nmap, so they count against its limit.
+pub const LIMITED_TOOLS: &[&str] = &["nmap", "nuclei", "feroxbuster", "headers", "nvd"];
+
+#[derive(Deserialize, Debug)]
+#[serde(deny_unknown_fields)]
+pub struct Config {
+    #[serde(default)]
+    pub rules: Vec<Rule>,
+    #[serde(default)]
+    pub profiles: HashMap<String, Profile>,
+}
+
+///Named set of defaults that can be selected with --profile. Command line flags take precedence.
+#[derive(Deserialize, Debug)]
+#[serde(deny_unknown_fields)]
+pub struct Profile {
+    ///Scanners that don't run in this profile.
+    #[serde(default)]
+    pub disable: Vec<String>,
+    pub concurrency: Option<usize>,
+    ///Maximum number of scans running at the same time per tool, e.g. `nmap = 2`. Overrides the built-in limits.
+    #[serde(default)]
+    pub tool_limits: HashMap<String, usize>,
+    ///Maximum number of hosts scanned at the same time.
+    pub host_concurrency: Option<usize>,
+    ///Timeout in seconds for every single scan.
+    pub timeout: Option<u64>,
+    ///Also scan this many of the most common UDP ports.
+    pub udp_top_ports: Option<u16>,
+    ///When to pass -Pn to Nmap.
+    pub pn_mode: Option<PnMode>,
+}
+
+///Declares which scans run when a port with a matching service is found.
+#[derive(Deserialize, Debug)]
+#[serde(deny_unknown_fields)]
+pub struct Rule {
+    pub name: String,
+    ///Nmap service names, e.g. 'http' or 'http*'.
+    #[serde(default)]
+    pub services: Vec<String>,
+    ///Only used when Nmap could not identify the service.
+    #[serde(default)]
+    pub fallback_ports: Vec<u16>,
+    #[serde(default)]
+    pub state: RuleState,
+    ///Ports of the other protocol never fire the rule. Defaults to TCP.
+    #[serde(default)]
+    pub protocol: Protocol,
+    ///When set, only fire for services that are (or are not) wrapped in SSL/TLS.
+    pub tls: Option<bool>,
+    pub scans: Vec<ScanConfig>,
+}
+
+#[derive(Deserialize, Debug, Default, Clone, Copy)]
+#[serde(rename_all = "lowercase")]
+pub enum RuleState {
+    #[default]
+    Open,
+    Filtered,
+    Any,
+}
+
+///When Nmap skips host discovery (-Pn). Hosts that drop ping probes look down to Nmap and don't get port scanned at all.
+#[derive(Serialize, Deserialize, ValueEnum, Debug, Default, Clone, Copy, PartialEq, Eq)]
+#[serde(rename_all = "lowercase")]
+pub enum PnMode {
+    ///Never pass -Pn
+    Never,
+    ///Retry with -Pn when Nmap reports the host as down
+    #[default]
+    Auto,
+    ///Always pass -Pn
+    Always,
+}
+
+#[derive(Deserialize, Debug, Clone)]
+#[serde(tag = "tool", rename_all = "lowercase", deny_unknown_fields)]
+pub enum ScanConfig {
+    Nuclei {
+        #[serde(default)]
+        tags: Vec<String>,
+    },
+    Feroxbuster {
+        ///Relative to the config file. The shipped wordlist when not set.
+        #[serde(default = "shipped_wordlist")]
+        wordlist: PathBuf,
+    },
+    Smb,
+    ///SNMP enumeration through Nmap's snmp-* scripts.
+    Snmp {
+        ///Community string to try, 'public' when not set.
+        community: Option<String>,
+    },
+    ///Protocol, cipher and certificate checks through Nmap's ssl-enum-ciphers and ssl-cert scripts.
+    Tls,
+}
+
+impl ScanConfig {
+    ///Name of the scanner as used by --enable/--disable and profiles.
+    pub fn scanner(&self) -> &'static str {
+        match self {
+            ScanConfig::Nuclei { .. } => "nuclei",
+            ScanConfig::Feroxbuster { .. } => "feroxbuster",
+            ScanConfig::Smb => "smb",
+            ScanConfig::Snmp { .. } => "snmp",
+            ScanConfig::Tls => "tls",
+        }
+    }
+}
+
+#[derive(Debug)]
+pub enum ConfigError {
+    Io(PathBuf, io::Error),
+    Parse(PathBuf, toml::de::Error),
+    Wordlist(PathBuf, io::Error),
+    Invalid { rule: String, reason: String },
+    InvalidProfile { profile: String, reason: String },
+}
+
+impl fmt::Display for ConfigError {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        match self {
+            ConfigError::Io(path, err) => {
+                write!(f, "Unable to read config file {}: {}", path.display(), err)
+            }
+            ConfigError::Parse(path, err) => {
+                write!(f, "Unable to parse config file {}: {}", path.display(), err)
+            }
+            ConfigError::Wordlist(path, err) => {
+                write!(
+                    f,
+                    "Unable to install the shipped wordlist to {}: {}",
+                    path.display(),
+                    err
+                )
+            }
+            ConfigError::Invalid { rule, reason } => {
+                write!(f, "Invalid trigger rule '{}': {}", rule, reason)
+            }
+            ConfigError::InvalidProfile { profile, reason } => {
+                write!(f, "Invalid profile '{}': {}", profile, reason)
+            }
+        }
+    }
+}
+
+impl Config {
+    ///Loads the user's config file if there is one, otherwise the embedded defaults. The result is always validated.
+    pub fn load() -> Result<Config, ConfigError> {
+        match user_config_path() {
+            Some(path) if path.exists() => Config::from_file(&path),
+            _ => Config::parse(DEFAULT_CONFIG, Path::new("<built-in defaults>"), None),
+        }
+    }
+
+    ///Where the config was loaded from, for display purposes.
+    pub fn source() -> String {
+        match user_config_path() {
+            Some(path) if path.exists() => path.display().to_string(),
+            _ => String::from("<built-in defaults>"),
+        }
+    }
+
+    pub fn from_file(path: &Path) -> Result<Config, ConfigError> {
+        let contents =
+            fs::read_to_string(path).map_err(|err| ConfigError::Io(path.to_path_buf(), err))?;
+        Config::parse(&contents, path, path.parent())
+    }
+
+    ///`config_dir` is where relative paths in the config start from, `None` for the built-in defaults.
+    fn parse(
+        contents: &str,
+        path: &Path,
+        config_dir: Option<&Path>,
+    ) -> Result<Config, ConfigError> {
+        let mut config: Config =
+            toml::from_str(contents).map_err(|err| ConfigError::Parse(path.to_path_buf(), err))?;
+        config.resolve_wordlists(config_dir)?;
+        config.validate()?;
+        Ok(config)
+    }
+
+    ///Makes relative wordlists relative to the config file, except the shipped wordlist, which lives in the data directory.
+    fn resolve_wordlists(&mut self, config_dir: Option<&Path>) -> Result<(), ConfigError> {
+        for scan in self.rules.iter_mut().flat_map(|rule| rule.scans.iter_mut()) {
+            let ScanConfig::Feroxbuster { wordlist } = scan else {
+                continue;
+            };
+            if wordlist.as_path() == Path::new(SHIPPED_WORDLIST) {
+                *wordlist = install_shipped_wordlist()?;
+            } else if let Some(config_dir) = config_dir.filter(|_| wordlist.is_relative()) {
+                *wordlist = config_dir.join(&wordlist);
+            }
+        }
+        Ok(())
+    }
+
+    fn validate(&self) -> Result<(), ConfigError> {
+        let mut names = HashSet::new();
+        for rule in &self.rules {
+            let invalid = |reason: &str| ConfigError::Invalid {
+                rule: rule.name.clone(),
+                reason: reason.to_string(),
+            };
+
+            if rule.name.is_empty() {
+                return Err(invalid("rules need a non-empty 'name'"));
+            }
+            if !names.insert(rule.name.as_str()) {
+                return Err(invalid("another rule already uses this name"));
+            }
+            if rule.services.is_empty() && rule.fallback_ports.is_empty() {
+                return Err(invalid(
+                    "the rule can never fire, specify 'services' and/or 'fallback_ports'",
+                ));
+            }
+            if rule
+                .services
+                .iter()
+                .any(|service| service.trim().is_empty())
+            {
+                return Err(invalid("'services' contains an empty service name"));
+            }
+            if rule.fallback_ports.contains(&0) {
+                return Err(invalid("'fallback_ports' contains port 0"));
+            }
+            if rule.scans.is_empty() {
+                return Err(invalid("the rule has no 'scans' to run"));
+            }
+            for scan in &rule.scans {
+                if let ScanConfig::Feroxbuster { wordlist } = scan {
+                    if !wordlist.is_file() {
+                        return Err(invalid(&format!(
+                            "feroxbuster wordlist '{}' does not exist",
+                            wordlist.display()
+                        )));
+                    }
+                }
+            }
+        }
+
+        for (name, profile) in &self.profiles {
+            let invalid = |reason: String| ConfigError::InvalidProfile {
+                profile: name.clone(),
+                reason,
+            };
+            if let Some(scanner) = profile
+                .disable
+                .iter()
+                .find(|scanner| !SCANNERS.contains(&scanner.as_str()))
+            {
+                return Err(invalid(format!(
+                    "unknown scanner '{}', expected one of: {}",
+                    scanner,
+                    SCANNERS.join(", ")
+                )));
+            }
+            if let Some(tool) = profile
+                .tool_limits
+                .keys()
+                .find(|tool| !LIMITED_TOOLS.contains(&tool.as_str()))
+            {
+                return Err(invalid(format!(
+                    "unknown tool '{}' in 'tool_limits', expected one of: {}",
+                    tool,
+                    LIMITED_TOOLS.join(", ")
+                )));
+            }
+            if let Some(tool) = profile.tool_limits.iter().find(|(_, limit)| **limit == 0) {
+                return Err(invalid(format!(
+                    "the tool limit of '{}' must be at least 1",
+                    tool.0
+                )));
+            }
+            if profile.concurrency == Some(0) {
+                return Err(invalid(String::from("'concurrency' must be at least 1")));
+            }
+            if profile.host_concurrency == Some(0) {
+                return Err(invalid(String::from(
+                    "'host_concurrency' must be at least 1",
+                )));
+            }
+            if profile.timeout == Some(0) {
+                return Err(invalid(String::from("'timeout' must be at least 1 second")));
+            }
+            if profile.udp_top_ports == Some(0) {
+                return Err(invalid(String::from("'udp_top_ports' must be at least 1")));
+            }
+        }
+
+        Ok(())
+    }
+}
+
+fn shipped_wordlist() -> PathBuf {
+    PathBuf::from(SHIPPED_WORDLIST)
+}
+
+///Writes the shipped wordlist to the data directory, unless it's already there. Returns where it is.
+fn install_shipped_wordlist() -> Result<PathBuf, ConfigError> {
+    let relative = shipped_wordlist();
+    let path = output::base_dir(None)
+        .map_err(|err| ConfigError::Wordlist(relative.clone(), err))?
+        .join(&relative);
+    if !path.is_file() {
+        if let Some(parent) = path.parent() {
+            fs::create_dir_all(parent).map_err(|err| ConfigError::Wordlist(path.clone(), err))?;
+        }
+        fs::write(&path, SHIPPED_WORDLIST_CONTENTS)
+            .map_err(|err| ConfigError::Wordlist(path.clone(), err))?;
+    }
+    Ok(path)
+}
+
+///$XDG_CONFIG_HOME/tartaros/config.toml, falling back to ~/.config/tartaros/config.toml.
+fn user_config_path() -> Option<PathBuf> {
+    let config_dir = std::env::var_os("XDG_CONFIG_HOME")
+        .filter(|dir| !dir.is_empty())
+        .map(PathBuf::from)
+        .or_else(|| std::env::var_os("HOME").map(|home| PathBuf::from(home).join(".config")))?;
+    Some(config_dir.join("tartaros").join("config.toml"))
+}
+
+///Matches `value` against a pattern where '*' matches any sequence of characters.
+pub fn wildcard_match(pattern: &str, value: &str) -> bool {
+    let parts: Vec<&str> = pattern.split('*').collect();
+    if parts.len() == 1 {
+        return pattern == value;
+    }
+
+    //The first part must be a prefix, the last part a suffix and everything in between must appear in order.
+    let (first, last) = (parts[0], parts[parts.len() - 1]);
+    if value.len() < first.len() + last.len() || !value.starts_with(first) || !value.ends_with(last)
+    {
+        return false;
+    }
+    let mut remainder = &value[first.len()..value.len() - last.len()];
+    for part in &parts[1..parts.len() - 1] {
+        match remainder.find(part) {
+            Some(index) => remainder = &remainder[index + part.len()..],
+            None => return false,
+        }
+    }
+
+    true
+}
@@ -0,0 +1,95 @@
+use comfy_table::{modifiers::UTF8_ROUND_CORNERS, presets::UTF8_FULL, Cell, Color, Table};
+use which::which;
+
+use crate::{config::Config, logger, nvd::NvdIndex, output, scans::process};
+
+///External tools Tartaros drives, and whether a scan can't run at all without them.
+const TOOLS: &[(&str, bool)] = &[
+    ("nmap", true),
+    ("rustscan", false),
+    ("nuclei", false),
+    ("feroxbuster", false),
+    ("curl", false),
+    ("smbclient", false),
+    ("enum4linux-ng", false),
+];
+
+///Checks the environment and prints what's wrong with it. Returns false if Tartaros can't scan.
+pub fn run() -> bool {
+    let mut healthy = true;
+
+    let mut table = Table::new();
+    table
+        .load_preset(UTF8_FULL)
+        .apply_modifier(UTF8_ROUND_CORNERS)
+        .set_header(vec!["Tool", "Required", "Status", "Version"]);
+    for (tool, required) in TOOLS {
+        let (status, version) = match which(tool) {
+            Ok(path) => (
+                Cell::new(path.display()).fg(Color::Green),
+                process::tool_version(tool).unwrap_or_default(),
+            ),
+            Err(_) => {
+                healthy &= !required;
+                let color = if *required { Color::Red } else { Color::Yellow };
+                (Cell::new("Not installed").fg(color), String::new())
+            }
+        };
+        table.add_row(vec![
+            Cell::new(tool),
+            Cell::new(if *required { "yes" } else { "no" }),
+            status,
+            Cell::new(version),
+        ]);
+    }
+    println!("{}", table);
+
+    match Config::load() {
+        Ok(config) => logger::print_ok(&format!(
+            "Config {} is valid ({} trigger rules, {} profiles).",
+            Config::source(),
+            config.rules.len(),
+            config.profiles.len()
+        )),
+        Err(err) => {
+            healthy = false;
+            logger::print_err(&err.to_string());
+        }
+    }
+
+    match output::base_dir(None) {
+        Ok(path) => logger::print_ok(&format!("Results are stored in {}.", path.display())),
+        Err(err) => {
+            healthy = false;
+            logger::print_err(&format!(
+                "Unable to determine the output directory: {}",
+                err
+            ));
+        }
+    }
+
+    //Optional, without an index there's just no offline CVE matching.
+    match NvdIndex::default_path().filter(|path| path.exists()) {
+        Some(path) => match NvdIndex::load(&path) {
+            Ok(index) => logger::print_ok(&format!(
+                "NVD index {} holds {} CVEs from {} feeds, last imported {}.",
+                path.display(),
+                index.cves.len(),
+                index.feeds.len(),
+                index
+                    .feeds
+                    .iter()
+                    .map(|feed| feed.imported_at)
+                    .max()
+                    .map(|imported_at| imported_at.format("%Y-%m-%d").to_string())
+                    .unwrap_or_else(|| String::from("never"))
+            )),
+            Err(err) => logger::print_warn(&err),
+        },
+        None => logger::print_warn(
+            "No NVD index, services aren't matched against the NVD offline. Import feeds with: tartaros nvd-import <feed>...",
+        ),
+    }
+
+    healthy
+}
@@ -1,142 +1,601 @@
 use crate::{
+    chain::Chainer,
+    config::{self, Config, PnMode, RuleState, ScanConfig},
     logger,
+    nvd::NvdIndex,
+    report,
     scans::{
+        cve::{self, CveScan},
         error::ScanError,
         feroxbuster::FeroxbusterScan,
-        nmap::{self, NmapScan, PortState},
+        finding::{self, Finding, Severity},
+        headers,
+        nmap::{
+            self, Address, HostStatus, Hostname, NmapHost, NmapScan, NmapScanResult, Port,
+            PortState, Protocol,
+        },
+        nmap_script::NmapScriptScan,
         nuclei::NucleiScan,
+        nvd::NvdMatch,
         scan::Scan,
+        smb::SmbScan,
+        udp::UdpScan,
     },
+    scheduler::{Job, JobResult, ScanOutput, Scheduler, SchedulerConfig},
+    scope::{Refusal, Scope},
+    targets::{self, Target},
 };
-use std::{collections::HashMap, path::PathBuf};
+use comfy_table::{modifiers::UTF8_ROUND_CORNERS, presets::UTF8_FULL, Table};
+use std::{
+    net::IpAddr,
+    path::{Path, PathBuf},
+    sync::Arc,
+    time::{Duration, Instant},
+};
+use url::Url;
+
+///NSE scripts that enumerate an SNMP agent once the community string is known.
+const SNMP_SCRIPTS: &[&str] = &[
+    "snmp-sysdescr",
+    "snmp-info",
+    "snmp-interfaces",
+    "snmp-netstat",
+    "snmp-processes",
+    "snmp-win32-users",
+    "snmp-win32-services",
+    "snmp-win32-software",
+];
+
+///NSE scripts of the TLS assessment. ssl-cert also runs with -sC, but not on every TLS service.
+const TLS_SCRIPTS: &[&str] = &["ssl-enum-ciphers", "ssl-cert"];
+
+///Everything that can be tweaked about a run, mostly from the command line.
+pub struct EngineOptions {
+    pub scan_triggers: ScanTriggers,
+    pub scheduler_config: SchedulerConfig,
+    ///Scanners that don't run, see `config::SCANNERS`.
+    pub disabled_scanners: Vec<String>,
+    ///Overrides the default timeout of every scan.
+    pub timeout: Option<Duration>,
+    ///When to pass -Pn to Nmap.
+    pub pn_mode: PnMode,
+    ///Every scan's target is checked against this before it runs.
+    pub scope: Scope,
+    ///Runs a UDP scan of this many of the most common UDP ports after the TCP scan.
+    pub udp_top_ports: Option<u16>,
+    ///Every detected service version is matched against it, see `NvdMatch`.
+    pub nvd_index: Option<Arc<NvdIndex>>,
+}
+
+impl EngineOptions {
+    pub fn is_enabled(&self, scanner: &str) -> bool {
+        !self
+            .disabled_scanners
+            .iter()
+            .any(|disabled| disabled == scanner)
+    }
+
+    ///Whether the job's target is in scope. Refused jobs are logged and added to `refused`.
+    fn in_scope(&self, job: &Job, refused: &mut Vec<Refusal>) -> bool {
+        match self.scope.check(&job.target) {
+            Ok(()) => true,
+            Err(reason) => {
+                logger::print_warn(&format!(
+                    "Not running {} against {}: out of scope ({}).",
+                    job.tool, job.target, reason
+                ));
+                refused.push(Refusal {
+                    tool: job.tool.to_string(),
+                    target: job.target.clone(),
+                    reason,
+                });
+                false
+            }
+        }
+    }
+}
+
+///Everything a trigger needs to know about the service that fired it.
+pub struct TriggerContext<'a> {
+    pub output_dir: PathBuf,
+    pub target: String,
+    pub port: u16,
+    ///Whether the service is wrapped in SSL/TLS, which decides between http:// and https://.
+    pub tls: bool,
+    ///Path the web scans start at, e.g. '/portal'. Empty for the root.
+    pub base_path: String,
+    pub options: &'a EngineOptions,
+}
+
+///A trigger rule from the config, compiled into something the engine can match Nmap results against.
+pub struct Trigger {
+    name: String,
+    ///Nmap service names that fire this trigger. May contain '*' wildcards.
+    services: Vec<String>,
+    ///Ports that fire this trigger, but only when Nmap could not identify the service.
+    fallback_ports: Vec<u16>,
+    state: RuleState,
+    protocol: Protocol,
+    tls: Option<bool>,
+    scans: Vec<ScanConfig>,
+}
+
+impl Trigger {
+    ///Matches on the service name Nmap detected. The port number is only considered when the service is unknown,
+    ///so e.g. HTTP on 8080 is scanned while a non-HTTP service on port 80 is not.
+    pub fn matches(&self, result: &NmapScanResult) -> bool {
+        let state_matches = match self.state {
+            RuleState::Open => matches!(result.port.state, PortState::Open),
+            RuleState::Filtered => matches!(result.port.state, PortState::Filtered),
+            RuleState::Any => true,
+        };
+        if !state_matches
+            || result.port.protocol != self.protocol
+            || self.tls.is_some_and(|tls| tls != result.is_tls())
+        {
+            return false;
+        }
+
+        let service = result.service_name.as_str();
+        if service.is_empty() || service == "unknown" || service == "tcpwrapped" {
+            return self.fallback_ports.contains(&result.port.num);
+        }
+
+        //Nmap sometimes reports the tunnel as part of the service name, e.g. 'ssl/http'.
+        let service = service.strip_prefix("ssl/").unwrap_or(service);
+        self.services
+            .iter()
+            .any(|pattern| config::wildcard_match(pattern, service))
+    }
 
+    ///Builds the jobs for all scans this trigger declares.
+    pub fn jobs(&self, context: &TriggerContext) -> Vec<Job> {
+        logger::print_debug(&format!(
+            "Rule '{}' fired for {}:{}",
+            self.name,
+            targets::host_in_url(&context.target),
+            context.port
+        ));
+        let scheme = if context.tls { "https" } else { "http" };
+        let url = format!(
+            "{}://{}:{}{}",
+            scheme,
+            targets::host_in_url(&context.target),
+            context.port,
+            context.base_path
+        );
+
+        let mut jobs = Vec::new();
+        for scan in &self.scans {
+            if !context.options.is_enabled(scan.scanner()) {
+                continue;
+            }
+            match scan {
+                ScanConfig::Feroxbuster { wordlist } => {
+                    let feroxbuster_scan = FeroxbusterScan::new(
+                        context.output_dir.clone(),
+                        &url,
+                        context.port,
+                        wordlist,
+                    )
+                    .with_timeout(context.options.timeout);
+                    jobs.push(Job::new(
+                        "feroxbuster",
+                        &url,
+                        feroxbuster_scan,
+                        ScanOutput::Feroxbuster,
+                    ));
+                }
+                ScanConfig::Nuclei { tags } => {
+                    let nuclei_scan =
+                        NucleiScan::new(context.output_dir.clone(), url.clone(), tags)
+                            .with_timeout(context.options.timeout);
+                    jobs.push(Job::new("nuclei", &url, nuclei_scan, ScanOutput::Nuclei));
+                }
+                ScanConfig::Smb => {
+                    let smb_scan =
+                        SmbScan::new(context.output_dir.clone(), &context.target, context.port)
+                            .with_timeout(context.options.timeout);
+                    let target = format!(
+                        "smb://{}:{}",
+                        targets::host_in_url(&context.target),
+                        context.port
+                    );
+                    //smbclient and enum4linux-ng run after Nmap's SMB scripts, in the same job.
+                    jobs.push(
+                        Job::new("smb", &target, smb_scan, ScanOutput::Smb).with_limit("nmap"),
+                    );
+                }
+                ScanConfig::Snmp { community } => {
+                    let community = community.as_deref().unwrap_or("public");
+                    let snmp_scan = NmapScriptScan::new(
+                        context.output_dir.clone(),
+                        "snmp",
+                        &context.target,
+                        context.port,
+                        Protocol::Udp,
+                        SNMP_SCRIPTS,
+                        Some(format!("snmpcommunity={}", community)),
+                    )
+                    .with_timeout(context.options.timeout);
+                    let target = format!(
+                        "snmp://{}:{}",
+                        targets::host_in_url(&context.target),
+                        context.port
+                    );
+                    jobs.push(
+                        Job::new("snmp", &target, snmp_scan, ScanOutput::Nmap).with_limit("nmap"),
+                    );
+                }
+                ScanConfig::Tls => {
+                    let tls_scan = NmapScriptScan::new(
+                        context.output_dir.clone(),
+                        "tls",
+                        &context.target,
+                        context.port,
+                        Protocol::Tcp,
+                        TLS_SCRIPTS,
+                        None,
+                    )
+                    .with_timeout(context.options.timeout);
+                    let target = format!(
+                        "tls://{}:{}",
+                        targets::host_in_url(&context.target),
+                        context.port
+                    );
+                    jobs.push(
+                        Job::new("tls", &target, tls_scan, ScanOutput::Nmap).with_limit("nmap"),
+                    );
+                }
+            }
+        }
+
+        jobs
+    }
+}
+
+///The engine's trigger table, compiled from the trigger rules in the config.
 pub struct ScanTriggers {
-    ///HashMap that maps Strings to function pointers. Allows looking up a port number and triggering the appropriate function.
-    pub triggers: HashMap<String, fn(PathBuf, String)>,
+    pub triggers: Vec<Trigger>,
 }
 
 impl ScanTriggers {
-    pub fn new() -> ScanTriggers {
+    pub fn from_config(config: &Config) -> ScanTriggers {
         ScanTriggers {
-            triggers: HashMap::from([
-                (String::from("80"), port80_triggers as fn(PathBuf, String)),
-                (String::from("443"), port443_triggers as fn(PathBuf, String)),
-                (String::from("445"), port445_triggers as fn(PathBuf, String)),
-            ]),
+            triggers: config
+                .rules
+                .iter()
+                .map(|rule| Trigger {
+                    name: rule.name.clone(),
+                    services: rule.services.clone(),
+                    fallback_ports: rule.fallback_ports.clone(),
+                    state: rule.state,
+                    protocol: rule.protocol,
+                    tls: rule.tls,
+                    scans: rule.scans.clone(),
+                })
+                .collect(),
         }
     }
 }
 
-pub fn run(target: String, output_dir: PathBuf) {
-    logger::print_ok(&format!("Scanning {}", target));
+///Everything a run produced, handed back to the caller for reporting.
+pub struct RunResults {
+    pub hosts: Vec<NmapHost>,
+    ///Only set when Nmap ran, URL targets skip it.
+    pub nmap_duration: Option<Duration>,
+    pub nmap_error: Option<ScanError>,
+    ///Whether the Nmap results came from a scan with -Pn. `None` if Nmap failed or didn't run.
+    pub pn_used: Option<bool>,
+    ///Only set when the UDP scan ran.
+    pub udp_duration: Option<Duration>,
+    pub udp_error: Option<ScanError>,
+    pub job_results: Vec<JobResult>,
+    ///Scans that were not run because their target is out of scope.
+    pub refused: Vec<Refusal>,
+    ///All findings of the run, deduplicated and sorted by severity.
+    pub findings: Vec<Finding>,
+}
+
+pub fn run(target: &Target, output_dir: PathBuf, options: &EngineOptions) -> RunResults {
+    logger::print_ok(&format!("Scanning {}", target.name()));
     logger::print_ok("NOTE: Most scans (if they offer the option) will output their raw results to a file in the output directory.\n\n");
-    let scan_triggers = ScanTriggers::new();
-    let nmap_scan = NmapScan::new(output_dir.clone(), target.clone());
-    match nmap_scan.run() {
-        //This error handling is very much experimental at this point. Should be worked out and be more elaborate in the future.
-        Err(err) => handle_scan_error(err),
-        Ok(results) => {
-            if results.len() < 1 {
-                //TODO: Add option to enable -Pn option for Nmap to assume that the host is up. Maybe even enable this by default...
-                logger::print_warn("NOTE: Nmap did not find any open ports. This is weird and should be investigated manually if you expect the host to be up. Terminating.");
-            } else {
-                println!("");
-                //Nmap scan was successful and found open ports. Now run the "always trigger" scans.
-                always_triggers(output_dir.clone(), target.clone());
-                results
-                    .iter()
-                    .filter(|x| matches!(x.port.state, PortState::Open)) // Make sure that the port is actually open
-                    .for_each(|result| {
-                        if scan_triggers.triggers.contains_key(&result.port.num) {
-                            scan_triggers.triggers[&result.port.num](
-                                output_dir.clone(),
-                                target.clone(),
-                            ); //Trigger the other scan based on the port number
-                        }
-                    });
+    let host = target.host();
+    let mut run_results = RunResults {
+        hosts: Vec::new(),
+        nmap_duration: None,
+        nmap_error: None,
+        pn_used: None,
+        udp_duration: None,
+        udp_error: None,
+        job_results: Vec::new(),
+        refused: Vec::new(),
+        findings: Vec::new(),
+    };
+
+    let (hosts, mut findings, base_path) = match target {
+        //There's nothing Nmap can add for a web application, its URL already says where and how to reach it.
+        Target::Url(url) => {
+            logger::print_ok(
+                "The target is a URL, skipping Nmap and running the web scans against it.",
+            );
+            (
+                vec![url_host(url, &host)],
+                Vec::new(),
+                url.path().trim_end_matches('/').to_string(),
+            )
+        }
+        _ => {
+            let port = match target {
+                Target::Service { port, .. } => Some(*port),
+                _ => None,
+            };
+            match port_scan(&host, port, &output_dir, options, &mut run_results) {
+                Some((hosts, findings)) => (hosts, findings, String::new()),
+                None => return run_results,
             }
         }
+    };
+
+    if hosts.iter().all(|host| host.ports.is_empty()) {
+        if run_results.pn_used == Some(true) {
+            logger::print_warn("NOTE: Nmap did not find any open ports, even with -Pn. This is weird and should be investigated manually if you expect the host to be up. Terminating.");
+        } else {
+            logger::print_warn("NOTE: Nmap did not find any open ports. This is weird and should be investigated manually if you expect the host to be up (try -Pn). Terminating.");
+        }
+        run_results.hosts = hosts;
+        return run_results;
     }
-}
 
-//Scans that are always triggered, regardless of port.
-fn always_triggers(output_dir: PathBuf, target: String) {
-    //NUCLEI
-    //After this scan I want to automatically report all the missing HTTP headers.
-    //I can loop through the result, check whether the missing header should be reported, and if so:
-    //Copy the curl command, perform it, save the output, and mark the command.
-    let nuclei_scan = NucleiScan::new(output_dir, target);
-    if let Err(err) = nuclei_scan.run() {
-        handle_scan_error(err)
+    println!();
+    //Collect the "always trigger" scans and the port based scans, then run them all at once.
+    //A single port or URL was asked for, so the scans that go beyond it are left out.
+    let mut jobs = match target {
+        Target::Host(_) => always_triggers(output_dir.clone(), host.clone(), options),
+        _ => Vec::new(),
+    };
+    jobs.extend(cve_job(&output_dir, &host, &hosts, options));
+    jobs.extend(nvd_job(&host, &hosts, options));
+    hosts
+        .iter()
+        .flat_map(|host| &host.ports)
+        .for_each(|result| {
+            let context = TriggerContext {
+                output_dir: output_dir.clone(),
+                target: host.clone(),
+                port: result.port.num,
+                tls: result.is_tls(),
+                base_path: base_path.clone(),
+                options,
+            };
+            //Trigger the other scans based on the detected service
+            options
+                .scan_triggers
+                .triggers
+                .iter()
+                .filter(|trigger| trigger.matches(result))
+                .for_each(|trigger| jobs.extend(trigger.jobs(&context)));
+        });
+
+    //Results can chain into more scans, e.g. new hostnames from TLS certificates.
+    let chainer = Chainer::new(output_dir.clone(), host.clone(), options);
+    jobs.extend(chainer.nmap_follow_ups(&hosts));
+    //Chained scans in particular can wander off to other hosts, so every job is checked against the scope.
+    let mut refused = Vec::new();
+    jobs.retain(|job| options.is_enabled(job.tool) && options.in_scope(job, &mut refused));
+
+    let job_results = Scheduler::new(options.scheduler_config.clone()).run(jobs, |job_result| {
+        let mut follow_ups = chainer.follow_ups(job_result);
+        follow_ups
+            .retain(|job| options.is_enabled(job.tool) && options.in_scope(job, &mut refused));
+        follow_ups
+    });
+    print_summary(&job_results);
+
+    //Normalize everything to findings, so the overview doesn't care which tool found what.
+    for job_result in &job_results {
+        findings.extend(job_result.findings.iter().cloned());
     }
+    //The header checks report Nuclei's missing headers as one confirmed finding per web service.
+    let checked_origins: Vec<&str> = job_results
+        .iter()
+        .filter(|job_result| job_result.tool == "headers" && job_result.result.is_ok())
+        .map(|job_result| job_result.target.as_str())
+        .collect();
+    findings.retain(|finding| !headers::replaced_by_check(finding, &checked_origins));
+    let findings = finding::dedup(findings);
+    let notable_findings = finding::filter_severity(findings.clone(), Severity::Low);
+    if notable_findings.is_empty() {
+        logger::print_ok("No findings of low severity or higher.");
+    } else {
+        report::print_findings(&notable_findings);
+    }
+    job_results
+        .iter()
+        .filter_map(|job_result| job_result.result.as_ref().err())
+        .for_each(handle_scan_error);
 
-    //CVE scan
-    //nmap -sC --script cve*
+    run_results.hosts = hosts;
+    run_results.job_results = job_results;
+    run_results.refused = refused;
+    run_results.findings = findings;
+    run_results
 }
-fn port80_triggers(output_dir: PathBuf, mut target: String) {
-    //FEROXBUSTER
-    {
-        let feroxbuster_scan = FeroxbusterScan::new(output_dir.clone(), &target, 80);
-        if let Err(err) = feroxbuster_scan.run() {
-            handle_scan_error(err);
-        }
+
+///Runs Nmap (and the UDP scan, if enabled) against the host, optionally limited to a single port.
+///Returns the hosts and their findings, or `None` when Nmap failed. The rest is recorded in `run_results`.
+fn port_scan(
+    host: &str,
+    port: Option<u16>,
+    output_dir: &Path,
+    options: &EngineOptions,
+    run_results: &mut RunResults,
+) -> Option<(Vec<NmapHost>, Vec<Finding>)> {
+    let nmap_scan = |no_ping: bool| {
+        NmapScan::new(output_dir.to_path_buf(), host.to_string(), no_ping)
+            .with_port(port)
+            .with_timeout(options.timeout)
+    };
+    let mut pn_used = options.pn_mode == PnMode::Always;
+    let mut scan = nmap_scan(pn_used);
+    let nmap_started = Instant::now();
+    let mut nmap_result = scan.run();
+    //Nmap doesn't port scan hosts that don't answer its pings, even though plenty of firewalls drop them.
+    let nothing_found =
+        matches!(&nmap_result, Ok(hosts) if hosts.iter().all(|host| host.ports.is_empty()));
+    if options.pn_mode == PnMode::Auto && nothing_found && scan.host_seems_down() {
+        logger::print_warn(
+            "Nmap reports the host as down, it might be blocking ping probes. Retrying with -Pn.",
+        );
+        pn_used = true;
+        scan = nmap_scan(pn_used);
+        nmap_result = scan.run();
     }
+    run_results.nmap_duration = Some(nmap_started.elapsed());
 
-    //NUCLEI
-    {
-        target.insert_str(0, "http://"); //Prepend the https:// protocol specifier.
-        let nuclei_scan = NucleiScan::new(output_dir, target);
-        if let Err(err) = nuclei_scan.run() {
-            handle_scan_error(err)
+    let mut hosts = match nmap_result {
+        Ok(hosts) => hosts,
+        //This error handling is very much experimental at this point. Should be worked out and be more elaborate in the future.
+        Err(err) => {
+            handle_scan_error(&err);
+            run_results.nmap_error = Some(err);
+            return None;
+        }
+    };
+    run_results.pn_used = Some(pn_used);
+
+    //A single port was asked for, so UDP is out.
+    if let (Some(top_ports), None) = (options.udp_top_ports, port) {
+        let udp_scan = UdpScan::new(output_dir.to_path_buf(), host.to_string(), top_ports)
+            .with_timeout(options.timeout);
+        let udp_started = Instant::now();
+        //A failed UDP scan (it needs root) is no reason to skip the TCP based scans.
+        match udp_scan.run() {
+            Ok(udp_hosts) => hosts = nmap::merge_hosts(udp_hosts, hosts),
+            Err(err) => {
+                handle_scan_error(&err);
+                run_results.udp_error = Some(err);
+            }
         }
+        run_results.udp_duration = Some(udp_started.elapsed());
     }
+
+    let findings = scan.to_findings(&hosts);
+    Some((hosts, findings))
 }
 
-fn port443_triggers(output_dir: PathBuf, mut target: String) {
-    //FEROXBUSTER
-    {
-        target.insert_str(0, "https://"); //Prepend the https:// protocol specifier.
-        let feroxbuster_scan = FeroxbusterScan::new(output_dir.clone(), &target, 443);
-        if let Err(err) = feroxbuster_scan.run() {
-            handle_scan_error(err);
-        }
+///Stands in for Nmap's results for a URL target: a single open web port, as the URL describes it.
+fn url_host(url: &Url, host: &str) -> NmapHost {
+    let result = NmapScanResult {
+        port: Port {
+            num: url.port_or_known_default().unwrap_or(80),
+            protocol: Protocol::Tcp,
+            state: PortState::Open,
+            reason: String::from("user-set"),
+        },
+        service_name: String::from("http"),
+        service_tunnel: if url.scheme() == "https" {
+            String::from("ssl")
+        } else {
+            String::new()
+        },
+        ..Default::default()
+    };
+    let addresses = match host.parse::<IpAddr>() {
+        Ok(addr) => vec![Address {
+            addr: addr.to_string(),
+            addrtype: String::from(if addr.is_ipv4() { "ipv4" } else { "ipv6" }),
+            vendor: String::new(),
+        }],
+        Err(_) => Vec::new(),
+    };
+
+    NmapHost {
+        status: HostStatus {
+            state: String::from("up"),
+            reason: String::from("user-set"),
+        },
+        addresses,
+        hostnames: vec![Hostname {
+            name: host.to_string(),
+            kind: String::from("user"),
+        }],
+        ports: vec![result],
+        ..Default::default()
     }
+}
 
+//Scans that are always triggered, regardless of port.
+fn always_triggers(output_dir: PathBuf, target: String, options: &EngineOptions) -> Vec<Job> {
     //NUCLEI
-    {
-        target.insert_str(0, "https://"); //Prepend the https:// protocol specifier.
-        let nuclei_scan = NucleiScan::new(output_dir, target);
-        if let Err(err) = nuclei_scan.run() {
-            handle_scan_error(err)
-        }
-    }
+    //Missing security headers it reports are replayed and reported per web service, see `HeaderCheck`.
+    //Nuclei also takes host:port input, brackets keep IPv6 addresses unambiguous.
+    let target = targets::host_in_url(&target);
+    let nuclei_scan =
+        NucleiScan::new(output_dir, target.clone(), &[]).with_timeout(options.timeout);
+
+    vec![Job::new("nuclei", &target, nuclei_scan, ScanOutput::Nuclei)]
+}
 
-    //NUCLEI with https:// prefix.
+///Looks up known vulnerabilities of every service Nmap detected a version of. Nothing to look up without one.
+fn cve_job(
+    output_dir: &Path,
+    target: &str,
+    hosts: &[NmapHost],
+    options: &EngineOptions,
+) -> Option<Job> {
+    let ports = cve::versioned_ports(hosts);
+    if ports.is_empty() {
+        return None;
+    }
+    let cve_scan =
+        CveScan::new(output_dir.to_path_buf(), target, &ports).with_timeout(options.timeout);
+    let job_target = format!("cve://{}", targets::host_in_url(target));
+    Some(Job::new("cve", &job_target, cve_scan, ScanOutput::Cve).with_limit("nmap"))
+}
 
-    //NMAP SSL SCAN
-    //This scan will perform an SSL scan of the target and automatically report the following findings:
-    // - Weak ciphers
-    // - TLS 1.0 and/or TLS 1.1
-    // - Self-signed/untrusted certificate
-    // I also want to do this for port 3389
+///Matches the detected versions against the local NVD index, when there is one.
+fn nvd_job(target: &str, hosts: &[NmapHost], options: &EngineOptions) -> Option<Job> {
+    let nvd_match = NvdMatch::new(target, hosts, options.nvd_index.clone()?);
+    if !nvd_match.has_services() {
+        return None;
+    }
+    let job_target = format!("nvd://{}", targets::host_in_url(target));
+    Some(Job::new("nvd", &job_target, nvd_match, ScanOutput::Cve))
 }
 
-//Tbh could use a lot of Nmap's scripting capabilities here already.
-fn port445_triggers(output_dir: PathBuf, target: String) {
-    //Run Nmap scan nmap -sC --scrip smb*
-    logger::print_warn("Running smbclient, smb nmap scan, etc.");
+///Prints an overview of all scans that were run by the scheduler.
+fn print_summary(job_results: &[JobResult]) {
+    let mut table = Table::new();
+    table
+        .load_preset(UTF8_FULL)
+        .apply_modifier(UTF8_ROUND_CORNERS)
+        .set_header(vec!["Tool", "Target", "Duration", "Results"]);
 
-    // Report SMB signing disabled here as well.
+    for job_result in job_results {
+        let status = match &job_result.result {
+            Ok(output) => output.len().to_string(),
+            Err(_) => String::from("Failed"),
+        };
+        table.add_row(vec![
+            job_result.tool.to_string(),
+            job_result.target.clone(),
+            format!("{}s", job_result.duration.as_secs()),
+            status,
+        ]);
+    }
+
+    println!("{}", table);
 }
 
-fn handle_scan_error(err: ScanError) {
+fn handle_scan_error(err: &ScanError) {
+    logger::print_err(&err.to_string());
     match err {
-        ScanError::NotInstalled(scan) => {
-            logger::print_err(&format!("{}: Not installed, skipping this scan.", scan))
-        }
-        ScanError::Runtime(scan) => {
-            logger::print_err(&format!("{}: Ecountered runtime error", scan))
+        ScanError::NonZeroExit { stderr_tail, .. } | ScanError::Timeout { stderr_tail, .. } => {
+            if !stderr_tail.is_empty() {
+                logger::print_err(&format!("Last lines of stderr:\n{}", stderr_tail));
+            }
         }
-        _ => {}
+        ScanError::NotInstalled(_) | ScanError::Runtime(_) => {}
     }
 }
@@ -1,7 +1,33 @@
 use colored::Colorize;
+use std::sync::atomic::{AtomicU8, Ordering};
+
+const QUIET: u8 = 0;
+const NORMAL: u8 = 1;
+
+///0 is quiet, 1 is the default and anything higher is verbose.
+static VERBOSITY: AtomicU8 = AtomicU8::new(NORMAL);
+
+pub fn set_verbosity(quiet: bool, verbose: u8) {
+    let verbosity = if quiet {
+        QUIET
+    } else {
+        NORMAL.saturating_add(verbose)
+    };
+    VERBOSITY.store(verbosity, Ordering::Relaxed);
+}
+
+pub fn is_quiet() -> bool {
+    VERBOSITY.load(Ordering::Relaxed) == QUIET
+}
+
+fn is_verbose() -> bool {
+    VERBOSITY.load(Ordering::Relaxed) > NORMAL
+}
 
 pub fn print_ok(msg: &str) {
-    println!("{} {}", "[+]".green(), msg.green());
+    if !is_quiet() {
+        println!("{} {}", "[+]".green(), msg.green());
+    }
 }
 
 pub fn print_err(msg: &str) {
@@ -9,5 +35,14 @@ pub fn print_err(msg: &str) {
 }
 
 pub fn print_warn(msg: &str) {
-    println!("{} {}", "[~]".yellow(), msg.yellow());
+    if !is_quiet() {
+        println!("{} {}", "[~]".yellow(), msg.yellow());
+    }
+}
+
+///Only printed with -v.
+pub fn print_debug(msg: &str) {
+    if is_verbose() {
+        println!("{} {}", "[*]".blue(), msg);
+    }
 }
@@ -1,17 +1,39 @@
+use clap::ErrorKind;
+use cli::{Cli, Command, NvdImportArgs, ReportFormat, ScanArgs};
 use colored::Colorize;
+use config::{Config, PnMode, LIMITED_TOOLS, SCANNERS};
+use engine::{EngineOptions, ScanTriggers};
+use manifest::RunManifest;
+use nvd::NvdIndex;
+use report::json::RunReport;
+use scans::{ping_sweep::PingSweep, scan::Scan};
+use scheduler::SchedulerConfig;
+use scope::{Refusal, Scope};
 use std::{
-    env,
-    fs::{create_dir, create_dir_all, remove_dir_all},
-    io,
-    path::PathBuf,
+    collections::HashSet,
+    path::Path,
     process::exit,
+    sync::{Arc, Mutex},
+    thread,
+    time::Duration,
 };
-use url::{Host, ParseError};
+use targets::Target;
 use which::which;
+mod chain;
+mod cli;
+mod config;
+mod doctor;
 mod engine;
 mod logger;
+mod manifest;
+mod nvd;
+mod output;
+mod report;
 mod scans;
-/**
+mod scheduler;
+mod scope;
+mod targets;
+/*
 
 So what we're doing is I'm creating a struct with all Scantypes in them. Nmap, Feroxbuster, etc.
 
@@ -21,65 +43,432 @@ These triggers are typically ports that are discovered to be open. If a scantype
 */
 
 fn main() {
-    print_banner();
-    let args: Vec<String> = env::args().collect();
-    //Not enough args. This will probably be replaced by code to configure the 'Clap' crate.
-    if args.len() < 2 {
-        terminate("No target provided. Usage: tartaros <ip|hostname> (make sure you DON'T specify a protocol such as http://*)");
+    let cli = Cli::parse_args();
+    logger::set_verbosity(cli.quiet, cli.verbose);
+    if !logger::is_quiet() {
+        print_banner();
     }
 
-    //We exit if the target can not be parsed to a valid Host.
-    if let Ok(target) = parse_target(&args[1]) {
-        if !nmap_is_installed() {
-            //Nmap is required for this program to run. If it's not installed, exit.
-            terminate(
-                "Nmap is not installed, but it is required. Install it with: sudo apt install nmap",
-            );
-        } else {
-            if let Ok(output_dir) = create_output_dir(&target) {
-                engine::run(target, output_dir);
-                // if let Err(_) = remove_dir_all(output_dir){
-                //     terminate("Unable to delete the temporary output directory '.tartaros_temp'. Please try to do so manually.");
-                // }
-            } else {
-                terminate("Unable to create an output directory for storing results. Please change your working directory or run this program as a high-privileged user.");
+    match cli.command {
+        Command::Scan(args) => scan(*args),
+        Command::Report(args) => {
+            let run_dir = output::resolve_run_dir(&args.run_dir);
+            let result = match args.format {
+                ReportFormat::Table => report::print_report(&run_dir),
+                ReportFormat::Markdown => {
+                    let notes_dir = args
+                        .output
+                        .unwrap_or_else(|| run_dir.join(report::markdown::NOTES_DIR));
+                    report::markdown::write_notes(&run_dir, &notes_dir).map(|written| {
+                        logger::print_ok(&format!(
+                            "Wrote {} notes to {}",
+                            written.len(),
+                            notes_dir.display()
+                        ))
+                    })
+                }
+                ReportFormat::Html => {
+                    let path = args
+                        .output
+                        .unwrap_or_else(|| run_dir.join(report::html::HTML_REPORT));
+                    report::html::write_html(&run_dir, &path).map(|()| {
+                        logger::print_ok(&format!("Wrote the HTML report to {}", path.display()))
+                    })
+                }
+            };
+            if let Err(err) = result {
+                terminate(&format!(
+                    "Unable to read results from {}: {}",
+                    args.run_dir.display(),
+                    err
+                ));
             }
         }
-    } else {
-        terminate("Unable to parse target string. Please make sure that you're not using any protocol specifiers (such as 'http://').")
+        Command::Diff(args) => {
+            if let Err(err) = report::print_diff(
+                &output::resolve_run_dir(&args.old_run_dir),
+                &output::resolve_run_dir(&args.new_run_dir),
+            ) {
+                terminate(&format!("Unable to read results: {}", err));
+            }
+        }
+        Command::Doctor => {
+            if !doctor::run() {
+                exit(1);
+            }
+        }
+        Command::NvdImport(args) => nvd_import(args),
     }
 }
 
-/// Parses the target to a Host.
-pub fn parse_target(target_raw: &str) -> Result<String, ParseError> {
-    let target = Host::parse(target_raw)?;
-    Ok(target.to_string())
+fn scan(args: ScanArgs) {
+    //Invalid trigger rules are a user error, so refuse to start instead of silently skipping them.
+    let config = match Config::load() {
+        Ok(config) => config,
+        Err(err) => terminate(&err.to_string()),
+    };
+
+    let profile = args
+        .profile
+        .as_ref()
+        .map(|name| match config.profiles.get(name) {
+            Some(profile) => profile,
+            None => {
+                let mut names: Vec<&String> = config.profiles.keys().collect();
+                names.sort();
+                terminate(&format!(
+                    "Unknown profile '{}'. Available profiles: {}",
+                    name,
+                    names
+                        .iter()
+                        .map(|name| name.as_str())
+                        .collect::<Vec<_>>()
+                        .join(", ")
+                ))
+            }
+        });
+
+    if let Some(scanner) = args
+        .enable
+        .iter()
+        .chain(&args.disable)
+        .find(|scanner| !SCANNERS.contains(&scanner.as_str()))
+    {
+        Cli::command_error(
+            ErrorKind::InvalidValue,
+            &format!(
+                "Unknown scanner '{}', expected one of: {}",
+                scanner,
+                SCANNERS.join(", ")
+            ),
+        );
+    }
+    if let Some((tool, _)) = args
+        .tool_limit
+        .iter()
+        .find(|(tool, _)| !LIMITED_TOOLS.contains(&tool.as_str()))
+    {
+        Cli::command_error(
+            ErrorKind::InvalidValue,
+            &format!(
+                "Unknown tool '{}' in --tool-limit, expected one of: {}",
+                tool,
+                LIMITED_TOOLS.join(", ")
+            ),
+        );
+    }
+    if args.concurrency == Some(0)
+        || args.tool_limit.iter().any(|(_, limit)| *limit == 0)
+        || args.host_concurrency == Some(0)
+        || args.timeout == Some(0)
+        || args.udp_top_ports == Some(0)
+    {
+        Cli::command_error(
+            ErrorKind::InvalidValue,
+            "--concurrency, --tool-limit, --host-concurrency, --timeout and --udp-top-ports must be at least 1",
+        );
+    }
+
+    //--enable is an allow-list and wins over everything. Otherwise the profile and --disable add up.
+    let disabled_scanners: Vec<String> = if !args.enable.is_empty() {
+        SCANNERS
+            .iter()
+            .filter(|scanner| !args.enable.iter().any(|enabled| enabled == *scanner))
+            .map(|scanner| scanner.to_string())
+            .collect()
+    } else {
+        profile
+            .map(|profile| profile.disable.clone())
+            .unwrap_or_default()
+            .into_iter()
+            .chain(args.disable.clone())
+            .collect()
+    };
+
+    let mut scheduler_config = SchedulerConfig::default();
+    if let Some(concurrency) = args
+        .concurrency
+        .or_else(|| profile.and_then(|profile| profile.concurrency))
+    {
+        scheduler_config.max_concurrent = concurrency;
+    }
+    //The profile's limits replace the built-in ones tool by tool, the command line's replace both.
+    let profile_limits = profile.map(|profile| profile.tool_limits.clone());
+    scheduler_config.tool_limits.extend(
+        profile_limits
+            .into_iter()
+            .flatten()
+            .chain(args.tool_limit.clone()),
+    );
+
+    let scope = match &args.scope {
+        Some(path) => match Scope::from_file(path) {
+            Ok(scope) => scope,
+            Err(err) => terminate(&err),
+        },
+        None => Scope::default(),
+    };
+
+    //An index that was asked for has to load. Without one there's simply no offline CVE matching.
+    let nvd_index = match &args.nvd_index {
+        Some(path) => Some(path.clone()),
+        None => NvdIndex::default_path().filter(|path| path.exists()),
+    }
+    .filter(|_| !disabled_scanners.iter().any(|scanner| scanner == "nvd"))
+    .map(|path| match NvdIndex::load(&path) {
+        Ok(index) => {
+            logger::print_ok(&format!(
+                "Loaded {} CVEs from the NVD index {}",
+                index.cves.len(),
+                path.display()
+            ));
+            Arc::new(index)
+        }
+        Err(err) => terminate(&err),
+    });
+
+    let options = EngineOptions {
+        scan_triggers: ScanTriggers::from_config(&config),
+        scheduler_config,
+        disabled_scanners,
+        timeout: args
+            .timeout
+            .or_else(|| profile.and_then(|profile| profile.timeout))
+            .map(Duration::from_secs),
+        pn_mode: if args.no_ping {
+            PnMode::Always
+        } else {
+            args.pn_mode
+                .or_else(|| profile.and_then(|profile| profile.pn_mode))
+                .unwrap_or_default()
+        },
+        udp_top_ports: args
+            .udp_top_ports
+            .or_else(|| profile.and_then(|profile| profile.udp_top_ports)),
+        scope,
+        nvd_index,
+    };
+
+    let host_concurrency = args
+        .host_concurrency
+        .or_else(|| profile.and_then(|profile| profile.host_concurrency))
+        .unwrap_or(1);
+
+    //We exit if any target can not be parsed, before anything is scanned.
+    let targets = match targets::collect(&args.targets, &args.target_files) {
+        Ok(targets) => targets,
+        Err(err) => terminate(&err),
+    };
+    //A target list that doesn't match the scope is a mistake somewhere, so nothing is scanned until it's fixed.
+    let out_of_scope: Vec<String> = targets
+        .iter()
+        .filter_map(|target| {
+            let result = match target {
+                Target::Range(range) => options.scope.check_range(range).map(|_| ()),
+                _ => options.scope.check(&target.host()),
+            };
+            result
+                .err()
+                .map(|reason| format!("{} ({})", target.name(), reason))
+        })
+        .collect();
+    if !out_of_scope.is_empty() {
+        terminate(&format!(
+            "Refusing to scan targets that are out of scope: {}",
+            out_of_scope.join(", ")
+        ));
+    }
+
+    if !nmap_is_installed() {
+        //Nmap is required for this program to run. If it's not installed, exit.
+        terminate(
+            "Nmap is not installed, but it is required. Install it with: sudo apt install nmap",
+        );
+    }
+
+    let base_dir = match output::base_dir(args.output_dir.as_deref()) {
+        Ok(base_dir) => base_dir,
+        Err(err) => terminate(&format!(
+            "Unable to determine the output directory: {}",
+            err
+        )),
+    };
+
+    //Ranges are swept for live hosts first, those join the targets that were given directly.
+    let mut hosts = Vec::new();
+    for target in targets {
+        match target {
+            Target::Range(range) => hosts.extend(sweep(&range, &base_dir, &options)),
+            target => hosts.push(target),
+        }
+    }
+    let mut seen = HashSet::new();
+    hosts.retain(|host| seen.insert(host.clone()));
+    if hosts.is_empty() {
+        logger::print_warn("No live hosts to scan. Terminating.");
+        return;
+    }
+
+    //Every worker takes the next target from the queue until it's empty.
+    let queue = Mutex::new(hosts.into_iter());
+    thread::scope(|scope| {
+        for _ in 0..host_concurrency {
+            scope.spawn(|| loop {
+                //The lock is released before the scan, other workers can take hosts in the meantime.
+                let next = queue.lock().map(|mut queue| queue.next());
+                match next {
+                    Ok(Some(target)) => scan_host(target, &base_dir, &options),
+                    _ => break,
+                }
+            });
+        }
+    });
 }
 
-/// Checks whether Nmap is installed by using 'which'.
-fn nmap_is_installed() -> bool {
-    if let Ok(_) = which("nmap") {
-        return true;
+///Runs the complete pipeline against a single host, in a run directory of its own.
+fn scan_host(target: Target, base_dir: &Path, options: &EngineOptions) {
+    let run_dir = match output::create_run_dir(base_dir, &target.dir_name()) {
+        Ok(run_dir) => run_dir,
+        Err(err) => {
+            logger::print_err(&format!("Unable to create an output directory for storing results of {} in {}: {}. Please use --output-dir.", target.name(), base_dir.display(), err));
+            return;
+        }
+    };
+    logger::print_ok(&format!("Storing results in {}", run_dir.display()));
+
+    //The manifest is written up front as well, so an interrupted run still says what it was.
+    //Reports group everything by host, the full target is in the recorded command line.
+    let mut manifest = RunManifest::start(&target.host(), options.pn_mode);
+    write_manifest(&manifest, &run_dir);
+    let run_results = engine::run(&target, run_dir.clone(), options);
+    manifest.pn_used = run_results.pn_used;
+    manifest.refused = run_results.refused.clone();
+    manifest.finish(&run_dir);
+    write_manifest(&manifest, &run_dir);
+    let run_report = RunReport::new(&manifest, run_results);
+    match run_report.write(&run_dir) {
+        Ok(()) => logger::print_ok(&format!(
+            "JSON report written to {}",
+            run_dir.join(report::json::REPORT).display()
+        )),
+        Err(err) => logger::print_err(&format!("Unable to write the JSON report: {}", err)),
     }
+}
 
-    false
+///Runs host discovery over a range and returns the addresses of the live hosts.
+///The sweep gets a run directory of its own, so its commands are on record too.
+fn sweep(range: &str, base_dir: &Path, options: &EngineOptions) -> Vec<Target> {
+    let target = Target::Range(range.to_string());
+    let run_dir = match output::create_run_dir(base_dir, &target.dir_name()) {
+        Ok(run_dir) => run_dir,
+        Err(err) => {
+            logger::print_err(&format!("Unable to create an output directory for storing results of {} in {}: {}. Please use --output-dir.", range, base_dir.display(), err));
+            return Vec::new();
+        }
+    };
+
+    //The denied parts of the range are never touched, not even by the sweep.
+    let excludes = match options.scope.check_range(range) {
+        Ok(excludes) => excludes,
+        Err(reason) => {
+            logger::print_err(&format!(
+                "Not sweeping {}: out of scope ({}).",
+                range, reason
+            ));
+            return Vec::new();
+        }
+    };
+    //Hosts that drop pings would never show up, so with -Pn every address in the range counts as live.
+    let list_only = options.pn_mode == PnMode::Always;
+    let mut manifest = RunManifest::start(range, options.pn_mode);
+    let ping_sweep = PingSweep::new(run_dir.clone(), &[range.to_string()], &excludes, list_only)
+        .with_timeout(options.timeout);
+    let hosts = match ping_sweep.run() {
+        Ok(hosts) => {
+            manifest.pn_used = Some(list_only);
+            let mut live_hosts = Vec::new();
+            for addr in hosts
+                .iter()
+                .filter_map(|host| {
+                    host.addresses
+                        .iter()
+                        .find(|address| address.addrtype != "mac")
+                })
+                .map(|address| address.addr.clone())
+            {
+                match options.scope.check(&addr) {
+                    Ok(()) => live_hosts.push(Target::Host(addr)),
+                    Err(reason) => {
+                        logger::print_warn(&format!(
+                            "Not scanning {}: out of scope ({}).",
+                            addr, reason
+                        ));
+                        manifest.refused.push(Refusal {
+                            tool: String::from("nmap"),
+                            target: addr,
+                            reason,
+                        });
+                    }
+                }
+            }
+            live_hosts
+        }
+        Err(err) => {
+            logger::print_err(&format!("Host discovery of {} failed: {}", range, err));
+            Vec::new()
+        }
+    };
+    manifest.finish(&run_dir);
+    write_manifest(&manifest, &run_dir);
+    hosts
 }
 
-/// Attempts to create a directory 'tartaros_results_{target}' in the current working directory for storing intermediate results of the separate scans that run.
-fn create_output_dir(target: &str) -> Result<PathBuf, io::Error> {
-    let home_dir = std::env::var("HOME").unwrap(); //Is always set as env variable because it's required by the POSIX spec (https://pubs.opengroup.org/onlinepubs/009695399/basedefs/xbd_chap08.html)
-    let path = PathBuf::from(home_dir).join("tartaros").join(target);
-    if let Err(_) = create_dir_all(&path) {
-        remove_dir_all(&path)?;
-        create_dir_all(&path)?;
+///Adds the feeds to the index one by one. A feed that fails to import stops the import, the index is only written when all succeeded.
+fn nvd_import(args: NvdImportArgs) {
+    let Some(path) = args.index.or_else(NvdIndex::default_path) else {
+        terminate("Unable to determine where to store the NVD index, use --index");
+    };
+    let mut index = if path.exists() {
+        match NvdIndex::load(&path) {
+            Ok(index) => index,
+            Err(err) => terminate(&err),
+        }
+    } else {
+        NvdIndex::default()
+    };
+
+    for feed in &args.feeds {
+        match index.import(feed) {
+            Ok(count) => {
+                logger::print_ok(&format!("Imported {} CVEs from {}", count, feed.display()))
+            }
+            Err(err) => terminate(&err),
+        }
     }
+    if let Err(err) = index.save(&path) {
+        terminate(&err);
+    }
+    logger::print_ok(&format!(
+        "The NVD index {} now holds {} CVEs affecting {} products.",
+        path.display(),
+        index.cves.len(),
+        index.products.len()
+    ));
+}
 
-    //Should always be a valid path since we return early if creation fails.
-    Ok(path)
+/// Checks whether Nmap is installed by using 'which'.
+fn nmap_is_installed() -> bool {
+    which("nmap").is_ok()
+}
+
+fn write_manifest(manifest: &RunManifest, run_dir: &Path) {
+    if let Err(err) = manifest.write(run_dir) {
+        logger::print_err(&format!("Unable to write the run manifest: {}", err));
+    }
 }
 
 /// Outputs an error message and terminates the process.
-fn terminate(msg: &str) {
+fn terminate(msg: &str) -> ! {
     logger::print_err(&format!("Error: {}", msg));
     exit(1);
 }
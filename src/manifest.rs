@@ -0,0 +1,78 @@
+use chrono::{DateTime, Utc};
+use serde::{Deserialize, Serialize};
+use std::{collections::BTreeMap, fs, io, path::Path};
+
+use crate::{
+    config::PnMode,
+    scans::process::{self, ExecutedCommand},
+    scope::Refusal,
+};
+
+///File in every run directory that describes the run.
+pub const MANIFEST: &str = "manifest.json";
+
+///Tools whose versions are recorded, so results can be reproduced later.
+const TOOLS: &[&str] = &[
+    "nmap",
+    "rustscan",
+    "nuclei",
+    "feroxbuster",
+    "smbclient",
+    "curl",
+];
+
+#[derive(Serialize, Deserialize, Debug)]
+pub struct RunManifest {
+    pub target: String,
+    pub started: DateTime<Utc>,
+    pub finished: Option<DateTime<Utc>>,
+    ///The full command line Tartaros was started with.
+    pub args: Vec<String>,
+    ///`None` for tools that are not installed.
+    pub tool_versions: BTreeMap<String, Option<String>>,
+    ///When the run was allowed to pass -Pn to Nmap.
+    #[serde(default)]
+    pub pn_mode: PnMode,
+    ///Whether the Nmap results came from a scan with -Pn. `None` if Nmap didn't finish.
+    #[serde(default)]
+    pub pn_used: Option<bool>,
+    ///Scans that were not run because their target is out of scope.
+    #[serde(default)]
+    pub refused: Vec<Refusal>,
+    pub commands: Vec<ExecutedCommand>,
+}
+
+impl RunManifest {
+    pub fn start(target: &str, pn_mode: PnMode) -> RunManifest {
+        RunManifest {
+            target: target.to_string(),
+            started: Utc::now(),
+            finished: None,
+            args: std::env::args().collect(),
+            tool_versions: TOOLS
+                .iter()
+                .map(|tool| (tool.to_string(), process::tool_version(tool)))
+                .collect(),
+            pn_mode,
+            pn_used: None,
+            refused: Vec::new(),
+            commands: Vec::new(),
+        }
+    }
+
+    ///Marks the run as finished and collects the commands the scans recorded in the run directory.
+    pub fn finish(&mut self, run_dir: &Path) {
+        self.finished = Some(Utc::now());
+        self.commands = process::executed_commands(run_dir);
+    }
+
+    pub fn write(&self, run_dir: &Path) -> io::Result<()> {
+        let json = serde_json::to_string_pretty(self)?;
+        fs::write(run_dir.join(MANIFEST), json)
+    }
+
+    pub fn load(run_dir: &Path) -> io::Result<RunManifest> {
+        let json = fs::read_to_string(run_dir.join(MANIFEST))?;
+        Ok(serde_json::from_str(&json)?)
+    }
+}
@@ -0,0 +1,709 @@
+use chrono::{DateTime, Utc};
+use serde::{Deserialize, Serialize};
+use std::{
+    cmp::Ordering,
+    collections::{BTreeMap, HashSet},
+    fs,
+    path::{Path, PathBuf},
+};
+
+use crate::output;
+
+///File name of the index in the data directory, next to the results.
+pub const NVD_INDEX: &str = "nvd_index.json";
+///Version suffixes that come before the release itself, e.g. 2.4.0-rc1 < 2.4.0.
+///Single letters aren't, OpenSSL's 1.1.1a comes after 1.1.1.
+const PRE_RELEASES: &[&str] = &["alpha", "beta", "rc", "pre", "preview", "dev"];
+
+///A local copy of the parts of the NVD that matter for matching services, built from the NVD's JSON feeds.
+///Scans only ever read it, so it works without network access.
+#[derive(Serialize, Deserialize, Default)]
+pub struct NvdIndex {
+    pub feeds: Vec<ImportedFeed>,
+    pub cves: BTreeMap<String, IndexedCve>,
+    ///The vulnerable CPEs of every CVE, by 'vendor:product'.
+    pub products: BTreeMap<String, Vec<CpeCriteria>>,
+}
+
+#[derive(Serialize, Deserialize)]
+pub struct ImportedFeed {
+    ///File name of the feed, e.g. 'nvdcve-2.0-2024.json'.
+    pub file: String,
+    pub imported_at: DateTime<Utc>,
+    pub cves: usize,
+}
+
+#[derive(Serialize, Deserialize)]
+pub struct IndexedCve {
+    ///The newest CVSS version's base score.
+    pub cvss: Option<f32>,
+    ///e.g. '3.1'.
+    pub cvss_version: Option<String>,
+    pub published: String,
+    pub description: String,
+}
+
+///A vulnerable CPE of a CVE, optionally limited to a range of versions.
+#[derive(Serialize, Deserialize, Clone)]
+pub struct CpeCriteria {
+    pub cve: String,
+    ///CPE 2.3, e.g. 'cpe:2.3:a:openbsd:openssh:*:*:*:*:*:*:*:*'.
+    pub criteria: String,
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub start_including: Option<String>,
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub start_excluding: Option<String>,
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub end_including: Option<String>,
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub end_excluding: Option<String>,
+}
+
+///A CVE that likely affects a service, and why.
+pub struct Candidate<'a> {
+    pub id: &'a str,
+    pub cve: &'a IndexedCve,
+    pub criteria: &'a CpeCriteria,
+    ///e.g. '8.9p1 >= 6.2 and < 9.3p2'.
+    pub reason: String,
+}
+
+///Both the legacy 1.1 feeds (CVE_Items) and the 2.0 feeds (vulnerabilities) are supported.
+#[derive(Deserialize)]
+struct Feed {
+    #[serde(rename = "CVE_Items")]
+    legacy_items: Option<Vec<LegacyItem>>,
+    vulnerabilities: Option<Vec<Vulnerability>>,
+}
+
+#[derive(Deserialize)]
+struct LegacyItem {
+    cve: LegacyCve,
+    #[serde(default)]
+    configurations: LegacyConfigurations,
+    #[serde(default)]
+    impact: LegacyImpact,
+    #[serde(rename = "publishedDate", default)]
+    published_date: String,
+}
+
+#[derive(Deserialize)]
+struct LegacyCve {
+    #[serde(rename = "CVE_data_meta")]
+    meta: LegacyMeta,
+    #[serde(default)]
+    description: LegacyDescription,
+}
+
+#[derive(Deserialize)]
+struct LegacyMeta {
+    #[serde(rename = "ID")]
+    id: String,
+}
+
+#[derive(Deserialize, Default)]
+struct LegacyDescription {
+    #[serde(default)]
+    description_data: Vec<Description>,
+}
+
+#[derive(Deserialize, Default)]
+struct LegacyConfigurations {
+    #[serde(default)]
+    nodes: Vec<LegacyNode>,
+}
+
+#[derive(Deserialize)]
+struct LegacyNode {
+    #[serde(default)]
+    children: Vec<LegacyNode>,
+    #[serde(default)]
+    cpe_match: Vec<CpeMatch>,
+}
+
+#[derive(Deserialize, Default)]
+struct LegacyImpact {
+    #[serde(rename = "baseMetricV3")]
+    v3: Option<LegacyMetricV3>,
+    #[serde(rename = "baseMetricV2")]
+    v2: Option<LegacyMetricV2>,
+}
+
+#[derive(Deserialize)]
+struct LegacyMetricV3 {
+    #[serde(rename = "cvssV3")]
+    cvss: CvssData,
+}
+
+#[derive(Deserialize)]
+struct LegacyMetricV2 {
+    #[serde(rename = "cvssV2")]
+    cvss: CvssData,
+}
+
+#[derive(Deserialize)]
+struct Vulnerability {
+    cve: Cve,
+}
+
+#[derive(Deserialize)]
+struct Cve {
+    id: String,
+    #[serde(default)]
+    published: String,
+    #[serde(default)]
+    descriptions: Vec<Description>,
+    #[serde(default)]
+    metrics: Metrics,
+    #[serde(default)]
+    configurations: Vec<Configuration>,
+}
+
+#[derive(Deserialize, Default)]
+struct Metrics {
+    #[serde(rename = "cvssMetricV40", default)]
+    v40: Vec<Metric>,
+    #[serde(rename = "cvssMetricV31", default)]
+    v31: Vec<Metric>,
+    #[serde(rename = "cvssMetricV30", default)]
+    v30: Vec<Metric>,
+    #[serde(rename = "cvssMetricV2", default)]
+    v2: Vec<Metric>,
+}
+
+#[derive(Deserialize)]
+struct Metric {
+    #[serde(rename = "cvssData")]
+    cvss: CvssData,
+}
+
+#[derive(Deserialize)]
+struct Configuration {
+    #[serde(default)]
+    nodes: Vec<Node>,
+}
+
+#[derive(Deserialize)]
+struct Node {
+    ///Negated nodes list what is *not* affected.
+    #[serde(default)]
+    negate: bool,
+    #[serde(rename = "cpeMatch", default)]
+    cpe_match: Vec<CpeMatch>,
+}
+
+#[derive(Deserialize)]
+struct Description {
+    lang: String,
+    value: String,
+}
+
+#[derive(Deserialize)]
+struct CvssData {
+    #[serde(rename = "baseScore")]
+    base_score: f32,
+    #[serde(default)]
+    version: String,
+}
+
+#[derive(Deserialize)]
+struct CpeMatch {
+    #[serde(default)]
+    vulnerable: bool,
+    #[serde(alias = "cpe23Uri")]
+    criteria: String,
+    #[serde(rename = "versionStartIncluding")]
+    start_including: Option<String>,
+    #[serde(rename = "versionStartExcluding")]
+    start_excluding: Option<String>,
+    #[serde(rename = "versionEndIncluding")]
+    end_including: Option<String>,
+    #[serde(rename = "versionEndExcluding")]
+    end_excluding: Option<String>,
+}
+
+impl NvdIndex {
+    ///$XDG_DATA_HOME/tartaros/nvd_index.json, falling back to ~/.local/share/tartaros/nvd_index.json.
+    pub fn default_path() -> Option<PathBuf> {
+        output::base_dir(None)
+            .ok()
+            .map(|base_dir| base_dir.join(NVD_INDEX))
+    }
+
+    pub fn load(path: &Path) -> Result<NvdIndex, String> {
+        let contents = fs::read(path)
+            .map_err(|err| format!("Unable to read NVD index {}: {}", path.display(), err))?;
+        serde_json::from_slice(&contents)
+            .map_err(|err| format!("Unable to parse NVD index {}: {}", path.display(), err))
+    }
+
+    ///Writes to a temporary file first, an interrupted import never leaves a broken index behind.
+    pub fn save(&self, path: &Path) -> Result<(), String> {
+        let error = |err: &dyn std::fmt::Display| {
+            format!("Unable to write NVD index {}: {}", path.display(), err)
+        };
+        if let Some(parent) = path.parent() {
+            fs::create_dir_all(parent).map_err(|err| error(&err))?;
+        }
+        let temp_path = path.with_extension("json.tmp");
+        let contents = serde_json::to_vec(self).map_err(|err| error(&err))?;
+        fs::write(&temp_path, contents).map_err(|err| error(&err))?;
+        fs::rename(&temp_path, path).map_err(|err| error(&err))
+    }
+
+    ///Adds the CVEs of an NVD JSON feed, replacing what an earlier import said about them.
+    ///Returns the number of CVEs in the feed.
+    pub fn import(&mut self, path: &Path) -> Result<usize, String> {
+        let contents =
+            fs::read(path).map_err(|err| format!("Unable to read {}: {}", path.display(), err))?;
+        if contents.starts_with(&[0x1f, 0x8b]) {
+            return Err(format!(
+                "{} is gzip compressed, decompress it with gunzip first",
+                path.display()
+            ));
+        }
+        let feed: Feed = serde_json::from_slice(&contents)
+            .map_err(|err| format!("Unable to parse {}: {}", path.display(), err))?;
+
+        let entries: Vec<(String, IndexedCve, Vec<CpeMatch>)> =
+            match (feed.legacy_items, feed.vulnerabilities) {
+                (Some(items), _) => items.into_iter().map(legacy_entry).collect(),
+                (None, Some(vulnerabilities)) => vulnerabilities
+                    .into_iter()
+                    .map(|vulnerability| entry(vulnerability.cve))
+                    .collect(),
+                (None, None) => {
+                    return Err(format!(
+                        "{} is not an NVD JSON feed, it has neither CVE_Items nor vulnerabilities",
+                        path.display()
+                    ))
+                }
+            };
+
+        let ids: HashSet<&str> = entries.iter().map(|(id, _, _)| id.as_str()).collect();
+        for criteria in self.products.values_mut() {
+            criteria.retain(|criteria| !ids.contains(criteria.cve.as_str()));
+        }
+        let count = entries.len();
+        for (id, cve, matches) in entries {
+            for cpe_match in matches.into_iter().filter(|cpe_match| cpe_match.vulnerable) {
+                let Some(product) = cpe23_product(&cpe_match.criteria) else {
+                    continue;
+                };
+                self.products.entry(product).or_default().push(CpeCriteria {
+                    cve: id.clone(),
+                    criteria: cpe_match.criteria,
+                    start_including: cpe_match.start_including,
+                    start_excluding: cpe_match.start_excluding,
+                    end_including: cpe_match.end_including,
+                    end_excluding: cpe_match.end_excluding,
+                });
+            }
+            self.cves.insert(id, cve);
+        }
+        self.products.retain(|_, criteria| !criteria.is_empty());
+
+        let file = path
+            .file_name()
+            .map(|name| name.to_string_lossy().to_string())
+            .unwrap_or_default();
+        self.feeds.retain(|feed| feed.file != file);
+        self.feeds.push(ImportedFeed {
+            file,
+            imported_at: Utc::now(),
+            cves: count,
+        });
+        Ok(count)
+    }
+
+    ///The CVEs whose vulnerable CPEs cover this version of the product.
+    ///Without a vendor, every vendor's product of that name matches, which is less precise.
+    pub fn candidates(
+        &self,
+        vendor: Option<&str>,
+        product: &str,
+        version: &str,
+    ) -> Vec<Candidate<'_>> {
+        let criteria: Vec<&CpeCriteria> = match vendor {
+            Some(vendor) => self
+                .products
+                .get(&format!("{}:{}", vendor, product))
+                .into_iter()
+                .flatten()
+                .collect(),
+            None => self
+                .products
+                .iter()
+                .filter(|(key, _)| key.split_once(':').map(|(_, name)| name) == Some(product))
+                .flat_map(|(_, criteria)| criteria)
+                .collect(),
+        };
+        let mut candidates: Vec<Candidate> = Vec::new();
+        for criteria in criteria {
+            let Some(reason) = criteria.affects(version) else {
+                continue;
+            };
+            let Some(cve) = self.cves.get(&criteria.cve) else {
+                continue;
+            };
+            //A CVE often lists several ranges of the same product, one that matches is enough.
+            if candidates
+                .iter()
+                .any(|candidate| candidate.id == criteria.cve)
+            {
+                continue;
+            }
+            candidates.push(Candidate {
+                id: &criteria.cve,
+                cve,
+                criteria,
+                reason,
+            });
+        }
+        candidates
+    }
+}
+
+impl CpeCriteria {
+    ///Explains why the version is affected, or returns `None` if it isn't.
+    fn affects(&self, version: &str) -> Option<String> {
+        let fields = cpe23_fields(&self.criteria);
+        let exact = fields.get(5).copied().unwrap_or("*");
+        let update = fields.get(6).copied().unwrap_or("*");
+        match exact {
+            //Not applicable, there are no versions to compare.
+            "-" => return None,
+            "*" => {}
+            exact => {
+                //The NVD often splits e.g. OpenSSH's '8.9p1' into the version '8.9' and the update 'p1'.
+                //'*' is any update, including none. '-' is explicitly no update.
+                if update == "*" && is_update_of(version, exact) {
+                    return Some(format!(
+                        "{} is an update of {}, which is listed as affected",
+                        version, exact
+                    ));
+                }
+                let exact = match update {
+                    "*" | "-" => exact.to_string(),
+                    update => format!("{}{}", exact, update),
+                };
+                return (compare_versions(version, &exact) == Ordering::Equal)
+                    .then(|| format!("{} is listed as affected", exact));
+            }
+        }
+
+        let mut reasons = Vec::new();
+        let bounds = [
+            (
+                &self.start_including,
+                ">=",
+                [Ordering::Greater, Ordering::Equal],
+            ),
+            (
+                &self.start_excluding,
+                ">",
+                [Ordering::Greater, Ordering::Greater],
+            ),
+            (&self.end_including, "<=", [Ordering::Less, Ordering::Equal]),
+            (&self.end_excluding, "<", [Ordering::Less, Ordering::Less]),
+        ];
+        for (bound, operator, allowed) in bounds {
+            if let Some(bound) = bound {
+                if !allowed.contains(&compare_versions(version, bound)) {
+                    return None;
+                }
+                reasons.push(format!("{} {}", operator, bound));
+            }
+        }
+        if reasons.is_empty() {
+            Some(String::from("all versions are listed as affected"))
+        } else {
+            Some(format!("{} {}", version, reasons.join(" and ")))
+        }
+    }
+}
+
+fn legacy_entry(item: LegacyItem) -> (String, IndexedCve, Vec<CpeMatch>) {
+    let metric = item
+        .impact
+        .v3
+        .map(|metric| metric.cvss)
+        .or(item.impact.v2.map(|metric| metric.cvss));
+    let mut matches = Vec::new();
+    let mut nodes = item.configurations.nodes;
+    while let Some(node) = nodes.pop() {
+        matches.extend(node.cpe_match);
+        nodes.extend(node.children);
+    }
+    (
+        item.cve.meta.id,
+        IndexedCve {
+            cvss: metric.as_ref().map(|cvss| cvss.base_score),
+            cvss_version: metric.map(|cvss| cvss.version),
+            published: item.published_date,
+            description: english(item.cve.description.description_data),
+        },
+        matches,
+    )
+}
+
+fn entry(cve: Cve) -> (String, IndexedCve, Vec<CpeMatch>) {
+    let metrics = cve.metrics;
+    let metric = [metrics.v40, metrics.v31, metrics.v30, metrics.v2]
+        .into_iter()
+        .find_map(|metrics| metrics.into_iter().next());
+    let matches = cve
+        .configurations
+        .into_iter()
+        .flat_map(|configuration| configuration.nodes)
+        .filter(|node| !node.negate)
+        .flat_map(|node| node.cpe_match)
+        .collect();
+    (
+        cve.id,
+        IndexedCve {
+            cvss: metric.as_ref().map(|metric| metric.cvss.base_score),
+            cvss_version: metric.map(|metric| metric.cvss.version),
+            published: cve.published,
+            description: english(cve.descriptions),
+        },
+        matches,
+    )
+}
+
+fn english(descriptions: Vec<Description>) -> String {
+    descriptions
+        .into_iter()
+        .find(|description| description.lang == "en")
+        .map(|description| description.value)
+        .unwrap_or_default()
+}
+
+///Splits a CPE 2.3 string into its fields. Colons escaped with a backslash don't split.
+fn cpe23_fields(cpe: &str) -> Vec<&str> {
+    let mut fields = Vec::new();
+    let mut start = 0;
+    let mut escaped = false;
+    for (i, c) in cpe.char_indices() {
+        match c {
+            _ if escaped => escaped = false,
+            '\\' => escaped = true,
+            ':' => {
+                fields.push(&cpe[start..i]);
+                start = i + 1;
+            }
+            _ => {}
+        }
+    }
+    fields.push(&cpe[start..]);
+    fields
+}
+
+///'cpe:2.3:a:openbsd:openssh:...' -> 'openbsd:openssh'.
+fn cpe23_product(cpe: &str) -> Option<String> {
+    let fields = cpe23_fields(cpe);
+    Some(format!("{}:{}", fields.get(3)?, fields.get(4)?))
+}
+
+///Compares versions the way most projects number them: numbers numerically, letters alphabetically.
+///'8.9p1' > '8.9', but '2.4.0-rc1' < '2.4.0'.
+pub fn compare_versions(a: &str, b: &str) -> Ordering {
+    let (a, b) = (version_parts(a), version_parts(b));
+    for i in 0..a.len().max(b.len()) {
+        let ordering = match (a.get(i), b.get(i)) {
+            (Some(a), Some(b)) => match (a.parse::<u64>(), b.parse::<u64>()) {
+                (Ok(a), Ok(b)) => a.cmp(&b),
+                //A number comes after a letter, 1.0.1 > 1.0a.
+                (Ok(_), Err(_)) => Ordering::Greater,
+                (Err(_), Ok(_)) => Ordering::Less,
+                (Err(_), Err(_)) => a.cmp(b),
+            },
+            (Some(extra), None) => extra_part(extra),
+            (None, Some(extra)) => extra_part(extra).reverse(),
+            (None, None) => Ordering::Equal,
+        };
+        if ordering != Ordering::Equal {
+            return ordering;
+        }
+    }
+    Ordering::Equal
+}
+
+///Whether a version with this extra part comes after the version without it. 1.0.0 is the same as 1.0.
+fn extra_part(extra: &str) -> Ordering {
+    if extra.parse::<u64>() == Ok(0) {
+        Ordering::Equal
+    } else if PRE_RELEASES.contains(&extra) {
+        Ordering::Less
+    } else {
+        Ordering::Greater
+    }
+}
+
+///Whether the version is the base version plus an update suffix, e.g. '8.9p1' for '8.9'. '8.9.1' is a version of its own.
+fn is_update_of(version: &str, base: &str) -> bool {
+    let (version, base) = (version_parts(version), version_parts(base));
+    version.len() > base.len()
+        && version
+            .iter()
+            .zip(&base)
+            .all(|(a, b)| compare_versions(a, b) == Ordering::Equal)
+        && version[base.len()].starts_with(|c: char| c.is_ascii_alphabetic())
+}
+
+///'2.4.0-rc1' -> ['2', '4', '0', 'rc', '1'].
+fn version_parts(version: &str) -> Vec<String> {
+    let mut parts: Vec<String> = Vec::new();
+    let mut last_digit = None;
+    for c in version.to_lowercase().chars() {
+        if !c.is_ascii_alphanumeric() {
+            last_digit = None;
+            continue;
+        }
+        match parts.last_mut() {
+            Some(part) if last_digit == Some(c.is_ascii_digit()) => part.push(c),
+            _ => parts.push(c.to_string()),
+        }
+        last_digit = Some(c.is_ascii_digit());
+    }
+    parts
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn criteria(cpe_version: &str, update: &str) -> CpeCriteria {
+        CpeCriteria {
+            cve: String::from("CVE-2023-38408"),
+            criteria: format!(
+                "cpe:2.3:a:openbsd:openssh:{}:{}:*:*:*:*:*:*",
+                cpe_version, update
+            ),
+            start_including: None,
+            start_excluding: None,
+            end_including: None,
+            end_excluding: None,
+        }
+    }
+
+    #[test]
+    fn compares_numbers_numerically() {
+        assert_eq!(compare_versions("1.10", "1.9"), Ordering::Greater);
+        assert_eq!(compare_versions("2.4.2", "2.4.10"), Ordering::Less);
+        assert_eq!(compare_versions("1.2", "1.2"), Ordering::Equal);
+    }
+
+    #[test]
+    fn trailing_zero_is_the_same_version() {
+        assert_eq!(compare_versions("1.0.0", "1.0"), Ordering::Equal);
+        assert_eq!(compare_versions("1.0", "1.0.0"), Ordering::Equal);
+        assert_eq!(compare_versions("1.0.1", "1.0"), Ordering::Greater);
+    }
+
+    #[test]
+    fn pre_releases_come_before_the_release() {
+        assert_eq!(compare_versions("2.4.0-rc1", "2.4.0"), Ordering::Less);
+        assert_eq!(compare_versions("2.4.0", "2.4.0-beta"), Ordering::Greater);
+        assert_eq!(
+            compare_versions("2.4.0-alpha", "2.4.0-beta"),
+            Ordering::Less
+        );
+        assert_eq!(compare_versions("2.4.0-rc1", "2.4.0-rc2"), Ordering::Less);
+    }
+
+    #[test]
+    fn updates_come_after_the_release() {
+        assert_eq!(compare_versions("8.9p1", "8.9"), Ordering::Greater);
+        assert_eq!(compare_versions("8.9p1", "8.9p2"), Ordering::Less);
+        assert_eq!(compare_versions("9.3p2", "9.3p1"), Ordering::Greater);
+        assert_eq!(compare_versions("8.9P1", "8.9p1"), Ordering::Equal);
+    }
+
+    #[test]
+    fn exact_version_with_any_update() {
+        let criteria = criteria("8.9", "*");
+        assert!(criteria.affects("8.9").is_some());
+        assert!(criteria.affects("8.9p1").is_some());
+        assert!(criteria.affects("8.9.1").is_none());
+        assert!(criteria.affects("8.8p1").is_none());
+        assert!(criteria.affects("9.0").is_none());
+    }
+
+    #[test]
+    fn exact_version_without_update() {
+        let criteria = criteria("8.9", "-");
+        assert!(criteria.affects("8.9").is_some());
+        assert!(criteria.affects("8.9.0").is_some());
+        assert!(criteria.affects("8.9p1").is_none());
+    }
+
+    #[test]
+    fn exact_version_with_update() {
+        let criteria = criteria("8.9", "p1");
+        assert!(criteria.affects("8.9p1").is_some());
+        assert!(criteria.affects("8.9p2").is_none());
+        assert!(criteria.affects("8.9").is_none());
+    }
+
+    #[test]
+    fn not_applicable_version_never_matches() {
+        assert!(criteria("-", "*").affects("8.9p1").is_none());
+    }
+
+    #[test]
+    fn start_including() {
+        let mut criteria = criteria("*", "*");
+        criteria.start_including = Some(String::from("6.2"));
+        assert!(criteria.affects("6.2").is_some());
+        assert!(criteria.affects("8.9p1").is_some());
+        assert!(criteria.affects("6.1").is_none());
+    }
+
+    #[test]
+    fn start_excluding() {
+        let mut criteria = criteria("*", "*");
+        criteria.start_excluding = Some(String::from("6.2"));
+        assert!(criteria.affects("6.2").is_none());
+        assert!(criteria.affects("6.2p1").is_some());
+        assert!(criteria.affects("6.1").is_none());
+    }
+
+    #[test]
+    fn end_including() {
+        let mut criteria = criteria("*", "*");
+        criteria.end_including = Some(String::from("9.3p2"));
+        assert!(criteria.affects("9.3p2").is_some());
+        assert!(criteria.affects("9.3").is_some());
+        assert!(criteria.affects("9.4").is_none());
+    }
+
+    #[test]
+    fn end_excluding() {
+        let mut criteria = criteria("*", "*");
+        criteria.end_excluding = Some(String::from("9.3p2"));
+        assert!(criteria.affects("9.3p1").is_some());
+        assert!(criteria.affects("9.3p2").is_none());
+        assert!(criteria.affects("9.4").is_none());
+    }
+
+    #[test]
+    fn range_reason_lists_both_bounds() {
+        let mut criteria = criteria("*", "*");
+        criteria.start_including = Some(String::from("6.2"));
+        criteria.end_excluding = Some(String::from("9.3p2"));
+        assert_eq!(
+            criteria.affects("8.9p1"),
+            Some(String::from("8.9p1 >= 6.2 and < 9.3p2"))
+        );
+        assert!(criteria.affects("9.3p2").is_none());
+    }
+
+    #[test]
+    fn unbounded_wildcard_affects_all_versions() {
+        assert_eq!(
+            criteria("*", "*").affects("1.0"),
+            Some(String::from("all versions are listed as affected"))
+        );
+    }
+}
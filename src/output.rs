@@ -0,0 +1,90 @@
+use chrono::Local;
+use std::{
+    fs, io,
+    path::{Path, PathBuf},
+};
+
+///Name of the pointer to the most recent run in a target's directory.
+pub const LATEST: &str = "latest";
+
+///Directory under which the results of every target are stored.
+///--output-dir wins, then $XDG_DATA_HOME/tartaros and finally ~/.local/share/tartaros.
+pub fn base_dir(output_dir: Option<&Path>) -> io::Result<PathBuf> {
+    if let Some(output_dir) = output_dir {
+        return Ok(output_dir.to_path_buf());
+    }
+    if let Some(data_home) = std::env::var_os("XDG_DATA_HOME").filter(|dir| !dir.is_empty()) {
+        return Ok(PathBuf::from(data_home).join("tartaros"));
+    }
+    let home_dir = std::env::var_os("HOME")
+        .filter(|dir| !dir.is_empty())
+        .ok_or_else(|| {
+            io::Error::new(
+                io::ErrorKind::NotFound,
+                "neither $XDG_DATA_HOME nor $HOME is set, use --output-dir",
+            )
+        })?;
+    Ok(PathBuf::from(home_dir)
+        .join(".local")
+        .join("share")
+        .join("tartaros"))
+}
+
+///Creates a new, timestamped directory for this run under `<base>/<target>/` and points `latest` at it.
+///Results of earlier runs are never touched.
+pub fn create_run_dir(base_dir: &Path, target: &str) -> io::Result<PathBuf> {
+    let target_dir = base_dir.join(target);
+    fs::create_dir_all(&target_dir)?;
+
+    let timestamp = Local::now().format("%Y%m%d-%H%M%S").to_string();
+    let mut run_dir = target_dir.join(&timestamp);
+    let mut suffix = 1;
+    //create_dir fails if the directory exists, so two runs within the same second can't share a directory.
+    loop {
+        match fs::create_dir(&run_dir) {
+            Ok(()) => break,
+            Err(err) if err.kind() == io::ErrorKind::AlreadyExists => {
+                run_dir = target_dir.join(format!("{}-{}", timestamp, suffix));
+                suffix += 1;
+            }
+            Err(err) => return Err(err),
+        }
+    }
+
+    update_latest(&target_dir, &run_dir)?;
+    Ok(run_dir)
+}
+
+#[cfg(unix)]
+fn update_latest(target_dir: &Path, run_dir: &Path) -> io::Result<()> {
+    let latest = target_dir.join(LATEST);
+    if fs::symlink_metadata(&latest).is_ok() {
+        fs::remove_file(&latest)?;
+    }
+    //A relative link keeps working when the whole results directory is moved.
+    let run_name = run_dir.file_name().map(PathBuf::from).unwrap_or_default();
+    std::os::unix::fs::symlink(run_name, latest)
+}
+
+#[cfg(not(unix))]
+fn update_latest(target_dir: &Path, run_dir: &Path) -> io::Result<()> {
+    //No symlinks without special privileges, so store the name of the run in a plain file instead.
+    let run_name = run_dir
+        .file_name()
+        .map(|name| name.to_string_lossy().to_string())
+        .unwrap_or_default();
+    fs::write(target_dir.join(LATEST), run_name)
+}
+
+///Accepts either a run directory or a target directory, in which case the latest run is used.
+pub fn resolve_run_dir(path: &Path) -> PathBuf {
+    let latest = path.join(LATEST);
+    if latest.is_dir() {
+        return latest;
+    }
+    //On platforms without symlinks 'latest' is a file containing the name of the run.
+    if let Ok(run_name) = fs::read_to_string(&latest) {
+        return path.join(run_name.trim());
+    }
+    path.to_path_buf()
+}
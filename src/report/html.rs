@@ -0,0 +1,528 @@
+use std::{fmt::Write, fs, io, path::Path};
+
+use base64::{engine::general_purpose::STANDARD, Engine};
+
+use super::json::{RunReport, ScanRecord};
+use crate::{
+    scans::{
+        cve::CveScanResult,
+        finding::Severity,
+        headers::HeaderCheckResult,
+        nmap::{NmapHost, ScriptOutput},
+        nuclei::NucleiScanResult,
+        smb::{Signing, SmbScanResult},
+    },
+    scheduler::ScanOutput,
+};
+
+///File in the run directory the HTML report is written to when no other path is given.
+pub const HTML_REPORT: &str = "report.html";
+
+///Embedded so the report works offline and without the repository next to the binary.
+const ICON: &[u8] = include_bytes!("../../img/icon_128.png");
+
+const SEVERITIES: [Severity; 5] = [
+    Severity::Critical,
+    Severity::High,
+    Severity::Medium,
+    Severity::Low,
+    Severity::Info,
+];
+
+const STYLE: &str = r#"
+body { font-family: -apple-system, "Segoe UI", Helvetica, Arial, sans-serif; margin: 0; color: #1d1d1f; background: #f5f5f7; }
+header { display: flex; align-items: center; gap: 1em; padding: 1em 2em; background: #1d1d1f; color: #f5f5f7; }
+header img { width: 64px; height: 64px; }
+header h1 { margin: 0; }
+main { padding: 1em 2em; }
+section { background: #fff; border-radius: 8px; padding: 1em 1.5em; margin-bottom: 1.5em; }
+.summary { display: flex; gap: 1em; flex-wrap: wrap; }
+.count { border-radius: 8px; padding: 0.5em 1em; color: #fff; min-width: 6em; text-align: center; }
+.count strong { display: block; font-size: 2em; }
+.controls { display: flex; gap: 1em; margin-bottom: 1em; position: sticky; top: 0; background: #f5f5f7; padding: 0.5em 0; }
+table { border-collapse: collapse; width: 100%; margin-bottom: 1em; }
+th, td { text-align: left; padding: 0.3em 0.6em; border-bottom: 1px solid #ddd; vertical-align: top; word-break: break-word; }
+th { cursor: pointer; user-select: none; background: #eee; }
+th.asc::after { content: " \25B2"; }
+th.desc::after { content: " \25BC"; }
+pre { white-space: pre-wrap; margin: 0.3em 0; }
+.severity { font-weight: bold; }
+.critical { background: #8b0000; }
+.high { background: #d70000; }
+.medium { background: #ffaf00; }
+.low { background: #008700; }
+.info { background: #0087ff; }
+td.severity.critical { color: #8b0000; background: none; }
+td.severity.high { color: #d70000; background: none; }
+td.severity.medium { color: #d78700; background: none; }
+td.severity.low { color: #008700; background: none; }
+td.severity.info { color: #0087ff; background: none; }
+.error { color: #d70000; }
+"#;
+
+//Sorting by clicking a column header, and filtering every table by text and minimum severity.
+const SCRIPT: &str = r#"
+const rank = { info: 0, low: 1, medium: 2, high: 3, critical: 4 };
+function applyFilters() {
+  const text = document.getElementById('filter').value.toLowerCase();
+  const minimum = rank[document.getElementById('severity').value];
+  document.querySelectorAll('table.data tbody tr').forEach(row => {
+    const severity = row.dataset.severity;
+    const severe = severity === undefined || rank[severity] >= minimum;
+    row.style.display = severe && row.textContent.toLowerCase().includes(text) ? '' : 'none';
+  });
+}
+function sortTable(header) {
+  const table = header.closest('table');
+  const column = Array.from(header.parentNode.children).indexOf(header);
+  const ascending = !header.classList.contains('asc');
+  table.querySelectorAll('th').forEach(th => th.classList.remove('asc', 'desc'));
+  header.classList.add(ascending ? 'asc' : 'desc');
+  const key = row => {
+    const cell = row.children[column];
+    return cell.dataset.sort !== undefined ? Number(cell.dataset.sort) : cell.textContent.trim();
+  };
+  const body = table.tBodies[0];
+  Array.from(body.rows)
+    .sort((a, b) => {
+      const [x, y] = [key(a), key(b)];
+      const order = typeof x === 'number' && typeof y === 'number' ? x - y : String(x).localeCompare(String(y), undefined, { numeric: true });
+      return ascending ? order : -order;
+    })
+    .forEach(row => body.appendChild(row));
+}
+document.querySelectorAll('table.data th').forEach(th => th.addEventListener('click', () => sortTable(th)));
+document.getElementById('filter').addEventListener('input', applyFilters);
+document.getElementById('severity').addEventListener('change', applyFilters);
+"#;
+
+///Renders a run into a single HTML file that needs nothing but a browser.
+pub fn write_html(run_dir: &Path, path: &Path) -> io::Result<()> {
+    let report = RunReport::load(run_dir)?;
+    fs::write(path, render(&report))
+}
+
+fn render(report: &RunReport) -> String {
+    let mut html = String::new();
+    let _ = write!(
+        html,
+        "<!DOCTYPE html>\n<html lang=\"en\">\n<head>\n<meta charset=\"utf-8\">\n<title>Tartaros report: {}</title>\n<style>{}</style>\n</head>\n<body>\n",
+        escape(&report.target),
+        STYLE
+    );
+    let _ = write!(
+        html,
+        "<header><img src=\"data:image/png;base64,{}\" alt=\"Tartaros\"><div><h1>{}</h1>Started {}",
+        STANDARD.encode(ICON),
+        escape(&report.target),
+        report.started.format("%Y-%m-%d %H:%M:%S UTC")
+    );
+    if let Some(finished) = report.finished {
+        let _ = write!(
+            html,
+            ", finished {}",
+            finished.format("%Y-%m-%d %H:%M:%S UTC")
+        );
+    }
+    let _ = write!(
+        html,
+        " &middot; {}</div></header>\n<main>\n",
+        escape(&report.generator)
+    );
+
+    summary(&mut html, report);
+    let _ = writeln!(
+        html,
+        "<div class=\"controls\"><input id=\"filter\" type=\"search\" placeholder=\"Filter\" size=\"40\">\
+         <label>Minimum severity <select id=\"severity\">{}</select></label></div>",
+        SEVERITIES
+            .iter()
+            .rev()
+            .map(|severity| format!("<option value=\"{0}\">{0}</option>", severity))
+            .collect::<String>()
+    );
+    findings_section(&mut html, report);
+    for host in report.host_names() {
+        host_section(&mut html, report, &host);
+    }
+
+    let _ = write!(
+        html,
+        "</main>\n<script>{}</script>\n</body>\n</html>\n",
+        SCRIPT
+    );
+    html
+}
+
+fn summary(html: &mut String, report: &RunReport) {
+    html.push_str("<section><h2>Summary</h2><div class=\"summary\">");
+    for severity in SEVERITIES {
+        let count = report
+            .findings
+            .iter()
+            .filter(|finding| finding.severity == severity)
+            .count();
+        let _ = write!(
+            html,
+            "<div class=\"count {0}\"><strong>{1}</strong>{0}</div>",
+            severity, count
+        );
+    }
+    html.push_str("</div>");
+
+    let failed: Vec<&ScanRecord> = report
+        .scans
+        .iter()
+        .filter(|scan| scan.error.is_some())
+        .collect();
+    if !failed.is_empty() {
+        html.push_str("<h3>Scans that did not complete</h3><ul>");
+        for scan in failed {
+            let _ = write!(
+                html,
+                "<li class=\"error\">{} against {}: {}</li>",
+                escape(&scan.tool),
+                escape(&scan.target),
+                //Unwrap is safe because of the filter above.
+                escape(&scan.error.as_ref().unwrap().to_string())
+            );
+        }
+        html.push_str("</ul>");
+    }
+    html.push_str("</section>\n");
+}
+
+fn findings_section(html: &mut String, report: &RunReport) {
+    html.push_str("<section><h2>Findings</h2>");
+    if report.findings.is_empty() {
+        html.push_str("<p>No findings.</p></section>\n");
+        return;
+    }
+
+    html.push_str("<table class=\"data\"><thead><tr><th>Severity</th><th>Title</th><th>Location</th><th>Source</th><th>Evidence</th></tr></thead><tbody>");
+    for finding in &report.findings {
+        let location = match (&finding.url, finding.port) {
+            (Some(url), _) => url.clone(),
+            (None, Some(port)) => format!("{}:{}", finding.host, port),
+            (None, None) => finding.host.clone(),
+        };
+        let mut evidence = finding.evidence.clone();
+        if let Some(raw_reference) = &finding.raw_reference {
+            evidence.push(format!("Raw output: {}", raw_reference));
+        }
+        let _ = write!(
+            html,
+            "<tr data-severity=\"{0}\">{1}<td>{2}</td><td>{3}</td><td>{4}</td><td>{5}</td></tr>",
+            finding.severity,
+            severity_cell(finding.severity),
+            escape(&finding.title),
+            escape(&location),
+            escape(&finding.source),
+            collapsible(&evidence)
+        );
+    }
+    html.push_str("</tbody></table></section>\n");
+}
+
+fn host_section(html: &mut String, report: &RunReport, host: &str) {
+    let _ = write!(html, "<section><h2>{}</h2>", escape(host));
+
+    let nmap_hosts: Vec<&NmapHost> = report
+        .hosts
+        .iter()
+        .filter(|nmap_host| nmap_host.name() == host)
+        .collect();
+    //Nmap only ran against the target itself.
+    if nmap_hosts.is_empty() && host == report.target {
+        html.push_str("<p>Nmap found no open ports.</p>");
+    }
+    for nmap_host in nmap_hosts {
+        nmap_tables(html, nmap_host);
+    }
+
+    for (service, scans) in report.services(host) {
+        let _ = write!(html, "<h3>{}</h3>", escape(service));
+        service_tables(html, &scans);
+    }
+    html.push_str("</section>\n");
+}
+
+fn nmap_tables(html: &mut String, host: &NmapHost) {
+    let addresses: Vec<&str> = host
+        .addresses
+        .iter()
+        .map(|address| address.addr.as_str())
+        .collect();
+    let hostnames: Vec<&str> = host
+        .hostnames
+        .iter()
+        .map(|hostname| hostname.name.as_str())
+        .collect();
+    let _ = write!(
+        html,
+        "<p>Addresses: {}<br>Hostnames: {}<br>Status: {} ({})",
+        escape(&addresses.join(", ")),
+        escape(&hostnames.join(", ")),
+        escape(&host.status.state),
+        escape(&host.status.reason)
+    );
+    if let Some(os_match) = host.os_matches.first() {
+        let _ = write!(
+            html,
+            "<br>OS: {} ({}%)",
+            escape(&os_match.name),
+            os_match.accuracy
+        );
+    }
+    html.push_str("</p><h3>Ports and services</h3>");
+
+    if host.ports.is_empty() {
+        html.push_str("<p>Nmap found no open ports.</p>");
+        return;
+    }
+    html.push_str("<table class=\"data\"><thead><tr><th>Port</th><th>State</th><th>Service</th><th>Product</th><th>Version</th><th>Scripts</th></tr></thead><tbody>");
+    for result in &host.ports {
+        let service = if result.service_tunnel.is_empty() {
+            result.service_name.clone()
+        } else {
+            format!("{}/{}", result.service_tunnel, result.service_name)
+        };
+        let version = format!("{} {}", result.service_version, result.service_extrainfo);
+        let scripts: Vec<String> = result
+            .scripts
+            .iter()
+            .map(|script| format!("{}:{}", script.id, script.output))
+            .collect();
+        let _ = write!(
+            html,
+            "<tr><td data-sort=\"{0}\">{0}/{1}</td><td>{2}</td><td>{3}</td><td>{4}</td><td>{5}</td><td>{6}</td></tr>",
+            result.port.num,
+            result.port.protocol,
+            result.port.state,
+            escape(&service),
+            escape(&result.service_product),
+            escape(version.trim()),
+            collapsible(&scripts)
+        );
+    }
+    html.push_str("</tbody></table>");
+}
+
+fn service_tables(html: &mut String, scans: &[&ScanRecord]) {
+    let mut paths = Vec::new();
+    let mut nuclei_results: Vec<&NucleiScanResult> = Vec::new();
+    let mut scripts: Vec<&ScriptOutput> = Vec::new();
+    let mut smb_results: Vec<&SmbScanResult> = Vec::new();
+    let mut header_results: Vec<&HeaderCheckResult> = Vec::new();
+    let mut cve_results: Vec<&CveScanResult> = Vec::new();
+    for scan in scans {
+        match &scan.output {
+            Some(ScanOutput::Feroxbuster(results)) => paths.extend(results),
+            Some(ScanOutput::Nuclei(results)) => nuclei_results.extend(results),
+            Some(ScanOutput::Smb(results)) => smb_results.extend(results),
+            Some(ScanOutput::Headers(results)) => header_results.extend(results),
+            Some(ScanOutput::Cve(results)) => cve_results.extend(results),
+            Some(ScanOutput::Nmap(hosts)) => scripts.extend(hosts.iter().flat_map(|host| {
+                host.ports
+                    .iter()
+                    .flat_map(|port| &port.scripts)
+                    .chain(&host.scripts)
+            })),
+            None => {}
+        }
+        if let Some(err) = &scan.error {
+            let _ = write!(
+                html,
+                "<p class=\"error\">{} did not complete: {}</p>",
+                escape(&scan.tool),
+                escape(&err.to_string())
+            );
+        }
+    }
+
+    if paths.is_empty()
+        && nuclei_results.is_empty()
+        && scripts.is_empty()
+        && smb_results.is_empty()
+        && header_results.is_empty()
+        && cve_results.is_empty()
+    {
+        html.push_str("<p>Nothing found.</p>");
+        return;
+    }
+
+    if !paths.is_empty() {
+        html.push_str("<h4>Paths</h4><table class=\"data\"><thead><tr><th>Status</th><th>Words</th><th>URL</th></tr></thead><tbody>");
+        for path in paths {
+            let _ = write!(
+                html,
+                "<tr><td data-sort=\"{0}\">{0}</td><td data-sort=\"{1}\">{1}</td><td>{2}</td></tr>",
+                path.status,
+                path.word_count,
+                escape(&path.url)
+            );
+        }
+        html.push_str("</tbody></table>");
+    }
+
+    for result in smb_results {
+        smb_tables(html, result);
+    }
+
+    if !header_results.is_empty() {
+        html.push_str("<h4>Missing security headers</h4><table class=\"data\"><thead><tr><th>URL</th><th>Missing</th><th>Recorded at</th><th>Reproduce</th></tr></thead><tbody>");
+        for result in header_results {
+            let mut evidence = vec![result.curl_command.clone()];
+            if let Some(file) = &result.evidence_file {
+                evidence.push(format!("Response: {}", file.display()));
+            }
+            let _ = write!(
+                html,
+                "<tr><td>{}</td><td>{}</td><td>{}</td><td>{}</td></tr>",
+                escape(&result.url),
+                escape(&result.missing.join(", ")),
+                result
+                    .recorded_at
+                    .map(|recorded_at| recorded_at.to_rfc3339())
+                    .unwrap_or_default(),
+                collapsible(&evidence)
+            );
+        }
+        html.push_str("</tbody></table>");
+    }
+
+    if !cve_results.is_empty() {
+        html.push_str("<h4>CVEs</h4><table class=\"data\"><thead><tr><th>CVSS</th><th>ID</th><th>Exploit</th><th>CPE</th><th>Port/service</th><th>Why</th></tr></thead><tbody>");
+        for result in cve_results {
+            let severity = result
+                .cvss
+                .map(Severity::from_cvss)
+                .unwrap_or(Severity::Info);
+            let _ = write!(
+                html,
+                "<tr data-severity=\"{0}\"><td data-sort=\"{1}\">{1}</td><td><a href=\"{2}\">{3}</a></td><td>{4}</td><td>{5}</td><td>{6}</td><td>{7}</td></tr>",
+                severity,
+                result
+                    .cvss
+                    .map(|cvss| format!("{:.1}", cvss))
+                    .unwrap_or_default(),
+                escape(&result.link()),
+                escape(&result.id),
+                if result.exploit { "yes" } else { "" },
+                escape(&result.cpe),
+                escape(&result.location()),
+                escape(&result.reason)
+            );
+        }
+        html.push_str("</tbody></table>");
+    }
+
+    for script in scripts {
+        let _ = write!(
+            html,
+            "<h4>{}</h4>{}",
+            escape(&script.id),
+            collapsible(&[script.output.trim().to_string()])
+        );
+    }
+
+    if !nuclei_results.is_empty() {
+        nuclei_results.sort_by_key(|result| std::cmp::Reverse(result.severity()));
+        html.push_str("<h4>Nuclei</h4><table class=\"data\"><thead><tr><th>Severity</th><th>Finding</th><th>Template</th><th>Location</th><th>Evidence</th></tr></thead><tbody>");
+        for result in nuclei_results {
+            let mut template = result.template_id.clone();
+            if !result.matcher_name.is_empty() {
+                template.push(':');
+                template.push_str(&result.matcher_name);
+            }
+            let _ = write!(
+                html,
+                "<tr data-severity=\"{0}\">{1}<td>{2}</td><td>{3}</td><td>{4}</td><td>{5}</td></tr>",
+                result.severity(),
+                severity_cell(result.severity()),
+                escape(result.title()),
+                escape(&template),
+                escape(result.matched_at()),
+                collapsible(result.extracted_results())
+            );
+        }
+        html.push_str("</tbody></table>");
+    }
+}
+
+fn smb_tables(html: &mut String, result: &SmbScanResult) {
+    let yes_no = |value: bool| String::from(if value { "yes" } else { "no" });
+    let signing = match result.signing {
+        Some(Signing::Required) => "required",
+        Some(Signing::Enabled) => "enabled, not required",
+        Some(Signing::Disabled) => "disabled",
+        None => "unknown",
+    };
+    let unknown = |value: &Option<String>| value.clone().unwrap_or_else(|| "unknown".into());
+    html.push_str("<h4>SMB</h4><table class=\"data\"><tbody>");
+    for (label, value) in [
+        ("Dialects", result.dialects.join(", ")),
+        ("SMBv1", yes_no(result.smbv1)),
+        ("Signing", signing.to_string()),
+        ("Null session", yes_no(result.null_session)),
+        ("Guest access", yes_no(result.guest_access)),
+        ("OS", unknown(&result.os)),
+        ("Computer name", unknown(&result.computer_name)),
+        ("Domain", unknown(&result.domain)),
+        ("FQDN", unknown(&result.fqdn)),
+    ] {
+        let _ = write!(
+            html,
+            "<tr><th>{}</th><td>{}</td></tr>",
+            label,
+            escape(&value)
+        );
+    }
+    html.push_str("</tbody></table>");
+
+    if result.shares.is_empty() {
+        return;
+    }
+    html.push_str("<table class=\"data\"><thead><tr><th>Share</th><th>Type</th><th>Anonymous</th><th>Guest/user</th><th>Comment</th></tr></thead><tbody>");
+    for share in &result.shares {
+        let _ = write!(
+            html,
+            "<tr><td>{}</td><td>{}</td><td>{}</td><td>{}</td><td>{}</td></tr>",
+            escape(&share.name),
+            escape(&share.kind),
+            escape(share.anonymous_access.as_deref().unwrap_or_default()),
+            escape(share.user_access.as_deref().unwrap_or_default()),
+            escape(&share.comment)
+        );
+    }
+    html.push_str("</tbody></table>");
+}
+
+///The numeric sort key keeps severities in order of importance instead of alphabetical order.
+fn severity_cell(severity: Severity) -> String {
+    format!(
+        "<td class=\"severity {0}\" data-sort=\"{1}\">{0}</td>",
+        severity, severity as u8
+    )
+}
+
+///Raw evidence can be long, so it's folded away by default.
+fn collapsible(lines: &[String]) -> String {
+    if lines.is_empty() {
+        return String::new();
+    }
+    format!(
+        "<details><summary>{} item{}</summary><pre>{}</pre></details>",
+        lines.len(),
+        if lines.len() == 1 { "" } else { "s" },
+        escape(&lines.join("\n"))
+    )
+}
+
+fn escape(text: &str) -> String {
+    text.replace('&', "&amp;")
+        .replace('<', "&lt;")
+        .replace('>', "&gt;")
+        .replace('"', "&quot;")
+        .replace('\'', "&#39;")
+}
@@ -0,0 +1,148 @@
+use chrono::{DateTime, Utc};
+use serde::{Deserialize, Serialize};
+use std::{collections::BTreeMap, fs, io, path::Path};
+
+use crate::{
+    engine::RunResults,
+    manifest::RunManifest,
+    scans::{error::ScanError, finding::Finding, nmap::NmapHost},
+    scheduler::ScanOutput,
+    targets::host_of,
+};
+
+///File in every run directory that contains the machine-readable report.
+pub const REPORT: &str = "report.json";
+
+///Bumped whenever a field is removed or changes meaning. Adding fields doesn't bump it.
+pub const SCHEMA_VERSION: u32 = 2;
+
+///Structured report of a complete run, meant for dashboards and scripts.
+#[derive(Serialize, Deserialize)]
+pub struct RunReport {
+    pub schema_version: u32,
+    ///Name and version of the tool that wrote the report, e.g. 'tartaros 0.1.0'.
+    pub generator: String,
+    pub target: String,
+    pub started: DateTime<Utc>,
+    pub finished: Option<DateTime<Utc>>,
+    ///Every host Nmap reported, with its ports, services and script output.
+    pub hosts: Vec<NmapHost>,
+    ///Every scan that ran, including Nmap, with its timing and outcome.
+    pub scans: Vec<ScanRecord>,
+    ///All findings of the run, deduplicated and sorted by severity.
+    pub findings: Vec<Finding>,
+}
+
+#[derive(Serialize, Deserialize)]
+pub struct ScanRecord {
+    pub tool: String,
+    pub target: String,
+    ///Number of follow-up steps between Nmap and this scan. 0 for scans fired by Nmap's results.
+    pub depth: usize,
+    pub duration_secs: u64,
+    pub error: Option<ScanError>,
+    ///The raw results of the scan. Nmap's results are in `RunReport::hosts` instead.
+    pub output: Option<ScanOutput>,
+}
+
+impl ScanRecord {
+    ///The TCP and UDP port scans. Their results are in `RunReport::hosts`, not in `output`.
+    pub fn is_port_scan(&self) -> bool {
+        self.tool == "nmap" || self.tool == "nmap-udp"
+    }
+}
+
+impl RunReport {
+    pub fn new(manifest: &RunManifest, run_results: RunResults) -> RunReport {
+        let mut scans = Vec::new();
+        if let Some(nmap_duration) = run_results.nmap_duration {
+            scans.push(ScanRecord {
+                tool: String::from("nmap"),
+                target: manifest.target.clone(),
+                depth: 0,
+                duration_secs: nmap_duration.as_secs(),
+                error: run_results.nmap_error,
+                output: None,
+            });
+        }
+        if let Some(udp_duration) = run_results.udp_duration {
+            scans.push(ScanRecord {
+                tool: String::from("nmap-udp"),
+                target: manifest.target.clone(),
+                depth: 0,
+                duration_secs: udp_duration.as_secs(),
+                error: run_results.udp_error,
+                output: None,
+            });
+        }
+        scans.extend(run_results.job_results.into_iter().map(|job_result| {
+            let (output, error) = match job_result.result {
+                Ok(output) => (Some(output), None),
+                Err(err) => (None, Some(err)),
+            };
+            ScanRecord {
+                tool: job_result.tool.to_string(),
+                target: job_result.target,
+                depth: job_result.depth,
+                duration_secs: job_result.duration.as_secs(),
+                error,
+                output,
+            }
+        }));
+
+        RunReport {
+            schema_version: SCHEMA_VERSION,
+            generator: format!("tartaros {}", env!("CARGO_PKG_VERSION")),
+            target: manifest.target.clone(),
+            started: manifest.started,
+            finished: manifest.finished,
+            hosts: run_results.hosts,
+            scans,
+            findings: run_results.findings,
+        }
+    }
+
+    ///The target followed by the hosts that were found along the way, e.g. through certificates.
+    pub fn host_names(&self) -> Vec<String> {
+        let mut hosts: Vec<String> = vec![self.target.clone()];
+        for scan in self.scans.iter().filter(|scan| !scan.is_port_scan()) {
+            let host = host_of(&scan.target);
+            if !hosts.contains(&host) {
+                hosts.push(host);
+            }
+        }
+        hosts
+    }
+
+    ///Scans against `host` other than Nmap, grouped by the service they ran against, e.g. 'https://10.0.0.1:443'.
+    pub fn services(&self, host: &str) -> BTreeMap<&str, Vec<&ScanRecord>> {
+        let mut services: BTreeMap<&str, Vec<&ScanRecord>> = BTreeMap::new();
+        for scan in &self.scans {
+            if !scan.is_port_scan() && host_of(&scan.target) == host {
+                services.entry(&scan.target).or_default().push(scan);
+            }
+        }
+        services
+    }
+
+    pub fn write(&self, run_dir: &Path) -> io::Result<()> {
+        let json = serde_json::to_string_pretty(self)?;
+        fs::write(run_dir.join(REPORT), json)
+    }
+
+    ///Loads the report of a run. Reports written by a newer, incompatible version of Tartaros are refused.
+    pub fn load(run_dir: &Path) -> io::Result<RunReport> {
+        let json = fs::read_to_string(run_dir.join(REPORT))?;
+        let report: RunReport = serde_json::from_str(&json)?;
+        if report.schema_version > SCHEMA_VERSION {
+            return Err(io::Error::new(
+                io::ErrorKind::InvalidData,
+                format!(
+                    "{} uses schema version {}, this version of Tartaros only understands up to {}",
+                    REPORT, report.schema_version, SCHEMA_VERSION
+                ),
+            ));
+        }
+        Ok(report)
+    }
+}
@@ -0,0 +1,392 @@
+use std::{
+    collections::BTreeMap,
+    fmt::Write,
+    fs, io,
+    path::{Path, PathBuf},
+};
+
+use super::json::{RunReport, ScanRecord};
+use crate::{
+    manifest::RunManifest,
+    scans::{
+        cve::CveScanResult,
+        finding::Severity,
+        headers::HeaderCheckResult,
+        nmap::{NmapHost, ScriptOutput},
+        nuclei::NucleiScanResult,
+        process::{self, ExecutedCommand},
+        smb::{Signing, SmbScanResult},
+    },
+    scheduler::ScanOutput,
+    targets::host_of,
+};
+
+///Directory inside the run directory the notes are written to when no other directory is given.
+pub const NOTES_DIR: &str = "notes";
+const INDEX: &str = "index.md";
+
+///Renders a run into a tree of Markdown notes: an index plus one file per host.
+///Returns the paths of all files that were written.
+pub fn write_notes(run_dir: &Path, notes_dir: &Path) -> io::Result<Vec<PathBuf>> {
+    let report = RunReport::load(run_dir)?;
+    //The manifest holds the commands once the run is done, commands.jsonl is all there is for runs that didn't finish.
+    let commands = match RunManifest::load(run_dir) {
+        Ok(manifest) => manifest.commands,
+        Err(_) => process::executed_commands(run_dir),
+    };
+
+    let hosts = report.host_names();
+
+    fs::create_dir_all(notes_dir)?;
+    let mut written = Vec::new();
+    for host in &hosts {
+        let path = notes_dir.join(note_file_name(host));
+        fs::write(&path, host_note(&report, host, &commands))?;
+        written.push(path);
+    }
+    let index_path = notes_dir.join(INDEX);
+    fs::write(&index_path, index_note(&report, &hosts))?;
+    written.push(index_path);
+
+    Ok(written)
+}
+
+fn index_note(report: &RunReport, hosts: &[String]) -> String {
+    let mut note = String::new();
+    let _ = writeln!(note, "# {}\n", report.target);
+    let _ = writeln!(note, "- Started: {}", report.started.to_rfc3339());
+    if let Some(finished) = report.finished {
+        let _ = writeln!(note, "- Finished: {}", finished.to_rfc3339());
+    }
+    let _ = writeln!(note, "- Generated by: {}\n", report.generator);
+
+    let _ = writeln!(note, "## Hosts\n");
+    for host in hosts {
+        let _ = writeln!(note, "- [{}]({})", host, note_file_name(host));
+    }
+
+    let _ = writeln!(note, "\n## Findings\n");
+    let _ = writeln!(note, "| Severity | Count |");
+    let _ = writeln!(note, "| --- | --- |");
+    let mut counts: BTreeMap<std::cmp::Reverse<Severity>, usize> = BTreeMap::new();
+    for finding in &report.findings {
+        *counts
+            .entry(std::cmp::Reverse(finding.severity))
+            .or_insert(0) += 1;
+    }
+    for (severity, count) in counts {
+        let _ = writeln!(note, "| {} | {} |", severity.0, count);
+    }
+    note
+}
+
+fn host_note(report: &RunReport, host: &str, commands: &[ExecutedCommand]) -> String {
+    let mut note = String::new();
+    let _ = writeln!(note, "# {}\n", host);
+
+    let nmap_hosts: Vec<&NmapHost> = report
+        .hosts
+        .iter()
+        .filter(|nmap_host| nmap_host.name() == host)
+        .collect();
+    //Nmap only ran against the target itself.
+    if nmap_hosts.is_empty() && host == report.target {
+        let _ = writeln!(note, "Nmap found no open ports.\n");
+    }
+    for nmap_host in nmap_hosts {
+        nmap_section(&mut note, nmap_host);
+    }
+
+    for (service, scans) in report.services(host) {
+        let _ = writeln!(note, "## {}\n", service);
+        service_section(&mut note, &scans);
+    }
+
+    let host_commands: Vec<&ExecutedCommand> = commands
+        .iter()
+        .filter(|command| command.args.iter().any(|arg| mentions_host(arg, host)))
+        .collect();
+    if !host_commands.is_empty() {
+        let _ = writeln!(note, "## Commands\n");
+        let _ = writeln!(note, "```sh");
+        for command in host_commands {
+            let _ = writeln!(note, "{}", shell_command(command));
+        }
+        let _ = writeln!(note, "```");
+    }
+    note
+}
+
+fn nmap_section(note: &mut String, host: &NmapHost) {
+    let addresses: Vec<&str> = host
+        .addresses
+        .iter()
+        .map(|address| address.addr.as_str())
+        .collect();
+    let _ = writeln!(note, "- Addresses: {}", addresses.join(", "));
+    let hostnames: Vec<&str> = host
+        .hostnames
+        .iter()
+        .map(|hostname| hostname.name.as_str())
+        .collect();
+    if !hostnames.is_empty() {
+        let _ = writeln!(note, "- Hostnames: {}", hostnames.join(", "));
+    }
+    let _ = writeln!(
+        note,
+        "- Status: {} ({})",
+        host.status.state, host.status.reason
+    );
+    if let Some(os_match) = host.os_matches.first() {
+        let _ = writeln!(note, "- OS: {} ({}%)", os_match.name, os_match.accuracy);
+    }
+    note.push('\n');
+
+    let _ = writeln!(note, "## Ports and services\n");
+    if host.ports.is_empty() {
+        let _ = writeln!(note, "Nmap found no open ports.\n");
+        return;
+    }
+    let _ = writeln!(
+        note,
+        "| Port | State | Service | Product | Version | Extra info |"
+    );
+    let _ = writeln!(note, "| --- | --- | --- | --- | --- | --- |");
+    for result in &host.ports {
+        let service = if result.service_tunnel.is_empty() {
+            result.service_name.clone()
+        } else {
+            format!("{}/{}", result.service_tunnel, result.service_name)
+        };
+        let _ = writeln!(
+            note,
+            "| {}/{} | {} | {} | {} | {} | {} |",
+            result.port.num,
+            result.port.protocol,
+            result.port.state,
+            cell(&service),
+            cell(&result.service_product),
+            cell(&result.service_version),
+            cell(&result.service_extrainfo)
+        );
+    }
+    note.push('\n');
+}
+
+fn service_section(note: &mut String, scans: &[&ScanRecord]) {
+    let mut paths = Vec::new();
+    let mut nuclei_results: Vec<&NucleiScanResult> = Vec::new();
+    let mut scripts: Vec<&ScriptOutput> = Vec::new();
+    let mut smb_results: Vec<&SmbScanResult> = Vec::new();
+    let mut header_results: Vec<&HeaderCheckResult> = Vec::new();
+    let mut cve_results: Vec<&CveScanResult> = Vec::new();
+    for scan in scans {
+        match &scan.output {
+            Some(ScanOutput::Feroxbuster(results)) => paths.extend(results),
+            Some(ScanOutput::Nuclei(results)) => nuclei_results.extend(results),
+            Some(ScanOutput::Smb(results)) => smb_results.extend(results),
+            Some(ScanOutput::Headers(results)) => header_results.extend(results),
+            Some(ScanOutput::Cve(results)) => cve_results.extend(results),
+            Some(ScanOutput::Nmap(hosts)) => scripts.extend(hosts.iter().flat_map(|host| {
+                host.ports
+                    .iter()
+                    .flat_map(|port| &port.scripts)
+                    .chain(&host.scripts)
+            })),
+            None => {}
+        }
+        if let Some(err) = &scan.error {
+            let _ = writeln!(
+                note,
+                "> {} did not complete: {}\n",
+                scan.tool,
+                cell(&err.to_string())
+            );
+        }
+    }
+
+    if paths.is_empty()
+        && nuclei_results.is_empty()
+        && scripts.is_empty()
+        && smb_results.is_empty()
+        && header_results.is_empty()
+        && cve_results.is_empty()
+    {
+        let _ = writeln!(note, "Nothing found.\n");
+        return;
+    }
+
+    if !paths.is_empty() {
+        let _ = writeln!(note, "### Paths\n");
+        let _ = writeln!(note, "| Status | Words | URL |");
+        let _ = writeln!(note, "| --- | --- | --- |");
+        for path in paths {
+            let _ = writeln!(
+                note,
+                "| {} | {} | {} |",
+                path.status,
+                path.word_count,
+                cell(&path.url)
+            );
+        }
+        note.push('\n');
+    }
+
+    for result in smb_results {
+        smb_section(note, result);
+    }
+
+    for result in header_results {
+        let _ = writeln!(note, "### Missing security headers\n");
+        let _ = writeln!(note, "- URL: {}", result.url);
+        let _ = writeln!(note, "- Missing: {}", result.missing.join(", "));
+        if let Some(file) = &result.evidence_file {
+            let _ = writeln!(note, "- Response: {}", file.display());
+        }
+        if let Some(recorded_at) = result.recorded_at {
+            let _ = writeln!(note, "- Recorded at: {}", recorded_at.to_rfc3339());
+        }
+        let _ = writeln!(note, "\n```sh\n{}\n```\n", result.curl_command);
+    }
+
+    if !cve_results.is_empty() {
+        let _ = writeln!(note, "### CVEs\n");
+        let _ = writeln!(note, "| CVSS | ID | Exploit | CPE | Port/service | Why |");
+        let _ = writeln!(note, "| --- | --- | --- | --- | --- | --- |");
+        for result in cve_results {
+            let _ = writeln!(
+                note,
+                "| {} | [{}]({}) | {} | {} | {} | {} |",
+                result
+                    .cvss
+                    .map(|cvss| format!("{:.1}", cvss))
+                    .unwrap_or_default(),
+                cell(&result.id),
+                result.link(),
+                if result.exploit { "yes" } else { "" },
+                cell(&result.cpe),
+                cell(&result.location()),
+                cell(&result.reason)
+            );
+        }
+        note.push('\n');
+    }
+
+    for script in scripts {
+        let _ = writeln!(note, "### {}\n", script.id);
+        let _ = writeln!(note, "```\n{}\n```\n", script.output.trim());
+    }
+
+    if !nuclei_results.is_empty() {
+        nuclei_results.sort_by_key(|result| std::cmp::Reverse(result.severity()));
+        let _ = writeln!(note, "### Nuclei\n");
+        let _ = writeln!(note, "| Severity | Finding | Template | Location | Value |");
+        let _ = writeln!(note, "| --- | --- | --- | --- | --- |");
+        for result in nuclei_results {
+            let mut template = result.template_id.clone();
+            if !result.matcher_name.is_empty() {
+                template.push(':');
+                template.push_str(&result.matcher_name);
+            }
+            let _ = writeln!(
+                note,
+                "| {} | {} | {} | {} | {} |",
+                result.severity(),
+                cell(result.title()),
+                cell(&template),
+                cell(result.matched_at()),
+                cell(&result.extracted_results().join(", "))
+            );
+        }
+        note.push('\n');
+    }
+}
+
+fn smb_section(note: &mut String, result: &SmbScanResult) {
+    let yes_no = |value: bool| if value { "yes" } else { "no" };
+    let _ = writeln!(note, "### SMB\n");
+    let _ = writeln!(note, "- Dialects: {}", result.dialects.join(", "));
+    let _ = writeln!(note, "- SMBv1: {}", yes_no(result.smbv1));
+    let _ = writeln!(
+        note,
+        "- Signing: {}",
+        match result.signing {
+            Some(Signing::Required) => "required",
+            Some(Signing::Enabled) => "enabled, not required",
+            Some(Signing::Disabled) => "disabled",
+            None => "unknown",
+        }
+    );
+    let _ = writeln!(note, "- Null session: {}", yes_no(result.null_session));
+    let _ = writeln!(note, "- Guest access: {}", yes_no(result.guest_access));
+    for (label, value) in [
+        ("OS", &result.os),
+        ("Computer name", &result.computer_name),
+        ("Domain", &result.domain),
+        ("FQDN", &result.fqdn),
+    ] {
+        if let Some(value) = value {
+            let _ = writeln!(note, "- {}: {}", label, value);
+        }
+    }
+    note.push('\n');
+
+    if result.shares.is_empty() {
+        return;
+    }
+    let _ = writeln!(note, "| Share | Type | Anonymous | Guest/user | Comment |");
+    let _ = writeln!(note, "| --- | --- | --- | --- | --- |");
+    for share in &result.shares {
+        let _ = writeln!(
+            note,
+            "| {} | {} | {} | {} | {} |",
+            cell(&share.name),
+            cell(&share.kind),
+            cell(share.anonymous_access.as_deref().unwrap_or_default()),
+            cell(share.user_access.as_deref().unwrap_or_default()),
+            cell(&share.comment)
+        );
+    }
+    note.push('\n');
+}
+
+fn mentions_host(arg: &str, host: &str) -> bool {
+    arg == host || (arg.contains("://") && host_of(arg) == host)
+}
+
+fn note_file_name(host: &str) -> String {
+    let name: String = host
+        .chars()
+        .map(|c| {
+            if c.is_ascii_alphanumeric() || c == '.' || c == '-' {
+                c
+            } else {
+                '_'
+            }
+        })
+        .collect();
+    format!("{}.md", name)
+}
+
+///Escapes text so it fits in a single Markdown table cell.
+fn cell(text: &str) -> String {
+    text.replace('|', "\\|").replace(['\r', '\n'], " ")
+}
+
+///Formats a command so it can be pasted into a shell.
+fn shell_command(command: &ExecutedCommand) -> String {
+    let mut line = command.tool.clone();
+    for arg in &command.args {
+        line.push(' ');
+        if !arg.is_empty()
+            && arg
+                .chars()
+                .all(|c| c.is_ascii_alphanumeric() || "-_./:,=@".contains(c))
+        {
+            line.push_str(arg);
+        } else {
+            line.push_str(&format!("'{}'", arg.replace('\'', "'\\''")));
+        }
+    }
+    line
+}
@@ -0,0 +1,153 @@
+use std::{collections::HashSet, fs, io, path::Path};
+
+use comfy_table::{
+    modifiers::UTF8_ROUND_CORNERS, presets::UTF8_FULL, Cell, ContentArrangement, Table,
+};
+
+use self::json::RunReport;
+use crate::{
+    logger,
+    scans::{
+        feroxbuster::FeroxbusterScan,
+        finding::{self, Finding},
+        nmap::{self, NmapScan},
+        nuclei::NucleiScan,
+        scan::Scan,
+    },
+};
+
+pub mod html;
+pub mod json;
+pub mod markdown;
+
+///Loads the findings of a run from its JSON report.
+pub fn load_findings(run_dir: &Path) -> io::Result<Vec<Finding>> {
+    if run_dir.join(json::REPORT).exists() {
+        return Ok(RunReport::load(run_dir)?.findings);
+    }
+    load_raw_findings(run_dir)
+}
+
+///Parses the raw output files a run left behind in its output directory and converts them to findings.
+///Used for runs that were interrupted before the JSON report was written.
+fn load_raw_findings(run_dir: &Path) -> io::Result<Vec<Finding>> {
+    let mut findings = Vec::new();
+    for entry in fs::read_dir(run_dir)? {
+        let path = entry?.path();
+        let Some(file_name) = path.file_name().and_then(|name| name.to_str()) else {
+            continue;
+        };
+        //Log files sit next to the output files, e.g. 'nmap_xml.stdout.log'.
+        if file_name.ends_with(".log")
+            || file_name.ends_with(".json")
+            || file_name.ends_with(".jsonl")
+        {
+            continue;
+        }
+
+        if file_name == "nmap_xml" {
+            let nmap_scan = NmapScan::from_output_file(path.clone());
+            findings.extend(nmap_scan.to_findings(&nmap_scan.parse_output()));
+        } else if file_name.starts_with("nmap_")
+            && file_name != "nmap_raw"
+            && file_name != "nmap_discovery_xml"
+        {
+            //The UDP scan and NSE script scans, e.g. 'nmap_udp_xml' and 'nmap_snmp_161_10_0_0_1'.
+            findings.extend(nmap::host_findings(
+                &nmap::parse_xml_file(&path).hosts,
+                &path,
+            ));
+        } else if file_name.starts_with("nuclei_") {
+            let nuclei_scan = NucleiScan::from_output_file(path.clone());
+            findings.extend(nuclei_scan.to_findings(&nuclei_scan.parse_output()));
+        } else if let Some(port) = file_name.strip_prefix("feroxbuster") {
+            //'feroxbuster443_10_0_0_1', or just 'feroxbuster443' for older runs.
+            let Ok(port) = port.split('_').next().unwrap_or_default().parse() else {
+                continue;
+            };
+            let feroxbuster_scan = FeroxbusterScan::from_output_file(path.clone(), port);
+            findings.extend(feroxbuster_scan.to_findings(&feroxbuster_scan.parse_output()));
+        }
+    }
+
+    Ok(finding::dedup(findings))
+}
+
+///Prints the port table and all findings of a previous run.
+pub fn print_report(run_dir: &Path) -> io::Result<()> {
+    let findings = load_findings(run_dir)?;
+    logger::print_ok(&format!("Results of {}", run_dir.display()));
+
+    let nmap_scan = NmapScan::from_output_file(run_dir.join("nmap_xml"));
+    match RunReport::load(run_dir) {
+        Ok(report) => {
+            nmap_scan.print_results(&report.hosts);
+            for scan in report.scans.iter().filter(|scan| scan.error.is_some()) {
+                logger::print_warn(&format!(
+                    "{} against {} did not complete.",
+                    scan.tool, scan.target
+                ));
+            }
+        }
+        Err(_) if nmap_scan.output_file_exists() => {
+            nmap_scan.print_results(&nmap_scan.parse_output())
+        }
+        Err(_) => {}
+    }
+    print_findings(&findings);
+    Ok(())
+}
+
+///Prints the findings that appeared and disappeared between two runs.
+pub fn print_diff(old_run_dir: &Path, new_run_dir: &Path) -> io::Result<()> {
+    let old_findings = load_findings(old_run_dir)?;
+    let new_findings = load_findings(new_run_dir)?;
+    let old_keys: HashSet<String> = old_findings.iter().map(Finding::dedup_key).collect();
+    let new_keys: HashSet<String> = new_findings.iter().map(Finding::dedup_key).collect();
+
+    let added: Vec<Finding> = new_findings
+        .into_iter()
+        .filter(|finding| !old_keys.contains(&finding.dedup_key()))
+        .collect();
+    let resolved: Vec<Finding> = old_findings
+        .into_iter()
+        .filter(|finding| !new_keys.contains(&finding.dedup_key()))
+        .collect();
+
+    logger::print_ok(&format!("{} new findings:", added.len()));
+    print_findings(&added);
+    logger::print_ok(&format!("{} findings no longer present:", resolved.len()));
+    print_findings(&resolved);
+    Ok(())
+}
+
+///Prints all findings in a single table, most severe first.
+pub fn print_findings(findings: &[Finding]) {
+    if findings.is_empty() {
+        logger::print_ok("No findings.");
+        return;
+    }
+
+    let mut table = Table::new();
+    table
+        .load_preset(UTF8_FULL)
+        .apply_modifier(UTF8_ROUND_CORNERS)
+        .set_content_arrangement(ContentArrangement::DynamicFullWidth)
+        .set_header(vec!["Severity", "Title", "Location", "Source"]);
+
+    for finding in findings {
+        let location = match (&finding.url, finding.port) {
+            (Some(url), _) => url.clone(),
+            (None, Some(port)) => format!("{}:{}", finding.host, port),
+            (None, None) => finding.host.clone(),
+        };
+        table.add_row(vec![
+            Cell::new(finding.severity).fg(finding.severity.color()),
+            Cell::new(&finding.title),
+            Cell::new(location),
+            Cell::new(&finding.source),
+        ]);
+    }
+
+    println!("{}", table);
+}
@@ -0,0 +1,473 @@
+use comfy_table::{modifiers::UTF8_ROUND_CORNERS, presets::UTF8_FULL, *};
+use serde::{Deserialize, Serialize};
+use std::{path::PathBuf, time::Duration};
+
+use super::{
+    error::ScanError,
+    finding::{Finding, Severity},
+    nmap::{self, find_elem, find_table, NmapHost, NmapScanResult, ScriptElement, ScriptOutput},
+    process,
+    scan::Scan,
+};
+use crate::logger;
+
+///vulners looks up the CPEs of the version detection, the safe vuln scripts check for specific vulnerabilities.
+const CVE_SCRIPTS: &str = "vulners or (vuln and safe)";
+///States of Nmap's vulns library that confirm a vulnerability. 'LIKELY VULNERABLE' is a guess, usually from the version alone, so it doesn't count.
+const CONFIRMED_STATES: &[&str] = &["VULNERABLE", "VULNERABLE (Exploitable)", "VULNERABLE (DoS)"];
+///Version detection and the scripts together can take a while on slow services.
+const DEFAULT_TIMEOUT: Duration = Duration::from_secs(30 * 60);
+
+///A single vulnerability or exploit, tied to the service it was found on.
+#[derive(Serialize, Deserialize, Debug)]
+pub struct CveScanResult {
+    pub host: String,
+    ///`None` for host scripts, e.g. smb-vuln-ms17-010.
+    pub port: Option<u16>,
+    ///The service it was found on, e.g. 'OpenSSH 8.9p1'.
+    pub service: String,
+    ///The CPE vulners matched. Empty for the vuln scripts.
+    pub cpe: String,
+    ///e.g. 'CVE-2023-38408' or 'PACKETSTORM:173661'.
+    pub id: String,
+    pub cvss: Option<f32>,
+    ///vulners' type of the entry, e.g. 'cve', 'exploitdb' or 'githubexploit'.
+    pub kind: String,
+    ///Whether the entry is a public exploit rather than an advisory.
+    pub exploit: bool,
+    ///The NSE script that reported it, or 'nvd' for a match against the local NVD index.
+    pub script: String,
+    ///Why the version is affected, e.g. '8.9p1 >= 6.2 and < 9.3p2'. Only the NVD index says, Nmap's scripts don't.
+    #[serde(default)]
+    pub reason: String,
+}
+
+impl CveScanResult {
+    pub fn link(&self) -> String {
+        if self.script != "vulners" && self.id.starts_with("CVE-") {
+            format!("https://nvd.nist.gov/vuln/detail/{}", self.id)
+        } else {
+            format!("https://vulners.com/{}/{}", self.kind, self.id)
+        }
+    }
+
+    ///Where it was found, e.g. '22 (OpenSSH 8.9p1)'.
+    pub fn location(&self) -> String {
+        match self.port {
+            Some(port) => format!("{} ({})", port, self.service),
+            None => self.service.clone(),
+        }
+    }
+}
+
+///Nmap's vulners and vuln scripts against the services whose version Nmap detected.
+pub struct CveScan {
+    host: String,
+    output_file: PathBuf,
+    scan_args: Vec<String>,
+    timeout: Duration,
+}
+
+impl CveScan {
+    ///`ports` are the TCP ports of services with a detected version.
+    pub fn new(output_dir: PathBuf, target: &str, ports: &[u16]) -> CveScan {
+        let host: String = target
+            .chars()
+            .map(|c| if c.is_ascii_alphanumeric() { c } else { '_' })
+            .collect();
+        let output_file = output_dir.join(format!("nmap_cve_{}", host));
+        let ports: Vec<String> = ports.iter().map(u16::to_string).collect();
+        let mut scan_args = vec![
+            //Version detection runs again, the vulners script needs the CPEs from the same run.
+            String::from("-sV"),
+            String::from("-p"),
+            ports.join(","),
+            String::from("--script"),
+            String::from(CVE_SCRIPTS),
+            String::from("-oX"),
+            output_file.to_str().unwrap().to_string(),
+            //The ports are already known to be open.
+            String::from("-Pn"),
+        ];
+        scan_args.extend(nmap::target_args(target));
+
+        CveScan {
+            host: target.to_string(),
+            output_file,
+            scan_args,
+            timeout: DEFAULT_TIMEOUT,
+        }
+    }
+
+    ///Overrides the default timeout, if one is given.
+    pub fn with_timeout(mut self, timeout: Option<Duration>) -> CveScan {
+        if let Some(timeout) = timeout {
+            self.timeout = timeout;
+        }
+        self
+    }
+}
+
+impl Scan for CveScan {
+    type ScanResult = CveScanResult;
+
+    fn run(&self) -> Result<Vec<CveScanResult>, ScanError> {
+        logger::print_ok("Running Nmap CVE scripts...");
+        self.print_command();
+
+        let output = process::execute("nmap", &self.scan_args, &self.output_file, self.timeout)?;
+        logger::print_ok(&format!(
+            "Nmap CVE scripts finished in {}s.",
+            output.duration.as_secs()
+        ));
+        let results = self.parse_output();
+        self.print_results(&results);
+        Ok(results)
+    }
+
+    ///Sorted from the highest to the lowest score, entries without a score last.
+    fn parse_output(&self) -> Vec<CveScanResult> {
+        run_results(nmap::parse_xml_file(&self.output_file).hosts, &self.host)
+    }
+
+    fn print_results(&self, results: &[CveScanResult]) {
+        if results.is_empty() {
+            logger::print_ok("No known vulnerabilities found.");
+            return;
+        }
+
+        let mut table = Table::new();
+        table
+            .load_preset(UTF8_FULL)
+            .apply_modifier(UTF8_ROUND_CORNERS)
+            .set_content_arrangement(ContentArrangement::DynamicFullWidth)
+            .set_header(vec!["CVSS", "ID", "Exploit", "CPE", "Port/service"]);
+        for result in results {
+            let severity = result
+                .cvss
+                .map(Severity::from_cvss)
+                .unwrap_or(Severity::Info);
+            table.add_row(vec![
+                Cell::new(
+                    result
+                        .cvss
+                        .map(|cvss| format!("{:.1}", cvss))
+                        .unwrap_or_default(),
+                )
+                .fg(severity.color()),
+                Cell::new(&result.id),
+                Cell::new(if result.exploit { "yes" } else { "" }),
+                Cell::new(&result.cpe),
+                Cell::new(result.location()),
+            ]);
+        }
+        println!("{}", table);
+    }
+
+    fn to_findings(&self, results: &[CveScanResult]) -> Vec<Finding> {
+        findings(
+            results,
+            "nmap",
+            Some(&self.output_file.display().to_string()),
+        )
+    }
+
+    fn print_command(&self) {
+        logger::print_ok(&format!("Command used: nmap {}", self.scan_args.join(" ")));
+    }
+
+    ///Nmap's installation is checked in main.rs, see `NmapScan`.
+    fn is_installed(&self) -> bool {
+        true
+    }
+}
+
+///A finding per CVE, plus one per service listing its public exploits.
+///Shared with `NvdMatch`, the same CVE gets the same finding whichever source reported it.
+pub fn findings(
+    results: &[CveScanResult],
+    source: &str,
+    raw_reference: Option<&str>,
+) -> Vec<Finding> {
+    let mut findings: Vec<Finding> = Vec::new();
+    for result in results {
+        let (id, title) = if result.exploit {
+            (
+                String::from("cve:public-exploits"),
+                format!("Public exploits for {}", result.service),
+            )
+        } else {
+            (
+                format!("cve:{}", result.id),
+                format!("{} in {}", result.id, result.service),
+            )
+        };
+        let severity = result
+            .cvss
+            .map(Severity::from_cvss)
+            .unwrap_or(Severity::Info);
+        let evidence = format!(
+            "{} (CVSS {}) {}",
+            result.id,
+            result
+                .cvss
+                .map(|cvss| format!("{:.1}", cvss))
+                .unwrap_or_else(|| String::from("?")),
+            result.link()
+        );
+
+        //Results are sorted by score, so the first exploit of a service sets the severity.
+        if let Some(finding) = findings
+            .iter_mut()
+            .find(|finding| finding.id == id && finding.port == result.port)
+        {
+            finding.evidence.push(evidence);
+            continue;
+        }
+        let mut finding = Finding::new(id, title, severity, result.host.clone(), source);
+        finding.port = result.port;
+        if !result.cpe.is_empty() {
+            finding.evidence.push(format!("CPE: {}", result.cpe));
+        }
+        finding
+            .evidence
+            .push(format!("Reported by {}", result.script));
+        finding.evidence.push(evidence);
+        if !result.reason.is_empty() {
+            finding
+                .evidence
+                .push(format!("Affected: {}", result.reason));
+        }
+        finding.raw_reference = raw_reference.map(String::from);
+        findings.push(finding);
+    }
+    findings
+}
+
+///The TCP ports worth a CVE scan: open, with a version or at least a CPE that includes one.
+pub fn versioned_ports(hosts: &[NmapHost]) -> Vec<u16> {
+    hosts
+        .iter()
+        .flat_map(|host| &host.ports)
+        .filter(|result| {
+            result.port.protocol == nmap::Protocol::Tcp
+                && matches!(result.port.state, nmap::PortState::Open)
+                && (!result.service_version.is_empty()
+                    || result
+                        .service_cpes
+                        .iter()
+                        .any(|cpe| cpe.split(':').count() > 4))
+        })
+        .map(|result| result.port.num)
+        .collect()
+}
+
+///Sorted from the highest to the lowest score, entries without a score last.
+fn run_results(hosts: Vec<NmapHost>, target: &str) -> Vec<CveScanResult> {
+    let mut results = Vec::new();
+    for host in hosts {
+        for result in &host.ports {
+            let service = service_name(result);
+            for script in &result.scripts {
+                results.extend(script_results(
+                    script,
+                    target,
+                    Some(result.port.num),
+                    &service,
+                ));
+            }
+        }
+        for script in &host.scripts {
+            results.extend(script_results(script, target, None, &script.id));
+        }
+    }
+    results.sort_by(|a, b| b.cvss.unwrap_or(-1.0).total_cmp(&a.cvss.unwrap_or(-1.0)));
+    results
+}
+
+fn service_name(result: &NmapScanResult) -> String {
+    let name = format!("{} {}", result.service_product, result.service_version);
+    if name.trim().is_empty() {
+        result.service_name.clone()
+    } else {
+        name.trim().to_string()
+    }
+}
+
+fn script_results(
+    script: &ScriptOutput,
+    host: &str,
+    port: Option<u16>,
+    service: &str,
+) -> Vec<CveScanResult> {
+    if script.id == "vulners" {
+        vulners_results(script, host, port, service)
+    } else {
+        vuln_results(script, host, port, service)
+    }
+}
+
+///One table per CPE, holding a table per entry with its id, type, CVSS score and whether it's an exploit.
+fn vulners_results(
+    script: &ScriptOutput,
+    host: &str,
+    port: Option<u16>,
+    service: &str,
+) -> Vec<CveScanResult> {
+    let mut results = Vec::new();
+    for cpe in &script.elements {
+        let Some(cpe_name) = cpe.key() else {
+            continue;
+        };
+        for entry in cpe.elements() {
+            let Some(id) = find_elem(entry.elements(), "id") else {
+                continue;
+            };
+            results.push(CveScanResult {
+                host: host.to_string(),
+                port,
+                service: service.to_string(),
+                cpe: cpe_name.to_string(),
+                id: id.to_string(),
+                cvss: find_elem(entry.elements(), "cvss").and_then(|cvss| cvss.parse().ok()),
+                kind: find_elem(entry.elements(), "type")
+                    .unwrap_or("cve")
+                    .to_string(),
+                exploit: find_elem(entry.elements(), "is_exploit") == Some("true"),
+                script: script.id.clone(),
+                reason: String::new(),
+            });
+        }
+    }
+    results
+}
+
+///The vuln scripts report a table per vulnerability with its state, ids and scores. Only confirmed ones count.
+fn vuln_results(
+    script: &ScriptOutput,
+    host: &str,
+    port: Option<u16>,
+    service: &str,
+) -> Vec<CveScanResult> {
+    let mut results = Vec::new();
+    for vulnerability in &script.elements {
+        let elements = vulnerability.elements();
+        //'NOT VULNERABLE' contains 'VULNERABLE' too, so the state has to match exactly.
+        if !find_elem(elements, "state").is_some_and(|state| CONFIRMED_STATES.contains(&state)) {
+            continue;
+        }
+        //e.g. 'CVE:CVE-2014-0160'. Without a CVE the script's table key has to do.
+        let id = find_table(elements, "ids")
+            .unwrap_or_default()
+            .iter()
+            .filter_map(ScriptElement::value)
+            .find_map(|id| id.strip_prefix("CVE:"))
+            .or(vulnerability.key())
+            .unwrap_or(&script.id);
+        let scores = find_table(elements, "scores").unwrap_or_default();
+        let cvss = find_elem(scores, "CVSSv3")
+            .or_else(|| find_elem(scores, "CVSSv2"))
+            .and_then(|cvss| cvss.parse().ok());
+        results.push(CveScanResult {
+            host: host.to_string(),
+            port,
+            service: service.to_string(),
+            cpe: String::new(),
+            id: id.to_string(),
+            cvss,
+            kind: String::from("cve"),
+            exploit: false,
+            script: script.id.clone(),
+            reason: String::new(),
+        });
+    }
+    results
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    const VULNERS_XML: &str = r#"<?xml version="1.0"?>
+<nmaprun args="nmap -sV --script vulners" version="7.94">
+<host><status state="up" reason="user-set"/><address addr="10.0.0.5" addrtype="ipv4"/>
+<ports><port protocol="tcp" portid="22"><state state="open" reason="syn-ack"/>
+<service name="ssh" product="OpenSSH" version="8.9p1 Ubuntu 3ubuntu0.1"><cpe>cpe:/a:openbsd:openssh:8.9p1</cpe></service>
+<script id="vulners" output="..."><table key="cpe:/a:openbsd:openssh:8.9p1">
+<table><elem key="id">CVE-2023-48795</elem><elem key="cvss">5.9</elem><elem key="type">cve</elem><elem key="is_exploit">false</elem></table>
+<table><elem key="id">PACKETSTORM:173661</elem><elem key="type">packetstorm</elem><elem key="is_exploit">true</elem></table>
+<table><elem key="id">CVE-2023-38408</elem><elem key="cvss">9.8</elem><elem key="type">cve</elem><elem key="is_exploit">false</elem></table>
+</table></script></port></ports></host>
+</nmaprun>"#;
+
+    const VULN_XML: &str = r#"<?xml version="1.0"?>
+<nmaprun args="nmap -sV --script vuln and safe" version="7.94">
+<host><status state="up" reason="user-set"/><address addr="10.0.0.5" addrtype="ipv4"/>
+<ports><port protocol="tcp" portid="443"><state state="open" reason="syn-ack"/>
+<service name="http" product="nginx" version="1.18.0" tunnel="ssl"/>
+<script id="ssl-heartbleed" output="VULNERABLE"><table key="NMAP-1">
+<elem key="title">The Heartbleed Bug</elem><elem key="state">VULNERABLE</elem>
+<table key="ids"><elem>BID:66690</elem><elem>CVE:CVE-2014-0160</elem></table>
+<table key="scores"><elem key="CVSSv2">5.0</elem></table>
+</table></script>
+<script id="ssl-ccs-injection" output="NOT VULNERABLE"><table key="NMAP-2">
+<elem key="state">NOT VULNERABLE</elem><table key="ids"><elem>CVE:CVE-2014-0224</elem></table>
+</table></script>
+<script id="ssl-poodle" output="LIKELY VULNERABLE"><table key="NMAP-3">
+<elem key="state">LIKELY VULNERABLE</elem><table key="ids"><elem>CVE:CVE-2014-3566</elem></table>
+</table></script>
+</port></ports>
+<hostscript><script id="smb-vuln-ms17-010" output="VULNERABLE"><table key="CVE-2017-0143">
+<elem key="state">VULNERABLE</elem>
+</table></script></hostscript>
+</host>
+</nmaprun>"#;
+
+    fn parse(xml: &str) -> Vec<CveScanResult> {
+        run_results(nmap::parse_xml(xml.as_bytes()).hosts, "10.0.0.5")
+    }
+
+    #[test]
+    fn vulners_entries_sorted_by_score_unscored_last() {
+        let results = parse(VULNERS_XML);
+        let ids: Vec<&str> = results.iter().map(|result| result.id.as_str()).collect();
+        assert_eq!(
+            ids,
+            ["CVE-2023-38408", "CVE-2023-48795", "PACKETSTORM:173661"]
+        );
+        assert_eq!(results[0].cvss, Some(9.8));
+        assert_eq!(results[0].cpe, "cpe:/a:openbsd:openssh:8.9p1");
+        assert_eq!(results[0].service, "OpenSSH 8.9p1 Ubuntu 3ubuntu0.1");
+        assert_eq!(results[0].port, Some(22));
+        assert!(!results[0].exploit);
+        assert_eq!(results[2].cvss, None);
+        assert_eq!(results[2].kind, "packetstorm");
+        assert!(results[2].exploit);
+    }
+
+    #[test]
+    fn vuln_scripts_only_report_confirmed_vulnerabilities() {
+        let results = parse(VULN_XML);
+        let ids: Vec<&str> = results.iter().map(|result| result.id.as_str()).collect();
+        assert_eq!(ids, ["CVE-2014-0160", "CVE-2017-0143"]);
+    }
+
+    #[test]
+    fn vuln_script_takes_the_cve_from_its_ids() {
+        let results = parse(VULN_XML);
+        let heartbleed = &results[0];
+        assert_eq!(heartbleed.script, "ssl-heartbleed");
+        assert_eq!(heartbleed.port, Some(443));
+        assert_eq!(heartbleed.cvss, Some(5.0));
+        assert_eq!(
+            heartbleed.link(),
+            "https://nvd.nist.gov/vuln/detail/CVE-2014-0160"
+        );
+
+        //No ids, the table key has to do. Host scripts have no port.
+        let ms17_010 = &results[1];
+        assert_eq!(ms17_010.port, None);
+        assert_eq!(ms17_010.cvss, None);
+        assert_eq!(ms17_010.service, "smb-vuln-ms17-010");
+    }
+}
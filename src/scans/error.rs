@@ -1,6 +1,44 @@
-#[derive(Debug)]
+use serde::{Deserialize, Serialize};
+use std::{fmt, time::Duration};
+
+#[derive(Serialize, Deserialize, Debug)]
+#[serde(tag = "kind", content = "details", rename_all = "snake_case")]
 pub enum ScanError {
     NotInstalled(String),
     Runtime(String),
-    None
-}
\ No newline at end of file
+    ///The tool exited with a non-zero status. `status` is `None` when the process was terminated by a signal.
+    NonZeroExit {
+        scan: String,
+        status: Option<i32>,
+        stderr_tail: String,
+    },
+    ///The tool exceeded its wall-clock timeout and was killed.
+    Timeout {
+        scan: String,
+        timeout: Duration,
+        stderr_tail: String,
+    },
+}
+
+impl fmt::Display for ScanError {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        match self {
+            ScanError::NotInstalled(scan) => {
+                write!(f, "{}: Not installed, skipping this scan.", scan)
+            }
+            ScanError::Runtime(scan) => write!(f, "{}: Encountered runtime error", scan),
+            ScanError::NonZeroExit { scan, status, .. } => match status {
+                Some(code) => write!(f, "{}: Exited with status {}", scan, code),
+                None => write!(f, "{}: Terminated by a signal", scan),
+            },
+            ScanError::Timeout { scan, timeout, .. } => {
+                write!(
+                    f,
+                    "{}: Timed out after {}s and was killed",
+                    scan,
+                    timeout.as_secs()
+                )
+            }
+        }
+    }
+}
@@ -1,25 +1,34 @@
 use std::{
-    fs::{self, File},
+    fs::File,
     io::{BufRead, BufReader},
-    path::PathBuf,
-    process::{Command, Stdio},
+    path::{Path, PathBuf},
+    time::Duration,
 };
 
 use comfy_table::{modifiers::UTF8_ROUND_CORNERS, presets::UTF8_FULL, Table};
 use serde::{Deserialize, Serialize};
-use url::Url;
 
-use super::{error::ScanError, scan::Scan};
+use super::{
+    error::ScanError,
+    finding::{Finding, Severity},
+    process,
+    scan::Scan,
+};
 use crate::logger;
+use url::Url;
 use which::which;
 
+///Directory brute forcing with recursion can run away on large sites, so cap it.
+const DEFAULT_TIMEOUT: Duration = Duration::from_secs(30 * 60);
+
 pub struct FeroxbusterScan {
     output_file: PathBuf,
     scan_args: Vec<String>,
-    port: u16
+    port: u16,
+    timeout: Duration,
 }
 
-#[derive(Deserialize, Debug)]
+#[derive(Serialize, Deserialize, Debug)]
 pub struct FeroxbusterScanResult {
     pub url: String,
     pub status: u32,
@@ -27,10 +36,24 @@ pub struct FeroxbusterScanResult {
 }
 
 impl FeroxbusterScan {
-    pub fn new(mut output_dir: PathBuf, target: &str, port: u16) -> FeroxbusterScan {
-        output_dir.push("feroxbuster");
-        let mut pathbuf_string = output_dir.to_str().unwrap().to_string();
-        pathbuf_string.push_str(&port.to_string());
+    pub fn new(
+        mut output_dir: PathBuf,
+        target: &str,
+        port: u16,
+        wordlist: &Path,
+    ) -> FeroxbusterScan {
+        //Hostnames found in certificates share the port with the target, so the host is part of the file name too.
+        let host: String = Url::parse(target)
+            .ok()
+            .and_then(|url| url.host_str().map(String::from))
+            .unwrap_or_else(|| target.to_string())
+            .trim_start_matches('[')
+            .trim_end_matches(']')
+            .chars()
+            .map(|c| if c.is_ascii_alphanumeric() { c } else { '_' })
+            .collect();
+        output_dir.push(format!("feroxbuster{}_{}", port, host));
+        let pathbuf_string = output_dir.to_str().unwrap().to_string();
 
         FeroxbusterScan {
             output_file: PathBuf::from(&pathbuf_string),
@@ -38,72 +61,81 @@ impl FeroxbusterScan {
                 String::from("-u"),
                 String::from(target),
                 String::from("-w"),
-                String::from("/Users/mick/Workspace/Wordlists/common.txt"),
+                wordlist.display().to_string(),
                 String::from("-o"),
                 pathbuf_string,
                 String::from("--json"),
-                String::from("--insecure")
+                String::from("--insecure"),
             ],
-            port
+            port,
+            timeout: DEFAULT_TIMEOUT,
+        }
+    }
+
+    ///Only parses an existing output file, e.g. from a previous run. Calling `run()` on this is pointless.
+    pub fn from_output_file(output_file: PathBuf, port: u16) -> FeroxbusterScan {
+        FeroxbusterScan {
+            output_file,
+            scan_args: Vec::new(),
+            port,
+            timeout: DEFAULT_TIMEOUT,
         }
     }
+
+    ///Overrides the default timeout, if one is given.
+    pub fn with_timeout(mut self, timeout: Option<Duration>) -> FeroxbusterScan {
+        if let Some(timeout) = timeout {
+            self.timeout = timeout;
+        }
+        self
+    }
 }
 
 impl Scan for FeroxbusterScan {
     type ScanResult = FeroxbusterScanResult;
 
     fn run(&self) -> Result<Vec<Self::ScanResult>, ScanError> {
-        if !self.is_installed(){
+        if !self.is_installed() {
             logger::print_err("Feroxbuster is not installed. Skipping scan.");
             return Err(ScanError::NotInstalled("feroxbuster".to_string()));
         }
         logger::print_ok("Running Feroxbuster...");
         self.print_command();
 
-        // match Command::new("feroxbuster")
-        //     .stdout(Stdio::null())
-        //     .stderr(Stdio::null())
-        //     .args(&self.scan_args)
-        //     .spawn()
-        // {
-        //     Ok(mut child) => {
-        //         if let Ok(_) = child.wait() {
-                    //Feroxbuster ran successfully.
-                    let results = self.parse_output();
-                    self.print_results(&results);
-                    return Ok(results);
-        //         } else {
-        //             return Err(ScanError::Runtime("feroxbuster".to_string()));
-        //         }
-        //     }
-        //     Err(err) => {
-        //         logger::print_err(&err.to_string());
-        //         return Err(ScanError::Runtime("feroxbuster".to_string()));
-        //     }
-        // }
+        let output = process::execute(
+            "feroxbuster",
+            &self.scan_args,
+            &self.output_file,
+            self.timeout,
+        )?;
+        logger::print_ok(&format!(
+            "Feroxbuster finished in {}s.",
+            output.duration.as_secs()
+        ));
+        let results = self.parse_output();
+        self.print_results(&results);
+        Ok(results)
     }
 
     fn parse_output(&self) -> Vec<Self::ScanResult> {
         let mut results: Vec<FeroxbusterScanResult> = Vec::new();
         if let Ok(file_handle) = File::open(&self.output_file) {
             let reader = BufReader::new(file_handle);
-            for line in reader.lines() {
-                if let Ok(line_read) = line {
-                    let json_result = serde_json::from_str(&line_read);
-                    if let Ok(feroxbuster_scan_result) = json_result {
-                        results.push(feroxbuster_scan_result);
-                    }
+            for line_read in reader.lines().map_while(Result::ok) {
+                let json_result = serde_json::from_str(&line_read);
+                if let Ok(feroxbuster_scan_result) = json_result {
+                    results.push(feroxbuster_scan_result);
                 }
             }
         } else {
             logger::print_err("Something went wrong when reading the feroxbuster output.");
         }
 
-        return results;
+        results
     }
 
     fn print_results(&self, scan_results: &[Self::ScanResult]) {
-        if scan_results.len() < 1 {
+        if scan_results.is_empty() {
             logger::print_warn("Feroxbuster found nothing.");
             return;
         }
@@ -126,6 +158,36 @@ impl Scan for FeroxbusterScan {
         println!("{}", table);
     }
 
+    fn to_findings(&self, scan_results: &[Self::ScanResult]) -> Vec<Finding> {
+        scan_results
+            .iter()
+            .map(|result| {
+                let url = Url::parse(&result.url).ok();
+                let path = url.as_ref().map(Url::path).unwrap_or(&result.url);
+                let host = url
+                    .as_ref()
+                    .and_then(|url| url.host_str().map(String::from))
+                    .unwrap_or_default();
+
+                let mut finding = Finding::new(
+                    String::from("feroxbuster:path"),
+                    format!("Discovered path {}", path),
+                    Severity::Info,
+                    host,
+                    "feroxbuster",
+                );
+                finding.port = Some(self.port);
+                finding.url = Some(result.url.clone());
+                finding.evidence.push(format!(
+                    "Status {}, {} words",
+                    result.status, result.word_count
+                ));
+                finding.raw_reference = Some(self.output_file.display().to_string());
+                finding
+            })
+            .collect()
+    }
+
     fn print_command(&self) {
         logger::print_ok(&format!(
             "Command used: feroxbuster {}",
@@ -134,9 +196,6 @@ impl Scan for FeroxbusterScan {
     }
 
     fn is_installed(&self) -> bool {
-        if let Ok(_) = which("feroxbuster"){
-            return true;
-        }
-        false
+        which("feroxbuster").is_ok()
     }
 }
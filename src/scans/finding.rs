@@ -0,0 +1,156 @@
+use std::{collections::HashMap, fmt};
+
+use chrono::{DateTime, Utc};
+use comfy_table::Color;
+use serde::{Deserialize, Serialize};
+
+///Severity shared by all scanners. Ordered from least to most severe so findings can be sorted and filtered.
+#[derive(Serialize, Deserialize, Debug, Clone, Copy, PartialEq, Eq, PartialOrd, Ord, Hash)]
+#[serde(rename_all = "lowercase")]
+pub enum Severity {
+    Info,
+    Low,
+    Medium,
+    High,
+    Critical,
+}
+
+impl Severity {
+    ///Parses the free-form severity strings that tools output. Anything unknown is treated as informational.
+    pub fn parse(severity: &str) -> Severity {
+        match severity.trim().to_lowercase().as_str() {
+            "low" => Severity::Low,
+            "medium" | "moderate" => Severity::Medium,
+            "high" => Severity::High,
+            "critical" => Severity::Critical,
+            _ => Severity::Info,
+        }
+    }
+
+    ///The CVSS v3 qualitative rating of a score. v2 scores are rated the same way, they're on the same scale.
+    pub fn from_cvss(score: f32) -> Severity {
+        match score {
+            score if score >= 9.0 => Severity::Critical,
+            score if score >= 7.0 => Severity::High,
+            score if score >= 4.0 => Severity::Medium,
+            score if score > 0.0 => Severity::Low,
+            _ => Severity::Info,
+        }
+    }
+
+    ///Color used for this severity in result tables.
+    pub fn color(&self) -> Color {
+        match self {
+            Severity::Info => Color::AnsiValue(33),
+            Severity::Low => Color::AnsiValue(28),
+            Severity::Medium => Color::AnsiValue(214),
+            Severity::High | Severity::Critical => Color::AnsiValue(196),
+        }
+    }
+}
+
+impl fmt::Display for Severity {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        match self {
+            Severity::Info => write!(f, "info"),
+            Severity::Low => write!(f, "low"),
+            Severity::Medium => write!(f, "medium"),
+            Severity::High => write!(f, "high"),
+            Severity::Critical => write!(f, "critical"),
+        }
+    }
+}
+
+///A single result of any scanner, normalized so reporting, deduplication and filtering don't need to know the tool.
+#[derive(Serialize, Deserialize, Debug, Clone)]
+pub struct Finding {
+    ///Stable identifier of the kind of finding, e.g. 'nuclei:tech-detect:wordpress'. Used for deduplication.
+    pub id: String,
+    pub title: String,
+    pub severity: Severity,
+    pub host: String,
+    pub port: Option<u16>,
+    pub url: Option<String>,
+    pub evidence: Vec<String>,
+    ///The tool that produced the finding.
+    pub source: String,
+    ///Where the raw result can be found, typically the tool's output file.
+    pub raw_reference: Option<String>,
+    pub first_seen: DateTime<Utc>,
+    pub last_seen: DateTime<Utc>,
+}
+
+impl Finding {
+    pub fn new(
+        id: String,
+        title: String,
+        severity: Severity,
+        host: String,
+        source: &str,
+    ) -> Finding {
+        let now = Utc::now();
+        Finding {
+            id,
+            title,
+            severity,
+            host,
+            port: None,
+            url: None,
+            evidence: Vec::new(),
+            source: source.to_string(),
+            raw_reference: None,
+            first_seen: now,
+            last_seen: now,
+        }
+    }
+
+    ///Two findings with the same key describe the same issue at the same location.
+    pub fn dedup_key(&self) -> String {
+        format!(
+            "{}|{}|{}|{}",
+            self.id,
+            self.host,
+            self.port.map(|port| port.to_string()).unwrap_or_default(),
+            self.url.as_deref().unwrap_or_default()
+        )
+    }
+}
+
+///Merges findings that describe the same issue, e.g. when several scans report the same thing.
+///The merged finding keeps the highest severity, all distinct evidence and the full time span it was seen in.
+///The result is sorted from most to least severe.
+pub fn dedup(findings: Vec<Finding>) -> Vec<Finding> {
+    let mut merged: Vec<Finding> = Vec::new();
+    let mut index: HashMap<String, usize> = HashMap::new();
+    for finding in findings {
+        let key = finding.dedup_key();
+        match index.get(&key) {
+            Some(&i) => {
+                let existing = &mut merged[i];
+                existing.severity = existing.severity.max(finding.severity);
+                existing.first_seen = existing.first_seen.min(finding.first_seen);
+                existing.last_seen = existing.last_seen.max(finding.last_seen);
+                for evidence in finding.evidence {
+                    if !existing.evidence.contains(&evidence) {
+                        existing.evidence.push(evidence);
+                    }
+                }
+            }
+            None => {
+                index.insert(key, merged.len());
+                merged.push(finding);
+            }
+        }
+    }
+
+    merged.sort_by_key(|finding| std::cmp::Reverse(finding.severity));
+    merged
+}
+
+///Keeps only the findings that are at least as severe as `minimum`.
+pub fn filter_severity(findings: Vec<Finding>, minimum: Severity) -> Vec<Finding> {
+    findings
+        .into_iter()
+        .filter(|finding| finding.severity >= minimum)
+        .collect()
+}
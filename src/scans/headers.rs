@@ -0,0 +1,611 @@
+use chrono::{DateTime, Utc};
+use comfy_table::{modifiers::UTF8_ROUND_CORNERS, presets::UTF8_FULL, Table};
+use serde::{Deserialize, Serialize};
+use std::{fs, path::PathBuf, time::Duration};
+use url::Url;
+use which::which;
+
+use super::{
+    error::ScanError,
+    finding::{Finding, Severity},
+    nuclei::NucleiScanResult,
+    process,
+    scan::Scan,
+};
+use crate::logger;
+
+///Security headers whose absence is reported, as named by the matchers of Nuclei's http-missing-security-headers template.
+pub const SECURITY_HEADERS: &[&str] = &[
+    "strict-transport-security",
+    "content-security-policy",
+    "x-frame-options",
+    "x-content-type-options",
+    "referrer-policy",
+    "permissions-policy",
+];
+///A single request, it should never take long.
+const DEFAULT_TIMEOUT: Duration = Duration::from_secs(60);
+
+///Replays the requests Nuclei flagged for missing security headers and checks which headers are really missing.
+///The responses are stored as evidence, so the finding can be reported as is.
+pub struct HeaderCheck {
+    ///The web service, e.g. 'https://example.com:8443'.
+    origin: String,
+    ///One per distinct method and URL, the headers can differ between pages of the same service.
+    replays: Vec<Replay>,
+    timeout: Duration,
+}
+
+///A single request Nuclei sent.
+struct Replay {
+    ///e.g. 'GET', taken from the raw request.
+    method: String,
+    url: String,
+    ///Lowercase header names Nuclei reported as missing.
+    reported: Vec<String>,
+    ///Nuclei's curl command, for the report.
+    curl_command: String,
+    ///`None` when the command couldn't be parsed, in which case only Nuclei's response is used.
+    curl_args: Option<Vec<String>>,
+    nuclei_response: String,
+    nuclei_timestamp: Option<DateTime<Utc>>,
+    evidence_file: PathBuf,
+}
+
+#[derive(Serialize, Deserialize, Debug)]
+pub struct HeaderCheckResult {
+    pub url: String,
+    ///Lowercase names of the security headers the response lacked.
+    pub missing: Vec<String>,
+    ///Reproduces the request, ready to paste into a shell.
+    pub curl_command: String,
+    ///When the response the result is based on was received.
+    pub recorded_at: Option<DateTime<Utc>>,
+    ///The raw response, headers included. `None` when the replay failed and Nuclei's response was used.
+    pub evidence_file: Option<PathBuf>,
+}
+
+impl HeaderCheck {
+    ///`results` are the http-missing-security-headers matches of a single web service.
+    pub fn new(output_dir: PathBuf, origin: &str, results: &[&NucleiScanResult]) -> HeaderCheck {
+        let mut replays: Vec<Replay> = Vec::new();
+        for result in results {
+            let url = result.matched_at().to_string();
+            let method = result
+                .request()
+                .split_whitespace()
+                .next()
+                .unwrap_or("GET")
+                .to_string();
+            //Nuclei reports every missing header of a request separately, all with the same request.
+            if let Some(replay) = replays
+                .iter_mut()
+                .find(|replay| replay.url == url && replay.method == method)
+            {
+                replay.reported.push(result.matcher_name.to_lowercase());
+                continue;
+            }
+
+            let curl_command = if result.curl_command().is_empty() {
+                //Without -omit-raw Nuclei always includes the command, this is for older versions.
+                format!("curl -X '{}' '{}'", method, url)
+            } else {
+                result.curl_command().to_string()
+            };
+            let curl_args = split_command(&curl_command)
+                .filter(|args| args.first().map(String::as_str) == Some("curl"))
+                .map(|args| args[1..].to_vec());
+            let file_name: String = format!("{}_{}", method, url)
+                .chars()
+                .map(|c| if c.is_ascii_alphanumeric() { c } else { '_' })
+                .collect();
+            let evidence_file = output_dir.join(format!(
+                "headers_{}_{}",
+                file_name,
+                Utc::now().format("%Y%m%dT%H%M%S")
+            ));
+
+            replays.push(Replay {
+                method,
+                url,
+                reported: vec![result.matcher_name.to_lowercase()],
+                curl_command,
+                curl_args,
+                nuclei_response: result.response().to_string(),
+                nuclei_timestamp: result.timestamp(),
+                evidence_file,
+            });
+        }
+
+        HeaderCheck {
+            origin: origin.to_string(),
+            replays,
+            timeout: DEFAULT_TIMEOUT,
+        }
+    }
+
+    ///Overrides the default timeout, if one is given.
+    pub fn with_timeout(mut self, timeout: Option<Duration>) -> HeaderCheck {
+        if let Some(timeout) = timeout {
+            self.timeout = timeout;
+        }
+        self
+    }
+}
+
+impl Replay {
+    ///Nuclei's arguments, plus what it takes to write the full response to the evidence file.
+    fn replay_args(&self) -> Option<Vec<String>> {
+        let mut args = vec![
+            String::from("-s"),
+            String::from("-k"),
+            String::from("-i"),
+            String::from("-o"),
+            self.evidence_file.to_str()?.to_string(),
+        ];
+        args.extend(self.curl_args.clone()?);
+        Some(args)
+    }
+
+    fn run(&self, timeout: Duration) {
+        match self.replay_args() {
+            Some(args) if which("curl").is_ok() => {
+                logger::print_ok(&format!("Command used: curl {}", args.join(" ")));
+                if let Err(err) = process::execute("curl", &args, &self.evidence_file, timeout) {
+                    logger::print_warn(&format!(
+                        "{} Falling back to the response Nuclei recorded.",
+                        err
+                    ));
+                }
+            }
+            Some(_) => logger::print_warn(
+                "curl is not installed, falling back to the response Nuclei recorded.",
+            ),
+            None => logger::print_warn(&format!(
+                "Unable to parse Nuclei's curl command for {}, falling back to the response it recorded.",
+                self.url
+            )),
+        }
+    }
+
+    ///`None` when the response has all security headers after all.
+    fn result(&self) -> Option<HeaderCheckResult> {
+        let replayed = fs::read(&self.evidence_file)
+            .ok()
+            .filter(|bytes| !bytes.is_empty());
+        let (response, recorded_at, evidence_file) = match replayed {
+            Some(bytes) => (
+                String::from_utf8_lossy(&bytes).to_string(),
+                fs::metadata(&self.evidence_file)
+                    .and_then(|metadata| metadata.modified())
+                    .ok()
+                    .map(DateTime::<Utc>::from),
+                Some(self.evidence_file.clone()),
+            ),
+            None => (self.nuclei_response.clone(), self.nuclei_timestamp, None),
+        };
+
+        let missing = missing_headers(&self.url, &response, &self.reported);
+        if missing.is_empty() {
+            return None;
+        }
+        Some(HeaderCheckResult {
+            url: self.url.clone(),
+            missing,
+            curl_command: self.curl_command.clone(),
+            recorded_at,
+            evidence_file,
+        })
+    }
+}
+
+impl Scan for HeaderCheck {
+    type ScanResult = HeaderCheckResult;
+
+    fn run(&self) -> Result<Vec<HeaderCheckResult>, ScanError> {
+        logger::print_ok(&format!("Checking security headers of {}...", self.origin));
+        for replay in &self.replays {
+            replay.run(self.timeout);
+        }
+
+        let results = self.parse_output();
+        self.print_results(&results);
+        Ok(results)
+    }
+
+    ///Only the requests whose response lacks security headers after all.
+    fn parse_output(&self) -> Vec<HeaderCheckResult> {
+        self.replays.iter().filter_map(Replay::result).collect()
+    }
+
+    fn print_results(&self, results: &[HeaderCheckResult]) {
+        if results.is_empty() {
+            logger::print_ok(&format!("{} has all security headers.", self.origin));
+            return;
+        }
+
+        let mut table = Table::new();
+        table
+            .load_preset(UTF8_FULL)
+            .apply_modifier(UTF8_ROUND_CORNERS)
+            .set_header(vec!["URL", "Missing headers", "Evidence"]);
+        for result in results {
+            table.add_row(vec![
+                result.url.clone(),
+                result
+                    .missing
+                    .iter()
+                    .map(|header| display_name(header))
+                    .collect::<Vec<String>>()
+                    .join("\n"),
+                result
+                    .evidence_file
+                    .as_ref()
+                    .map(|file| file.display().to_string())
+                    .unwrap_or_else(|| String::from("Nuclei's response")),
+            ]);
+        }
+        println!("{}", table);
+    }
+
+    ///One finding per web service, instead of one per header.
+    fn to_findings(&self, results: &[HeaderCheckResult]) -> Vec<Finding> {
+        if results.is_empty() {
+            return Vec::new();
+        }
+
+        //Every header that is missing from any of the responses.
+        let mut missing: Vec<&str> = Vec::new();
+        for header in results.iter().flat_map(|result| &result.missing) {
+            if !missing.contains(&header.as_str()) {
+                missing.push(header);
+            }
+        }
+        let names: Vec<String> = missing.iter().map(|header| display_name(header)).collect();
+        let url = Url::parse(&self.origin).ok();
+        let mut finding = Finding::new(
+            String::from("headers:missing-security-headers"),
+            format!("Missing security headers: {}", names.join(", ")),
+            Severity::Low,
+            url.as_ref()
+                .and_then(|url| url.host_str().map(String::from))
+                .unwrap_or_default(),
+            "headers",
+        );
+        finding.port = url.as_ref().and_then(Url::port_or_known_default);
+        finding.url = Some(self.origin.clone());
+        for result in results {
+            let names: Vec<String> = result
+                .missing
+                .iter()
+                .map(|header| display_name(header))
+                .collect();
+            finding
+                .evidence
+                .push(format!("Missing {} at {}", names.join(", "), result.url));
+            finding
+                .evidence
+                .push(format!("Reproduce with: {}", result.curl_command));
+            let recorded_at = result
+                .recorded_at
+                .map(|recorded_at| recorded_at.to_rfc3339())
+                .unwrap_or_else(|| String::from("an unknown time"));
+            match &result.evidence_file {
+                Some(file) => {
+                    finding.evidence.push(format!(
+                        "Response recorded at {} in {}",
+                        recorded_at,
+                        file.display()
+                    ));
+                    finding
+                        .raw_reference
+                        .get_or_insert_with(|| file.display().to_string());
+                }
+                None => finding
+                    .evidence
+                    .push(format!("Response recorded by Nuclei at {}", recorded_at)),
+            }
+        }
+        vec![finding]
+    }
+
+    fn print_command(&self) {
+        for args in self.replays.iter().filter_map(Replay::replay_args) {
+            logger::print_ok(&format!("Command used: curl {}", args.join(" ")));
+        }
+    }
+
+    fn is_installed(&self) -> bool {
+        which("curl").is_ok()
+    }
+}
+
+///Whether the finding is one of Nuclei's per-header findings that a `HeaderCheck` of one of the origins replaced.
+///Headers the check doesn't cover stay as Nuclei reported them.
+pub fn replaced_by_check(finding: &Finding, origins: &[&str]) -> bool {
+    let Some(header) = finding
+        .id
+        .strip_prefix("nuclei:http-missing-security-headers:")
+    else {
+        return false;
+    };
+    SECURITY_HEADERS.contains(&header)
+        && finding
+            .url
+            .as_deref()
+            .and_then(|url| Url::parse(url).ok())
+            .is_some_and(|url| origins.contains(&url.origin().ascii_serialization().as_str()))
+}
+
+///The security headers the response lacks. Without a response to check, Nuclei has the last word.
+fn missing_headers(url: &str, response: &str, reported: &[String]) -> Vec<String> {
+    let mut missing: Vec<String> = if response.is_empty() {
+        reported.to_vec()
+    } else {
+        let present = response_headers(response);
+        SECURITY_HEADERS
+            .iter()
+            .filter(|header| !present.iter().any(|present| present == *header))
+            .map(|header| header.to_string())
+            .collect()
+    };
+    //Browsers ignore HSTS over plain HTTP, so it's only missing on HTTPS.
+    if Url::parse(url).is_ok_and(|url| url.scheme() == "http") {
+        missing.retain(|header| header != "strict-transport-security");
+    }
+    missing
+}
+
+///Lowercase names of the headers of the final response. With a '100 Continue' first, there are several.
+fn response_headers(response: &str) -> Vec<String> {
+    let mut headers = Vec::new();
+    let mut in_headers = false;
+    for line in response.lines().map(|line| line.trim_end_matches('\r')) {
+        if line.starts_with("HTTP/") {
+            headers.clear();
+            in_headers = true;
+        } else if line.is_empty() {
+            in_headers = false;
+        } else if !in_headers {
+            //The body.
+            break;
+        } else if let Some((name, _)) = line.split_once(':') {
+            headers.push(name.trim().to_lowercase());
+        }
+    }
+    headers
+}
+
+///'x-frame-options' -> 'X-Frame-Options', as it's usually written in reports.
+fn display_name(header: &str) -> String {
+    header
+        .split('-')
+        .map(|part| {
+            let mut chars = part.chars();
+            match chars.next() {
+                Some(first) => first.to_uppercase().chain(chars).collect(),
+                None => String::new(),
+            }
+        })
+        .collect::<Vec<String>>()
+        .join("-")
+}
+
+///Splits a command line the way a POSIX shell would, as far as quotes and backslashes go.
+///Nuclei single-quotes every argument. Returns `None` for unbalanced quotes.
+fn split_command(command: &str) -> Option<Vec<String>> {
+    let mut args = Vec::new();
+    let mut current: Option<String> = None;
+    let mut chars = command.chars();
+    while let Some(c) = chars.next() {
+        match c {
+            '\'' => {
+                let arg = current.get_or_insert_with(String::new);
+                loop {
+                    match chars.next()? {
+                        '\'' => break,
+                        c => arg.push(c),
+                    }
+                }
+            }
+            '"' => {
+                let arg = current.get_or_insert_with(String::new);
+                loop {
+                    match chars.next()? {
+                        '"' => break,
+                        '\\' => arg.push(chars.next()?),
+                        c => arg.push(c),
+                    }
+                }
+            }
+            '\\' => current.get_or_insert_with(String::new).push(chars.next()?),
+            c if c.is_whitespace() => {
+                if let Some(arg) = current.take() {
+                    args.push(arg);
+                }
+            }
+            c => current.get_or_insert_with(String::new).push(c),
+        }
+    }
+    args.extend(current);
+    Some(args)
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn nuclei_result(matched_at: &str, request: &str, header: &str) -> NucleiScanResult {
+        serde_json::from_value(serde_json::json!({
+            "info": {"name": "HTTP Missing Security Headers", "severity": "info"},
+            "template-id": "http-missing-security-headers",
+            "type": "http",
+            "matcher-name": header,
+            "matched-at": matched_at,
+            "curl-command": format!("curl -X '{}' -H 'User-Agent: Nuclei' '{}'", request, matched_at),
+            "request": format!("{} / HTTP/1.1\r\nHost: example.com\r\n\r\n", request),
+        }))
+        .unwrap()
+    }
+
+    #[test]
+    fn splits_single_quoted_arguments() {
+        assert_eq!(
+            split_command(
+                "curl -X 'GET' -H 'User-Agent: Mozilla/5.0 (X11)' 'https://example.com/'"
+            ),
+            Some(vec![
+                String::from("curl"),
+                String::from("-X"),
+                String::from("GET"),
+                String::from("-H"),
+                String::from("User-Agent: Mozilla/5.0 (X11)"),
+                String::from("https://example.com/"),
+            ])
+        );
+    }
+
+    #[test]
+    fn splits_double_quotes_and_backslashes() {
+        assert_eq!(
+            split_command(r#"curl -d "a \"quoted\" value" it\'s  '' x'y'z"#),
+            Some(vec![
+                String::from("curl"),
+                String::from("-d"),
+                String::from(r#"a "quoted" value"#),
+                String::from("it's"),
+                String::new(),
+                String::from("xyz"),
+            ])
+        );
+    }
+
+    #[test]
+    fn unbalanced_quotes_are_rejected() {
+        assert_eq!(split_command("curl 'https://example.com/"), None);
+        assert_eq!(split_command("curl \"https://example.com/"), None);
+        assert_eq!(split_command("curl https://example.com/\\"), None);
+    }
+
+    #[test]
+    fn response_headers_are_lowercased_without_the_body() {
+        let response = "HTTP/1.1 200 OK\r\nContent-Type: text/html\r\nX-Frame-Options: DENY\r\n\r\n<p>X-Content-Type-Options: nosniff</p>\r\n";
+        assert_eq!(
+            response_headers(response),
+            vec![
+                String::from("content-type"),
+                String::from("x-frame-options")
+            ]
+        );
+    }
+
+    #[test]
+    fn response_headers_of_the_final_response_only() {
+        let response = "HTTP/1.1 100 Continue\r\nX-Frame-Options: DENY\r\n\r\nHTTP/1.1 200 OK\r\nReferrer-Policy: no-referrer\r\n\r\nbody";
+        assert_eq!(
+            response_headers(response),
+            vec![String::from("referrer-policy")]
+        );
+    }
+
+    #[test]
+    fn present_headers_are_not_missing() {
+        let response = "HTTP/2 200\nstrict-transport-security: max-age=31536000\ncontent-security-policy: default-src 'self'\nx-frame-options: DENY\nx-content-type-options: nosniff\n\n";
+        assert_eq!(
+            missing_headers("https://example.com/", response, &[]),
+            vec![
+                String::from("referrer-policy"),
+                String::from("permissions-policy")
+            ]
+        );
+    }
+
+    #[test]
+    fn hsts_is_not_missing_over_http() {
+        let reported = vec![
+            String::from("strict-transport-security"),
+            String::from("x-frame-options"),
+        ];
+        assert_eq!(
+            missing_headers("http://example.com/", "", &reported),
+            vec![String::from("x-frame-options")]
+        );
+    }
+
+    #[test]
+    fn replays_each_distinct_request_once() {
+        let results = [
+            nuclei_result("https://example.com/", "GET", "x-frame-options"),
+            nuclei_result("https://example.com/", "GET", "referrer-policy"),
+            nuclei_result("https://example.com/login", "GET", "x-frame-options"),
+            nuclei_result("https://example.com/", "POST", "x-frame-options"),
+        ];
+        let results: Vec<&NucleiScanResult> = results.iter().collect();
+        let check = HeaderCheck::new(PathBuf::from("/tmp"), "https://example.com", &results);
+        let replays: Vec<(&str, &str, usize)> = check
+            .replays
+            .iter()
+            .map(|replay| {
+                (
+                    replay.method.as_str(),
+                    replay.url.as_str(),
+                    replay.reported.len(),
+                )
+            })
+            .collect();
+        assert_eq!(
+            replays,
+            [
+                ("GET", "https://example.com/", 2),
+                ("GET", "https://example.com/login", 1),
+                ("POST", "https://example.com/", 1),
+            ]
+        );
+        assert_ne!(
+            check.replays[0].evidence_file,
+            check.replays[2].evidence_file
+        );
+    }
+
+    #[test]
+    fn checked_headers_replace_nuclei_findings_of_the_origin() {
+        let finding = |id: &str, url: &str| {
+            let mut finding = Finding::new(
+                id.to_string(),
+                String::new(),
+                Severity::Info,
+                String::from("example.com"),
+                "nuclei",
+            );
+            finding.url = Some(url.to_string());
+            finding
+        };
+        let origins = ["https://example.com"];
+        assert!(replaced_by_check(
+            &finding(
+                "nuclei:http-missing-security-headers:x-frame-options",
+                "https://example.com/login"
+            ),
+            &origins
+        ));
+        assert!(!replaced_by_check(
+            &finding(
+                "nuclei:http-missing-security-headers:x-frame-options",
+                "https://example.com:8443/"
+            ),
+            &origins
+        ));
+        //Not one of the headers the check covers.
+        assert!(!replaced_by_check(
+            &finding(
+                "nuclei:http-missing-security-headers:clear-site-data",
+                "https://example.com/"
+            ),
+            &origins
+        ));
+        assert!(!replaced_by_check(
+            &finding("nuclei:tech-detect:nginx", "https://example.com/"),
+            &origins
+        ));
+    }
+}
@@ -1,5 +1,15 @@
-pub mod nmap;
-pub mod scan;
+pub mod cve;
+pub mod error;
 pub mod feroxbuster;
+pub mod finding;
+pub mod headers;
+pub mod nmap;
+pub mod nmap_script;
+pub mod nse;
 pub mod nuclei;
-pub mod error;
\ No newline at end of file
+pub mod nvd;
+pub mod ping_sweep;
+pub mod process;
+pub mod scan;
+pub mod smb;
+pub mod udp;
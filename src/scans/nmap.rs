@@ -1,24 +1,161 @@
 use std::{
     fmt,
-    fs::File,
-    io::BufReader,
-    path::PathBuf,
-    process::{Command, Stdio},
+    fs::{self, File},
+    io::{BufReader, Read},
+    net::Ipv6Addr,
+    path::{Path, PathBuf},
+    time::{Duration, Instant},
 };
 
-use super::{scan::Scan, error::ScanError};
+use super::{
+    error::ScanError,
+    finding::{Finding, Severity},
+    nse, process,
+    scan::Scan,
+};
 use crate::logger;
 use comfy_table::{modifiers::UTF8_ROUND_CORNERS, presets::UTF8_FULL, Table};
+use serde::{Deserialize, Serialize};
+use which::which;
 use xml::reader::XmlEvent;
 
+///Applies to each phase. Scanning all 65535 ports or running -sV -sC on many open ports can take a while on slow hosts.
+const DEFAULT_TIMEOUT: Duration = Duration::from_secs(2 * 60 * 60);
+
 pub struct NmapScan {
+    target: String,
+    ///XML output of the service detection phase.
     output_file: PathBuf,
-    scan_args: Vec<String>,
+    raw_output_file: PathBuf,
+    ///XML output of the port discovery phase.
+    discovery_file: PathBuf,
+    no_ping: bool,
+    ///Only scan this port instead of all of them.
+    port: Option<u16>,
+    timeout: Duration,
+}
+
+///The parsed Nmap XML document (-oX).
+#[derive(Serialize, Deserialize, Default)]
+pub struct NmapRun {
+    ///The command line Nmap was started with.
+    pub args: String,
+    pub version: String,
+    pub hosts: Vec<NmapHost>,
+    ///Hosts as they were found during host discovery, before Nmap decided whether to scan them.
+    ///Only the status, addresses and hostnames are set.
+    pub hosthints: Vec<NmapHost>,
+    pub runstats: RunStats,
+}
+
+impl NmapRun {
+    ///Whether Nmap considered the target down after host discovery, which is usually a firewall dropping the ping probes.
+    ///An empty document (e.g. Nmap never got to write it) is not proof of anything, so it's not considered down.
+    pub fn host_seems_down(&self) -> bool {
+        let is_up = |host: &NmapHost| host.status.state == "up";
+        let is_down = |host: &NmapHost| host.status.state == "down";
+        if self.runstats.hosts_up > 0 || self.hosts.iter().any(is_up) {
+            return false;
+        }
+        self.runstats.hosts_down > 0
+            || self.hosts.iter().any(is_down)
+            || self.hosthints.iter().any(is_down)
+    }
+}
+
+#[derive(Serialize, Deserialize, Default)]
+pub struct RunStats {
+    ///'success' or 'error'.
+    pub exit: String,
+    pub elapsed_secs: f64,
+    pub summary: String,
+    pub hosts_up: u32,
+    pub hosts_down: u32,
+    pub hosts_total: u32,
+}
+
+///A single `<host>` of the Nmap XML.
+#[derive(Serialize, Deserialize, Default)]
+pub struct NmapHost {
+    pub status: HostStatus,
+    pub addresses: Vec<Address>,
+    pub hostnames: Vec<Hostname>,
+    pub ports: Vec<NmapScanResult>,
+    ///OS detection guesses (-O), most accurate first.
+    pub os_matches: Vec<OsMatch>,
+    ///Output of the NSE scripts that run against the host rather than a port, e.g. smb2-security-mode.
+    pub scripts: Vec<ScriptOutput>,
+}
+
+#[derive(Serialize, Deserialize, Default)]
+pub struct HostStatus {
+    ///'up', 'down' or 'unknown'.
+    pub state: String,
+    ///Why Nmap thinks so, e.g. 'echo-reply', 'user-set' or 'no-response'.
+    pub reason: String,
+}
+
+#[derive(Serialize, Deserialize, Default)]
+pub struct Address {
+    pub addr: String,
+    ///'ipv4', 'ipv6' or 'mac'.
+    pub addrtype: String,
+    ///Only set for MAC addresses.
+    pub vendor: String,
+}
+
+#[derive(Serialize, Deserialize, Default)]
+pub struct Hostname {
+    pub name: String,
+    ///'user' for the name that was scanned, 'PTR' for reverse DNS.
+    pub kind: String,
+}
+
+#[derive(Serialize, Deserialize, Default)]
+pub struct OsMatch {
+    pub name: String,
+    ///Percentage, 0-100.
+    pub accuracy: u8,
+    pub cpes: Vec<String>,
+}
+
+impl NmapHost {
+    ///The name the host was scanned as, falling back to its first address.
+    pub fn name(&self) -> String {
+        self.hostnames
+            .iter()
+            .find(|hostname| hostname.kind == "user")
+            .map(|hostname| hostname.name.clone())
+            .or_else(|| self.addresses.first().map(|address| address.addr.clone()))
+            .unwrap_or_default()
+    }
 }
 
+#[derive(Serialize, Deserialize, Debug, Default, Clone, Copy, PartialEq, Eq)]
+#[serde(rename_all = "lowercase")]
+pub enum Protocol {
+    #[default]
+    Tcp,
+    Udp,
+    Sctp,
+}
+
+impl fmt::Display for Protocol {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        match self {
+            Protocol::Tcp => write!(f, "tcp"),
+            Protocol::Udp => write!(f, "udp"),
+            Protocol::Sctp => write!(f, "sctp"),
+        }
+    }
+}
+
+#[derive(Serialize, Deserialize, Default)]
+#[serde(rename_all = "lowercase")]
 pub enum PortState {
     Open,
     Filtered,
+    #[default]
     None,
 }
 
@@ -39,194 +176,936 @@ impl fmt::Display for PortState {
     }
 }
 
+#[derive(Serialize, Deserialize, Default)]
 pub struct Port {
-    pub num: String,
+    pub num: u16,
+    pub protocol: Protocol,
     pub state: PortState,
+    ///Why Nmap decided on the state, e.g. 'syn-ack'.
+    pub reason: String,
 }
 
 //Has to be public because a Vec of this struct is returned.
+#[derive(Serialize, Deserialize, Default)]
 pub struct NmapScanResult {
     pub port: Port,
-    service_name: String,
-    service_version: String,
+    ///Service name as reported by Nmap, e.g. 'http', 'microsoft-ds' or 'ssh'.
+    pub service_name: String,
+    pub service_product: String,
+    pub service_version: String,
+    ///Anything else the version detection found, e.g. 'Ubuntu Linux; protocol 2.0'.
+    pub service_extrainfo: String,
+    ///Set to 'ssl' by Nmap when the service is wrapped in SSL/TLS.
+    pub service_tunnel: String,
+    ///CPE names of the detected service, e.g. 'cpe:/a:openbsd:openssh:8.9p1'.
+    pub service_cpes: Vec<String>,
+    ///Output of the NSE scripts (-sC) that ran against this port.
+    pub scripts: Vec<ScriptOutput>,
+}
+
+#[derive(Serialize, Deserialize)]
+pub struct ScriptOutput {
+    pub id: String,
+    ///The human readable output, as shown in Nmap's normal output.
+    pub output: String,
+    ///The structured output. Not every script has one.
+    pub elements: Vec<ScriptElement>,
+}
+
+///Structured NSE script output: `<elem>` values and `<table>`s of them, which can be nested.
+#[derive(Serialize, Deserialize)]
+#[serde(tag = "type", rename_all = "lowercase")]
+pub enum ScriptElement {
+    Elem {
+        key: Option<String>,
+        value: String,
+    },
+    Table {
+        key: Option<String>,
+        elements: Vec<ScriptElement>,
+    },
+}
+
+impl ScriptElement {
+    pub fn key(&self) -> Option<&str> {
+        match self {
+            ScriptElement::Elem { key, .. } | ScriptElement::Table { key, .. } => key.as_deref(),
+        }
+    }
+
+    ///The value of an `<elem>`, `None` for tables.
+    pub fn value(&self) -> Option<&str> {
+        match self {
+            ScriptElement::Elem { value, .. } => Some(value),
+            ScriptElement::Table { .. } => None,
+        }
+    }
+
+    ///The children of a `<table>`, empty for elems.
+    pub fn elements(&self) -> &[ScriptElement] {
+        match self {
+            ScriptElement::Elem { .. } => &[],
+            ScriptElement::Table { elements, .. } => elements,
+        }
+    }
+}
+
+///Looks up the `<elem>` with the given key among `elements`.
+pub fn find_elem<'a>(elements: &'a [ScriptElement], key: &str) -> Option<&'a str> {
+    elements
+        .iter()
+        .filter(|element| element.key() == Some(key))
+        .find_map(ScriptElement::value)
+}
+
+///Looks up the children of the `<table>` with the given key among `elements`.
+pub fn find_table<'a>(elements: &'a [ScriptElement], key: &str) -> Option<&'a [ScriptElement]> {
+    elements
+        .iter()
+        .find(|element| {
+            element.key() == Some(key) && matches!(element, ScriptElement::Table { .. })
+        })
+        .map(ScriptElement::elements)
+}
+
+impl ScriptOutput {
+    ///The value of the top-level `<elem>` with the given key.
+    pub fn elem(&self, key: &str) -> Option<&str> {
+        find_elem(&self.elements, key)
+    }
+
+    ///The children of the top-level `<table>` with the given key.
+    pub fn table(&self, key: &str) -> Option<&[ScriptElement]> {
+        find_table(&self.elements, key)
+    }
+}
+
+impl NmapScanResult {
+    ///Whether the service speaks SSL/TLS, either through a tunnel or because the service name says so.
+    pub fn is_tls(&self) -> bool {
+        self.service_tunnel == "ssl"
+            || self.service_name.starts_with("ssl")
+            || self.service_name.starts_with("https")
+    }
+
+    ///DNS names from the 'Subject Alternative Name' of the certificate reported by the ssl-cert script.
+    pub fn certificate_dns_names(&self) -> Vec<String> {
+        self.scripts
+            .iter()
+            .filter(|script| script.id == "ssl-cert")
+            .flat_map(|script| script.output.lines())
+            .filter_map(|line| line.trim().strip_prefix("Subject Alternative Name:"))
+            .flat_map(|names| names.split(','))
+            .filter_map(|name| name.trim().strip_prefix("DNS:"))
+            .map(String::from)
+            .collect()
+    }
 }
 
 impl NmapScan {
-    pub fn new(mut output_dir: PathBuf, target: String) -> NmapScan {
-        //Create a separate file for the raw output
-        let mut output_file_raw = output_dir.clone();
-        output_file_raw.push("nmap_raw");
+    ///`no_ping` passes -Pn, which makes Nmap treat the host as online and skip host discovery.
+    pub fn new(output_dir: PathBuf, target: String, no_ping: bool) -> NmapScan {
+        NmapScan {
+            target,
+            output_file: output_dir.join("nmap_xml"),
+            raw_output_file: output_dir.join("nmap_raw"),
+            discovery_file: output_dir.join("nmap_discovery_xml"),
+            no_ping,
+            port: None,
+            timeout: DEFAULT_TIMEOUT,
+        }
+    }
 
-        //Create a separate file for the XML output
-        output_dir.push("nmap_xml");
+    ///Only parses an existing XML file, e.g. from a previous run. Calling `run()` on this is pointless.
+    pub fn from_output_file(output_file: PathBuf) -> NmapScan {
         NmapScan {
-            output_file: output_dir.clone(), //Clone here because the .to_str()'s below need it as well.
-            scan_args: vec![
-                String::from("-sV"),
-                String::from("-sC"),
-                String::from("-oX"),
-                output_dir.to_str().unwrap().to_string(),
-                String::from("-oN"),
-                output_file_raw.to_str().unwrap().to_string(),
-                target,
-            ],
+            target: String::new(),
+            output_file,
+            raw_output_file: PathBuf::new(),
+            discovery_file: PathBuf::new(),
+            no_ping: false,
+            port: None,
+            timeout: DEFAULT_TIMEOUT,
         }
     }
-}
 
-impl Scan for NmapScan {
-    type ScanResult = NmapScanResult;
+    ///Phase one: find every open TCP port, but don't bother with what runs on it yet.
+    ///RustScan is a lot faster at this, so it's used when it's installed. It's not worth it for a single port.
+    fn discovery_command(&self) -> (&'static str, Vec<String>) {
+        if self.port.is_none() && which("rustscan").is_ok() {
+            let args = vec![
+                String::from("-a"),
+                self.target.clone(),
+                String::from("-g"), //Greppable output: '10.0.0.1 -> [22,80]'
+                String::from("--ulimit"),
+                String::from("5000"),
+            ];
+            return ("rustscan", args);
+        }
 
-    fn run(&self) -> Result<Vec<NmapScanResult>, ScanError> {
-        logger::print_ok("Running Nmap...");
-        self.print_command();
+        let ports = match self.port {
+            Some(port) => port.to_string(),
+            None => String::from("-"),
+        };
+        let mut args = vec![
+            format!("-p{}", ports),
+            String::from("-T4"),
+            String::from("--min-rate"),
+            String::from("1000"),
+            String::from("--max-retries"),
+            String::from("2"),
+            String::from("-oX"),
+            self.discovery_file.to_str().unwrap().to_string(),
+        ];
+        if self.no_ping {
+            args.push(String::from("-Pn"));
+        }
+        args.extend(target_args(&self.target));
+        ("nmap", args)
+    }
 
-        // match Command::new("nmap")
-        //     .stdout(Stdio::null())
-        //     .stderr(Stdio::null())
-        //     .args(&self.scan_args)
-        //     .spawn()
-        // {
-        //     Ok(mut child) => {
-        //         if let Ok(_) = child.wait() {
-                    //Nmap ran successfully.
-                    let results = self.parse_output();
-                    self.print_results(&results);
-                    return Ok(results);
-            //     } else {
-            //         return Err(ScanError::Runtime("nmap".to_string()));
-            //     }
-            // }
-            // Err(err) => {
-            //     logger::print_err(&err.to_string());
-            //     return Err(ScanError::Runtime("nmap".to_string()));
-            // }
-        // }
-    }
-
-    fn parse_output(&self) -> Vec<NmapScanResult> {
-        println!("Now parsing nmap output");
-        //Open the XML file and create a buffered reader for the XML reader.
-        let file = File::open(&self.output_file).unwrap();
-        let buf_reader = BufReader::new(file);
-        let xml_reader = xml::EventReader::new(buf_reader);
-
-        //Create an object that will constantly be updated
-        let mut scan_results: Vec<NmapScanResult> = Vec::new();
-        let mut scan_result = NmapScanResult {
-            port: Port {
-                num: String::from(""),
-                state: PortState::None,
-            },
-            service_name: String::from(""),
-            service_version: String::from(""),
+    ///Phase two: version detection and default scripts, only against the ports that are known to be open.
+    fn service_args(&self, ports: &[u16]) -> Vec<String> {
+        let mut args = vec![
+            String::from("-sV"),
+            String::from("-sC"),
+            String::from("-p"),
+            ports
+                .iter()
+                .map(u16::to_string)
+                .collect::<Vec<String>>()
+                .join(","),
+            String::from("-oX"),
+            self.output_file.to_str().unwrap().to_string(),
+            String::from("-oN"),
+            self.raw_output_file.to_str().unwrap().to_string(),
+            //The discovery already showed the host is up, no need to find that out again.
+            String::from("-Pn"),
+        ];
+        args.extend(target_args(&self.target));
+        args
+    }
+
+    ///Runs the discovery phase and returns the open ports, plus the hosts if Nmap did the discovery.
+    fn discover(&self) -> Result<(Vec<u16>, Vec<NmapHost>), ScanError> {
+        let (tool, args) = self.discovery_command();
+        if tool == "rustscan" {
+            let log_base = self.discovery_file.with_file_name("rustscan");
+            let output = process::execute(tool, &args, &log_base, self.timeout)?;
+            let mut ports: Vec<u16> = fs::read_to_string(&output.stdout_log)
+                .unwrap_or_default()
+                .lines()
+                .filter_map(|line| line.split_once("->"))
+                .flat_map(|(_, ports)| {
+                    ports
+                        .trim()
+                        .trim_start_matches('[')
+                        .trim_end_matches(']')
+                        .split(',')
+                        .filter_map(|port| port.trim().parse().ok())
+                        .collect::<Vec<u16>>()
+                })
+                .collect();
+            ports.sort_unstable();
+            ports.dedup();
+            return Ok((ports, Vec::new()));
+        }
+
+        process::execute(tool, &args, &self.discovery_file, self.timeout)?;
+        let hosts = match File::open(&self.discovery_file) {
+            Ok(file) => parse_xml(BufReader::new(file)).hosts,
+            Err(_) => Vec::new(),
         };
-        //TODO: This parsing logic can possibly be a lot simpler. Another crate could be used for this instead of the default XML reader.
-        //This parsing logic simply loops through ALL elements in the XML tree, and constructs NmapScanResults for entries on the fly.
-        for elem in xml_reader {
-            match elem {
-                Ok(XmlEvent::StartElement {
-                    name, attributes, ..
-                }) => {
-                    if name.local_name.eq("port") {
-                        for attr in &attributes {
-                            if attr.name.local_name.eq("portid") {
-                                scan_result.port.num.push_str(&attr.value);
-                            }
-                        }
-                    }
+        let mut ports: Vec<u16> = hosts
+            .iter()
+            .flat_map(|host| &host.ports)
+            .filter(|result| matches!(result.port.state, PortState::Open))
+            .map(|result| result.port.num)
+            .collect();
+        ports.sort_unstable();
+        ports.dedup();
+        Ok((ports, hosts))
+    }
 
-                    if name.local_name.eq("state") {
-                        for attr in &attributes {
-                            if attr.name.local_name.eq("state") {
-                                if attr.value == "open" {
-                                    scan_result.port.state = PortState::Open;
-                                } else {
-                                    //Just default to filtered because Nmap doesn't show closed ports...
-                                    scan_result.port.state = PortState::Filtered;
-                                }
-                            }
-                        }
-                    }
+    ///Parses the complete XML document. `parse_output()` only returns its hosts.
+    pub fn parse_document(&self) -> NmapRun {
+        parse_xml_file(&self.output_file)
+    }
 
-                    if name.local_name.eq("service") {
-                        for attr in attributes {
-                            if attr.name.local_name.eq("name") {
-                                scan_result.service_name.push_str(&attr.value);
-                            }
+    ///Whether the discovery phase found the target to be down. Always false after a discovery with RustScan, which doesn't ping.
+    pub fn host_seems_down(&self) -> bool {
+        self.discovery_file.exists() && parse_xml_file(&self.discovery_file).host_seems_down()
+    }
 
-                            if attr.name.local_name.eq("product") {
-                                if scan_result.service_name != "" {
-                                    scan_result.service_name.push_str(": ");
-                                }
-                                scan_result.service_name.push_str(&attr.value);
-                            }
+    pub fn output_file_exists(&self) -> bool {
+        self.output_file.exists()
+    }
 
-                            if attr.name.local_name.eq("version") {
-                                scan_result.service_version.push_str(&attr.value);
-                            }
-                        }
-                    }
-                }
+    ///Limits the scan to a single port, if one is given.
+    pub fn with_port(mut self, port: Option<u16>) -> NmapScan {
+        self.port = port;
+        self
+    }
 
-                Ok(XmlEvent::EndElement { name }) => {
-                    if name.local_name.eq("port") {
-                        scan_results.push(scan_result);
-                        //Reinstantiate the ScanResult object so it can form a new instance.
-                        scan_result = NmapScanResult {
-                            port: Port {
-                                num: String::from(""),
-                                state: PortState::None,
-                            },
-                            service_name: String::from(""),
-                            service_version: String::from(""),
-                        }
-                    }
-                }
-                Err(_) => {
-                    logger::print_err("Error reading Nmap XML entry");
-                }
+    ///Overrides the default timeout, if one is given.
+    pub fn with_timeout(mut self, timeout: Option<Duration>) -> NmapScan {
+        if let Some(timeout) = timeout {
+            self.timeout = timeout;
+        }
+        self
+    }
+}
 
-                _ => {}
+impl Scan for NmapScan {
+    type ScanResult = NmapHost;
+
+    fn run(&self) -> Result<Vec<NmapHost>, ScanError> {
+        match self.port {
+            Some(port) => {
+                logger::print_ok(&format!("Running port discovery against port {}...", port))
             }
+            None => logger::print_ok("Running port discovery against all TCP ports..."),
+        }
+        self.print_command();
+        let started = Instant::now();
+        let (ports, discovered_hosts) = self.discover()?;
+        logger::print_ok(&format!(
+            "Port discovery finished in {}s, {} open ports.",
+            started.elapsed().as_secs(),
+            ports.len()
+        ));
+        if ports.is_empty() {
+            self.print_results(&discovered_hosts);
+            return Ok(discovered_hosts);
         }
 
-        return scan_results;
+        logger::print_ok("Running Nmap service detection against the open ports...");
+        let scan_args = self.service_args(&ports);
+        logger::print_ok(&format!("Command used: nmap {}", scan_args.join(" ")));
+        let output = process::execute("nmap", &scan_args, &self.output_file, self.timeout)?;
+        logger::print_ok(&format!("Nmap finished in {}s.", output.duration.as_secs()));
+        let results = merge_hosts(discovered_hosts, self.parse_output());
+        self.print_results(&results);
+        Ok(results)
+    }
+
+    fn parse_output(&self) -> Vec<NmapHost> {
+        self.parse_document().hosts
+    }
+
+    fn print_results(&self, hosts: &[NmapHost]) {
+        print_hosts(hosts);
+    }
+
+    fn to_findings(&self, hosts: &[NmapHost]) -> Vec<Finding> {
+        host_findings(hosts, &self.output_file)
+    }
+
+    fn print_command(&self) {
+        let (tool, args) = self.discovery_command();
+        logger::print_ok(&format!("Command used: {} {}", tool, args.join(" ")));
+    }
+
+    /// NOT USED.
+    /// This function is just here because it's mandatory to implement according to the trait.
+    /// However, Nmap's installation is already checked in main.rs. If this function is reached, we know Nmap is installed already.
+    /// Thus: always return true here.
+    fn is_installed(&self) -> bool {
+        true
+    }
+}
+
+///Prints a port table per host. Shared by every scan that produces Nmap XML.
+pub fn print_hosts(hosts: &[NmapHost]) {
+    if hosts.iter().all(|host| host.ports.is_empty()) {
+        logger::print_warn("No open ports found.");
+        return;
     }
 
-    fn print_results(&self, scan_results: &[NmapScanResult]) {
-        if scan_results.len() < 1 {
-            logger::print_warn("No open ports found.");
-            return;
+    for host in hosts.iter().filter(|host| !host.ports.is_empty()) {
+        //Only worth mentioning when Nmap scanned more than one host.
+        if hosts.len() > 1 {
+            logger::print_ok(&format!("Results for {}:", host.name()));
         }
         let mut table = Table::new();
-
         table
             .load_preset(UTF8_FULL)
             .apply_modifier(UTF8_ROUND_CORNERS)
             .set_header(vec!["Port", "State", "Service", "Version"]);
 
-        for result in scan_results {
+        for result in &host.ports {
+            let mut service = result.service_name.clone();
+            if !result.service_tunnel.is_empty() {
+                service.insert_str(0, &format!("{}/", result.service_tunnel));
+            }
+            if !result.service_product.is_empty() {
+                service.push_str(&format!(": {}", result.service_product));
+            }
             table.add_row(vec![
-                &result.port.num,
+                &format!("{}/{}", result.port.num, result.port.protocol),
                 &result.port.state.to_string(),
-                &result.service_name,
+                &service,
                 &result.service_version,
             ]);
         }
 
         println!("{}", table);
+        if let Some(os_match) = host.os_matches.first() {
+            logger::print_ok(&format!(
+                "OS: {} ({}% accuracy)",
+                os_match.name, os_match.accuracy
+            ));
+        }
     }
+}
 
-    fn print_command(&self) {
-        logger::print_ok(&format!("Command used: nmap {}", self.scan_args.join(" ")));
+///Open ports and interpreted script output of every host. Shared by every scan that produces Nmap XML.
+pub fn host_findings(hosts: &[NmapHost], output_file: &Path) -> Vec<Finding> {
+    let raw_reference = output_file.display().to_string();
+    let mut findings = Vec::new();
+    for host in hosts {
+        let host_name = host.name();
+        for result in host
+            .ports
+            .iter()
+            .filter(|result| matches!(result.port.state, PortState::Open))
+        {
+            //The same port number can be open on TCP and UDP, those are different findings.
+            let (id, port) = match result.port.protocol {
+                Protocol::Tcp => (String::from("nmap:open-port"), result.port.num.to_string()),
+                protocol => (
+                    format!("nmap:open-port:{}", protocol),
+                    format!("{}/{}", result.port.num, protocol),
+                ),
+            };
+            let mut finding = Finding::new(
+                id,
+                format!("Open port {}: {}", port, result.service_name),
+                Severity::Info,
+                host_name.clone(),
+                "nmap",
+            );
+            finding.port = Some(result.port.num);
+            let version = format!("{} {}", result.service_product, result.service_version);
+            if !version.trim().is_empty() {
+                finding.evidence.push(version.trim().to_string());
+            }
+            findings.push(finding);
+
+            for script in &result.scripts {
+                findings.extend(nse::findings(script, &host_name, Some(result.port.num)));
+            }
+        }
+        for script in &host.scripts {
+            findings.extend(nse::findings(script, &host_name, None));
+        }
     }
 
-    /// NOT USED.
-    /// This function is just here because it's mandatory to implement according to the trait.
-    /// However, Nmap's installation is already checked in main.rs. If this function is reached, we know Nmap is installed already.
-    /// Thus: always return true here.
-    fn is_installed(&self) -> bool {
-        true
+    for finding in &mut findings {
+        finding.raw_reference = Some(raw_reference.clone());
+    }
+    findings
+}
+
+///The target as the last arguments of an Nmap command. Nmap only scans IPv6 addresses and ranges with -6.
+pub fn target_args(target: &str) -> Vec<String> {
+    let addr = target.split('/').next().unwrap_or(target);
+    if addr.parse::<Ipv6Addr>().is_ok() {
+        vec![String::from("-6"), target.to_string()]
+    } else {
+        vec![target.to_string()]
+    }
+}
+
+///Combines the hosts of both phases. The service detection knows more about every port,
+///but a port that only showed up during discovery (e.g. because it flapped) is kept as well.
+pub fn merge_hosts(discovered: Vec<NmapHost>, detected: Vec<NmapHost>) -> Vec<NmapHost> {
+    let address = |host: &NmapHost| host.addresses.first().map(|address| address.addr.clone());
+    let mut merged = detected;
+    for discovered_host in discovered {
+        match merged
+            .iter_mut()
+            .find(|host| address(host) == address(&discovered_host))
+        {
+            Some(host) => {
+                for result in discovered_host.ports {
+                    if !host.ports.iter().any(|known| {
+                        known.port.num == result.port.num
+                            && known.port.protocol == result.port.protocol
+                    }) {
+                        host.ports.push(result);
+                    }
+                }
+                host.ports.sort_by_key(|result| result.port.num);
+            }
+            None => merged.push(discovered_host),
+        }
+    }
+    merged
+}
+
+pub fn parse_xml_file(path: &Path) -> NmapRun {
+    match File::open(path) {
+        Ok(file) => parse_xml(BufReader::new(file)),
+        Err(_) => {
+            logger::print_err("Something went wrong when reading the nmap output.");
+            NmapRun::default()
+        }
+    }
+}
+
+///Builds the typed model from the XML event stream.
+///Elements can only appear in a handful of places, so tracking the element that is currently open per level is enough.
+pub fn parse_xml<R: Read>(reader: R) -> NmapRun {
+    let mut run = NmapRun::default();
+    let mut host: Option<NmapHost> = None;
+    let mut port: Option<NmapScanResult> = None;
+    let mut os_match: Option<OsMatch> = None;
+    let mut in_service = false;
+    let mut script: Option<ScriptOutput> = None;
+    //Open <table>s of the current script, innermost last.
+    let mut tables: Vec<(Option<String>, Vec<ScriptElement>)> = Vec::new();
+    //Key of the open <elem>, if any.
+    let mut elem_key: Option<Option<String>> = None;
+    //Text of the open <elem> or <cpe>.
+    let mut text = String::new();
+
+    for event in xml::EventReader::new(reader) {
+        match event {
+            Ok(XmlEvent::StartElement {
+                name, attributes, ..
+            }) => {
+                let attribute = |key: &str| {
+                    attributes
+                        .iter()
+                        .find(|attr| attr.name.local_name == key)
+                        .map(|attr| attr.value.clone())
+                        .unwrap_or_default()
+                };
+                text.clear();
+                match name.local_name.as_str() {
+                    "nmaprun" => {
+                        run.args = attribute("args");
+                        run.version = attribute("version");
+                    }
+                    "host" | "hosthint" => host = Some(NmapHost::default()),
+                    "status" => {
+                        if let Some(host) = host.as_mut() {
+                            host.status = HostStatus {
+                                state: attribute("state"),
+                                reason: attribute("reason"),
+                            };
+                        }
+                    }
+                    "address" => {
+                        if let Some(host) = host.as_mut() {
+                            host.addresses.push(Address {
+                                addr: attribute("addr"),
+                                addrtype: attribute("addrtype"),
+                                vendor: attribute("vendor"),
+                            });
+                        }
+                    }
+                    "hostname" => {
+                        if let Some(host) = host.as_mut() {
+                            host.hostnames.push(Hostname {
+                                name: attribute("name"),
+                                kind: attribute("type"),
+                            });
+                        }
+                    }
+                    "port" => {
+                        let mut result = NmapScanResult::default();
+                        result.port.num = attribute("portid").parse().unwrap_or_default();
+                        result.port.protocol = match attribute("protocol").as_str() {
+                            "udp" => Protocol::Udp,
+                            "sctp" => Protocol::Sctp,
+                            _ => Protocol::Tcp,
+                        };
+                        port = Some(result);
+                    }
+                    "state" => {
+                        if let Some(port) = port.as_mut() {
+                            port.port.state = if attribute("state") == "open" {
+                                PortState::Open
+                            } else {
+                                //Just default to filtered because Nmap doesn't show closed ports...
+                                PortState::Filtered
+                            };
+                            port.port.reason = attribute("reason");
+                        }
+                    }
+                    "service" => {
+                        if let Some(port) = port.as_mut() {
+                            in_service = true;
+                            port.service_name = attribute("name");
+                            port.service_product = attribute("product");
+                            port.service_version = attribute("version");
+                            port.service_extrainfo = attribute("extrainfo");
+                            port.service_tunnel = attribute("tunnel");
+                        }
+                    }
+                    "osmatch" => {
+                        os_match = Some(OsMatch {
+                            name: attribute("name"),
+                            accuracy: attribute("accuracy").parse().unwrap_or_default(),
+                            cpes: Vec::new(),
+                        })
+                    }
+                    "script" => {
+                        tables.clear();
+                        script = Some(ScriptOutput {
+                            id: attribute("id"),
+                            output: attribute("output"),
+                            elements: Vec::new(),
+                        });
+                    }
+                    "table" if script.is_some() => {
+                        tables.push((attributes_key(&attribute("key")), Vec::new()))
+                    }
+                    "elem" if script.is_some() => {
+                        elem_key = Some(attributes_key(&attribute("key")))
+                    }
+                    "finished" => {
+                        run.runstats.exit = attribute("exit");
+                        run.runstats.elapsed_secs =
+                            attribute("elapsed").parse().unwrap_or_default();
+                        run.runstats.summary = attribute("summary");
+                    }
+                    //<hosts> only appears in <runstats>.
+                    "hosts" => {
+                        run.runstats.hosts_up = attribute("up").parse().unwrap_or_default();
+                        run.runstats.hosts_down = attribute("down").parse().unwrap_or_default();
+                        run.runstats.hosts_total = attribute("total").parse().unwrap_or_default();
+                    }
+                    _ => {}
+                }
+            }
+
+            Ok(XmlEvent::Characters(characters)) | Ok(XmlEvent::CData(characters)) => {
+                text.push_str(&characters)
+            }
+
+            Ok(XmlEvent::EndElement { name }) => match name.local_name.as_str() {
+                "cpe" => {
+                    let cpe = std::mem::take(&mut text);
+                    if let (true, Some(port)) = (in_service, port.as_mut()) {
+                        port.service_cpes.push(cpe);
+                    } else if let Some(os_match) = os_match.as_mut() {
+                        os_match.cpes.push(cpe);
+                    }
+                }
+                "service" => in_service = false,
+                "elem" => {
+                    if let Some(key) = elem_key.take() {
+                        let element = ScriptElement::Elem {
+                            key,
+                            value: std::mem::take(&mut text),
+                        };
+                        match (tables.last_mut(), script.as_mut()) {
+                            (Some((_, elements)), _) => elements.push(element),
+                            (None, Some(script)) => script.elements.push(element),
+                            (None, None) => {}
+                        }
+                    }
+                }
+                "table" => {
+                    if let Some((key, elements)) = tables.pop() {
+                        let element = ScriptElement::Table { key, elements };
+                        match (tables.last_mut(), script.as_mut()) {
+                            (Some((_, elements)), _) => elements.push(element),
+                            (None, Some(script)) => script.elements.push(element),
+                            (None, None) => {}
+                        }
+                    }
+                }
+                "script" => {
+                    //Scripts in <port> belong to the port, scripts in <hostscript> to the host. Pre- and postscripts are dropped.
+                    if let Some(script) = script.take() {
+                        match (port.as_mut(), host.as_mut()) {
+                            (Some(port), _) => port.scripts.push(script),
+                            (None, Some(host)) => host.scripts.push(script),
+                            (None, None) => {}
+                        }
+                    }
+                }
+                "port" => {
+                    if let (Some(port), Some(host)) = (port.take(), host.as_mut()) {
+                        host.ports.push(port);
+                    }
+                }
+                "osmatch" => {
+                    if let (Some(os_match), Some(host)) = (os_match.take(), host.as_mut()) {
+                        host.os_matches.push(os_match);
+                    }
+                }
+                "host" => {
+                    if let Some(host) = host.take() {
+                        run.hosts.push(host);
+                    }
+                }
+                "hosthint" => {
+                    if let Some(host) = host.take() {
+                        run.hosthints.push(host);
+                    }
+                }
+                _ => {}
+            },
+
+            Err(_) => {
+                logger::print_err("Error reading Nmap XML entry");
+            }
+
+            _ => {}
+        }
+    }
+
+    run
+}
+
+///Nmap leaves out the key attribute of list-like tables and elements.
+fn attributes_key(key: &str) -> Option<String> {
+    if key.is_empty() {
+        None
+    } else {
+        Some(key.to_string())
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn parse(xml: &str) -> NmapRun {
+        parse_xml(xml.as_bytes())
+    }
+
+    const VERSION_XML: &str = r#"<?xml version="1.0" encoding="UTF-8"?>
+<nmaprun scanner="nmap" args="nmap -sV -sC -O -p22,443 10.0.0.5" version="7.94">
+<prescript><script id="broadcast-ping" output="nothing"/></prescript>
+<host>
+<status state="up" reason="echo-reply"/>
+<address addr="10.0.0.5" addrtype="ipv4"/>
+<address addr="00:11:22:33:44:55" addrtype="mac" vendor="Acme"/>
+<hostnames><hostname name="fs01.corp.local" type="user"/><hostname name="fs01" type="PTR"/></hostnames>
+<ports>
+<extraports state="closed" count="998"/>
+<port protocol="tcp" portid="22"><state state="open" reason="syn-ack"/>
+<service name="ssh" product="OpenSSH" version="8.9p1 Ubuntu 3ubuntu0.1" extrainfo="Ubuntu Linux; protocol 2.0"><cpe>cpe:/a:openbsd:openssh:8.9p1</cpe><cpe>cpe:/o:linux:linux_kernel</cpe></service>
+</port>
+<port protocol="tcp" portid="443"><state state="open" reason="syn-ack"/>
+<service name="http" product="nginx" tunnel="ssl"/>
+<script id="ssl-cert" output="Subject: commonName=fs01.corp.local&#xa;Subject Alternative Name: DNS:fs01.corp.local, DNS:files.corp.local">
+<table key="subject"><elem key="commonName">fs01.corp.local</elem></table>
+<table key="extensions">
+<table><elem key="name">X509v3 Subject Alternative Name</elem><elem key="value">DNS:fs01.corp.local, DNS:files.corp.local</elem></table>
+</table>
+<elem key="sig_algo">sha256WithRSAEncryption</elem>
+<elem key="pem">-----BEGIN CERTIFICATE-----&#xa;MIIB&amp;&lt;&gt;&#xa;-----END CERTIFICATE-----</elem>
+</script>
+</port>
+<port protocol="udp" portid="161"><state state="open|filtered" reason="no-response"/></port>
+</ports>
+<os><osmatch name="Linux 5.0 - 5.14" accuracy="98"><osclass type="general purpose" vendor="Linux"><cpe>cpe:/o:linux:linux_kernel:5</cpe></osclass></osmatch>
+<osmatch name="MikroTik RouterOS 7.2" accuracy="91"><osclass type="router"><cpe>cpe:/o:mikrotik:routeros:7</cpe></osclass></osmatch></os>
+<hostscript><script id="smb2-security-mode" output="&#xa;  3:1:1: &#xa;    Message signing enabled but not required"><table key="3:1:1"><elem>Message signing enabled but not required</elem></table></script></hostscript>
+</host>
+<runstats><finished time="1700000000" elapsed="12.34" summary="Nmap done; 1 IP address (1 host up) scanned in 12.34 seconds" exit="success"/><hosts up="1" down="0" total="1"/></runstats>
+</nmaprun>"#;
+
+    #[test]
+    fn host_addresses_and_hostnames() {
+        let run = parse(VERSION_XML);
+        assert_eq!(run.args, "nmap -sV -sC -O -p22,443 10.0.0.5");
+        assert_eq!(run.version, "7.94");
+        assert_eq!(run.hosts.len(), 1);
+        let host = &run.hosts[0];
+        assert_eq!(host.status.state, "up");
+        assert_eq!(host.status.reason, "echo-reply");
+        assert_eq!(host.addresses.len(), 2);
+        assert_eq!(host.addresses[1].vendor, "Acme");
+        assert_eq!(host.name(), "fs01.corp.local");
+    }
+
+    #[test]
+    fn ports_and_services() {
+        let run = parse(VERSION_XML);
+        let ports = &run.hosts[0].ports;
+        assert_eq!(ports.len(), 3);
+        assert_eq!(ports[0].port.num, 22);
+        assert!(matches!(ports[0].port.state, PortState::Open));
+        assert_eq!(ports[0].port.reason, "syn-ack");
+        assert_eq!(ports[0].service_product, "OpenSSH");
+        assert_eq!(ports[0].service_version, "8.9p1 Ubuntu 3ubuntu0.1");
+        assert_eq!(ports[0].service_extrainfo, "Ubuntu Linux; protocol 2.0");
+        assert!(ports[1].is_tls());
+        assert_eq!(ports[2].port.protocol, Protocol::Udp);
+        assert!(matches!(ports[2].port.state, PortState::Filtered));
+    }
+
+    #[test]
+    fn service_cpes_are_kept_apart_from_os_cpes() {
+        let run = parse(VERSION_XML);
+        let host = &run.hosts[0];
+        assert_eq!(
+            host.ports[0].service_cpes,
+            ["cpe:/a:openbsd:openssh:8.9p1", "cpe:/o:linux:linux_kernel"]
+        );
+        assert!(host.ports[1].service_cpes.is_empty());
+        assert_eq!(host.os_matches.len(), 2);
+        assert_eq!(host.os_matches[0].name, "Linux 5.0 - 5.14");
+        assert_eq!(host.os_matches[0].accuracy, 98);
+        assert_eq!(host.os_matches[0].cpes, ["cpe:/o:linux:linux_kernel:5"]);
+        assert_eq!(host.os_matches[1].cpes, ["cpe:/o:mikrotik:routeros:7"]);
+    }
+
+    #[test]
+    fn nested_script_tables_and_elems() {
+        let run = parse(VERSION_XML);
+        let script = &run.hosts[0].ports[1].scripts[0];
+        assert_eq!(script.id, "ssl-cert");
+        assert_eq!(
+            script
+                .table("subject")
+                .map(|subject| find_elem(subject, "commonName")),
+            Some(Some("fs01.corp.local"))
+        );
+        let extensions = script.table("extensions").unwrap();
+        assert_eq!(extensions.len(), 1);
+        assert_eq!(extensions[0].key(), None);
+        assert_eq!(
+            find_elem(extensions[0].elements(), "name"),
+            Some("X509v3 Subject Alternative Name")
+        );
+        assert_eq!(script.elem("sig_algo"), Some("sha256WithRSAEncryption"));
+        //Entities and character references end up in the same value.
+        assert_eq!(
+            script.elem("pem"),
+            Some("-----BEGIN CERTIFICATE-----\nMIIB&<>\n-----END CERTIFICATE-----")
+        );
+        assert_eq!(
+            run.hosts[0].ports[1].certificate_dns_names(),
+            ["fs01.corp.local", "files.corp.local"]
+        );
+    }
+
+    #[test]
+    fn host_scripts_belong_to_the_host_and_prescripts_are_dropped() {
+        let run = parse(VERSION_XML);
+        let host = &run.hosts[0];
+        let ids: Vec<&str> = host
+            .scripts
+            .iter()
+            .map(|script| script.id.as_str())
+            .collect();
+        assert_eq!(ids, ["smb2-security-mode"]);
+        let modes = host.scripts[0].table("3:1:1").unwrap();
+        assert_eq!(
+            modes[0].value(),
+            Some("Message signing enabled but not required")
+        );
+        assert!(host.ports[0].scripts.is_empty());
+    }
+
+    #[test]
+    fn runstats() {
+        let run = parse(VERSION_XML);
+        assert_eq!(run.runstats.exit, "success");
+        assert_eq!(run.runstats.elapsed_secs, 12.34);
+        assert_eq!(run.runstats.hosts_up, 1);
+        assert_eq!(run.runstats.hosts_total, 1);
+        assert!(!run.host_seems_down());
+    }
+
+    #[test]
+    fn host_down_after_discovery() {
+        let run = parse(
+            r#"<nmaprun args="nmap -p- 10.0.0.9" version="7.94">
+<hosthint><status state="down" reason="no-response"/><address addr="10.0.0.9" addrtype="ipv4"/></hosthint>
+<runstats><finished exit="success" summary="Nmap done; 1 IP address (0 hosts up)"/><hosts up="0" down="1" total="1"/></runstats>
+</nmaprun>"#,
+        );
+        assert!(run.hosts.is_empty());
+        assert_eq!(run.hosthints.len(), 1);
+        assert_eq!(run.hosthints[0].addresses[0].addr, "10.0.0.9");
+        assert_eq!(run.runstats.hosts_down, 1);
+        assert!(run.host_seems_down());
+    }
+
+    #[test]
+    fn hosthint_alone_tells_the_host_is_down() {
+        //Nmap was killed before it wrote the runstats.
+        let run = parse(
+            r#"<nmaprun><hosthint><status state="down" reason="no-response"/><address addr="10.0.0.9" addrtype="ipv4"/></hosthint>"#,
+        );
+        assert!(run.host_seems_down());
+    }
+
+    #[test]
+    fn up_hosthint_is_not_down() {
+        let run = parse(
+            r#"<nmaprun><hosthint><status state="up" reason="echo-reply"/><address addr="10.0.0.5" addrtype="ipv4"/></hosthint>
+<host><status state="up" reason="echo-reply"/><address addr="10.0.0.5" addrtype="ipv4"/></host>
+<runstats><hosts up="1" down="0" total="1"/></runstats></nmaprun>"#,
+        );
+        assert!(!run.host_seems_down());
+    }
+
+    #[test]
+    fn empty_document_is_not_down() {
+        assert!(!parse("").host_seems_down());
+        assert!(!parse("<nmaprun></nmaprun>").host_seems_down());
+    }
+
+    #[test]
+    fn merges_discovery_into_version_detection() {
+        let discovered = parse(
+            r#"<nmaprun>
+<host><status state="up"/><address addr="10.0.0.5" addrtype="ipv4"/><ports>
+<port protocol="tcp" portid="22"><state state="open"/></port>
+<port protocol="tcp" portid="8080"><state state="open"/></port>
+<port protocol="udp" portid="22"><state state="open"/></port>
+</ports></host>
+<host><status state="up"/><address addr="10.0.0.6" addrtype="ipv4"/><ports>
+<port protocol="tcp" portid="80"><state state="open"/></port>
+</ports></host>
+</nmaprun>"#,
+        )
+        .hosts;
+        let detected = parse(
+            r#"<nmaprun>
+<host><status state="up"/><address addr="10.0.0.5" addrtype="ipv4"/><ports>
+<port protocol="tcp" portid="443"><state state="open"/><service name="https"/></port>
+<port protocol="tcp" portid="22"><state state="open"/><service name="ssh" product="OpenSSH"/></port>
+</ports></host>
+</nmaprun>"#,
+        )
+        .hosts;
+
+        let merged = merge_hosts(discovered, detected);
+        assert_eq!(merged.len(), 2);
+        let ports: Vec<(u16, Protocol, &str)> = merged[0]
+            .ports
+            .iter()
+            .map(|result| {
+                (
+                    result.port.num,
+                    result.port.protocol,
+                    result.service_name.as_str(),
+                )
+            })
+            .collect();
+        //The version detection's ports win, ports only the discovery found are kept. Sorted by port.
+        assert_eq!(
+            ports,
+            [
+                (22, Protocol::Tcp, "ssh"),
+                (22, Protocol::Udp, ""),
+                (443, Protocol::Tcp, "https"),
+                (8080, Protocol::Tcp, ""),
+            ]
+        );
+        assert_eq!(merged[1].addresses[0].addr, "10.0.0.6");
+        assert_eq!(merged[1].ports.len(), 1);
     }
 }
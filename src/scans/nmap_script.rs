@@ -0,0 +1,135 @@
+use std::{
+    path::{Path, PathBuf},
+    time::Duration,
+};
+
+use super::{
+    error::ScanError,
+    finding::Finding,
+    nmap::{self, NmapHost, Protocol},
+    process,
+    scan::Scan,
+};
+use crate::logger;
+
+///Scripts only run against a single port, but some of them (brute forcing, enumeration) can still take a while.
+const DEFAULT_TIMEOUT: Duration = Duration::from_secs(30 * 60);
+
+///Runs a set of NSE scripts against a single port, e.g. SNMP enumeration against 161/udp.
+pub struct NmapScriptScan {
+    name: &'static str,
+    output_file: PathBuf,
+    scan_args: Vec<String>,
+    timeout: Duration,
+}
+
+impl NmapScriptScan {
+    ///`name` identifies the scan in the output, e.g. 'snmp'. `script_args` is passed to --script-args.
+    pub fn new(
+        output_dir: PathBuf,
+        name: &'static str,
+        target: &str,
+        port: u16,
+        protocol: Protocol,
+        scripts: &[&str],
+        script_args: Option<String>,
+    ) -> NmapScriptScan {
+        let host: String = target
+            .chars()
+            .map(|c| if c.is_ascii_alphanumeric() { c } else { '_' })
+            .collect();
+        let output_file = output_dir.join(format!("nmap_{}_{}_{}", name, port, host));
+
+        let mut scan_args = Vec::new();
+        if protocol == Protocol::Udp {
+            scan_args.push(String::from("-sU"));
+        }
+        scan_args.extend([
+            String::from("-p"),
+            port.to_string(),
+            String::from("--script"),
+            scripts.join(","),
+        ]);
+        if let Some(script_args) = script_args {
+            scan_args.push(String::from("--script-args"));
+            scan_args.push(script_args);
+        }
+        scan_args.extend([
+            String::from("-oX"),
+            output_file.to_str().unwrap().to_string(),
+            //The port is already known to be open.
+            String::from("-Pn"),
+        ]);
+        scan_args.extend(nmap::target_args(target));
+
+        NmapScriptScan {
+            name,
+            output_file,
+            scan_args,
+            timeout: DEFAULT_TIMEOUT,
+        }
+    }
+
+    pub fn output_file(&self) -> &Path {
+        &self.output_file
+    }
+
+    ///Overrides the default timeout, if one is given.
+    pub fn with_timeout(mut self, timeout: Option<Duration>) -> NmapScriptScan {
+        if let Some(timeout) = timeout {
+            self.timeout = timeout;
+        }
+        self
+    }
+}
+
+impl Scan for NmapScriptScan {
+    type ScanResult = NmapHost;
+
+    fn run(&self) -> Result<Vec<NmapHost>, ScanError> {
+        logger::print_ok(&format!("Running Nmap {} scripts...", self.name));
+        self.print_command();
+
+        let output = process::execute("nmap", &self.scan_args, &self.output_file, self.timeout)?;
+        logger::print_ok(&format!(
+            "Nmap {} scripts finished in {}s.",
+            self.name,
+            output.duration.as_secs()
+        ));
+        let results = self.parse_output();
+        self.print_results(&results);
+        Ok(results)
+    }
+
+    fn parse_output(&self) -> Vec<NmapHost> {
+        nmap::parse_xml_file(&self.output_file).hosts
+    }
+
+    fn print_results(&self, hosts: &[NmapHost]) {
+        for script in hosts.iter().flat_map(|host| {
+            host.ports
+                .iter()
+                .flat_map(|port| &port.scripts)
+                .chain(&host.scripts)
+        }) {
+            logger::print_ok(&format!("{}:{}", script.id, script.output));
+        }
+    }
+
+    ///The open port itself was already reported by the port scan, only the script output is new.
+    fn to_findings(&self, hosts: &[NmapHost]) -> Vec<Finding> {
+        nmap::host_findings(hosts, &self.output_file)
+            .into_iter()
+            .filter(|finding| !finding.id.starts_with("nmap:open-port"))
+            .collect()
+    }
+
+    fn print_command(&self) {
+        logger::print_ok(&format!("Command used: nmap {}", self.scan_args.join(" ")));
+    }
+
+    ///Nmap's installation is checked in main.rs, see `NmapScan`.
+    fn is_installed(&self) -> bool {
+        true
+    }
+}
@@ -0,0 +1,1022 @@
+use chrono::{DateTime, NaiveDateTime, TimeDelta, Utc};
+use std::net::IpAddr;
+
+use super::{
+    finding::{Finding, Severity},
+    nmap::{find_elem, find_table, ScriptElement, ScriptOutput},
+};
+
+///Where the script ran. Host scripts, such as smb2-security-mode, have no port.
+struct Location<'a> {
+    host: &'a str,
+    port: Option<u16>,
+}
+
+impl Location<'_> {
+    fn finding(
+        &self,
+        id: &str,
+        title: String,
+        severity: Severity,
+        evidence: Vec<String>,
+    ) -> Finding {
+        let mut finding = Finding::new(
+            format!("nmap:{}", id),
+            title,
+            severity,
+            self.host.to_string(),
+            "nmap",
+        );
+        finding.port = self.port;
+        finding.evidence = evidence;
+        finding
+    }
+}
+
+///Turns the output of a single NSE script into findings.
+///Scripts without an interpreter still produce an informational finding with their raw output, so nothing gets lost.
+pub fn findings(script: &ScriptOutput, host: &str, port: Option<u16>) -> Vec<Finding> {
+    let location = Location { host, port };
+    match script.id.as_str() {
+        "http-title" => http_title(script, &location),
+        "ssl-cert" => ssl_cert(script, &location),
+        "ssl-enum-ciphers" => ssl_enum_ciphers(script, &location),
+        "smb-security-mode" => smb_security_mode(script, &location),
+        "smb2-security-mode" => smb2_security_mode(script, &location),
+        "ftp-anon" => ftp_anon(script, &location),
+        "http-methods" => http_methods(script, &location),
+        "ssh-hostkey" => ssh_hostkey(script, &location),
+        "rdp-ntlm-info" => rdp_ntlm_info(script, &location),
+        "snmp-sysdescr" => snmp_sysdescr(script, &location),
+        "snmp-win32-users" => snmp_win32_users(script, &location),
+        _ => raw(script, &location),
+    }
+}
+
+///Keeps the raw output of scripts that don't have an interpreter.
+fn raw(script: &ScriptOutput, location: &Location) -> Vec<Finding> {
+    let output = script.output.trim();
+    if output.is_empty() {
+        return Vec::new();
+    }
+    vec![location.finding(
+        &format!("script:{}", script.id),
+        format!("Nmap script {}", script.id),
+        Severity::Info,
+        vec![output.to_string()],
+    )]
+}
+
+fn http_title(script: &ScriptOutput, location: &Location) -> Vec<Finding> {
+    //Pages without a title only have the human readable output, e.g. "Site doesn't have a title (text/html)."
+    let Some(title) = script.elem("title") else {
+        return raw(script, location);
+    };
+    let mut evidence = vec![title.to_string()];
+    if let Some(redirect) = script.elem("redirect_url") {
+        evidence.push(format!("Redirects to {}", redirect));
+    }
+    vec![location.finding(
+        "http-title",
+        format!("HTTP title: {}", title.trim()),
+        Severity::Info,
+        evidence,
+    )]
+}
+
+fn ssl_cert(script: &ScriptOutput, location: &Location) -> Vec<Finding> {
+    let Some(subject) = script.table("subject") else {
+        return raw(script, location);
+    };
+    let common_name = find_elem(subject, "commonName").unwrap_or("unknown");
+
+    let mut evidence = Vec::new();
+    if let Some(issuer) = script.table("issuer") {
+        evidence.push(format!("Issuer: {}", distinguished_name(issuer)));
+    }
+    if let Some(validity) = script.table("validity") {
+        evidence.push(format!(
+            "Valid from {} until {}",
+            find_elem(validity, "notBefore").unwrap_or("?"),
+            find_elem(validity, "notAfter").unwrap_or("?")
+        ));
+    }
+    if let Some(pubkey) = script.table("pubkey") {
+        evidence.push(format!(
+            "Key: {} {} bits",
+            find_elem(pubkey, "type").unwrap_or("?"),
+            find_elem(pubkey, "bits").unwrap_or("?")
+        ));
+    }
+    //Every extension is a table with a name and a value.
+    let mut alternative_names = None;
+    for extension in script.table("extensions").unwrap_or_default() {
+        if find_elem(extension.elements(), "name") == Some("X509v3 Subject Alternative Name") {
+            if let Some(names) = find_elem(extension.elements(), "value") {
+                evidence.push(format!("Subject Alternative Name: {}", names));
+                alternative_names = Some(names);
+            }
+        }
+    }
+
+    let mut findings = vec![location.finding(
+        "ssl-cert",
+        format!("TLS certificate for {}", common_name),
+        Severity::Info,
+        evidence,
+    )];
+    findings.extend(certificate_validity(script, location));
+    if script.table("issuer").map(distinguished_name) == Some(distinguished_name(subject)) {
+        findings.push(location.finding(
+            "tls-self-signed",
+            String::from("Self-signed TLS certificate"),
+            Severity::Medium,
+            vec![format!(
+                "Subject and issuer: {}",
+                distinguished_name(subject)
+            )],
+        ));
+    }
+    findings.extend(certificate_hostname(
+        location,
+        common_name,
+        alternative_names,
+    ));
+    findings.extend(certificate_key(script, location));
+    findings
+}
+
+///Expired, not yet valid or about to expire. Nmap reports the dates in UTC, e.g. '2031-01-01T00:00:00',
+///with an offset only when the certificate has one.
+fn certificate_validity(script: &ScriptOutput, location: &Location) -> Vec<Finding> {
+    let parse = |key| {
+        let date = find_elem(script.table("validity")?, key)?;
+        DateTime::parse_from_rfc3339(date)
+            .map(|date| date.naive_utc())
+            .or_else(|_| NaiveDateTime::parse_from_str(date, "%Y-%m-%dT%H:%M:%S"))
+            .ok()
+    };
+    let now = Utc::now().naive_utc();
+    let mut findings = Vec::new();
+    if let Some(not_after) = parse("notAfter") {
+        let evidence = vec![format!("Not valid after {}", not_after)];
+        if not_after < now {
+            findings.push(location.finding(
+                "tls-cert-expired",
+                String::from("Expired TLS certificate"),
+                Severity::High,
+                evidence,
+            ));
+        } else if not_after < now + TimeDelta::days(30) {
+            findings.push(location.finding(
+                "tls-cert-expiring",
+                String::from("TLS certificate expires within 30 days"),
+                Severity::Low,
+                evidence,
+            ));
+        }
+    }
+    if let Some(not_before) = parse("notBefore").filter(|not_before| *not_before > now) {
+        findings.push(location.finding(
+            "tls-cert-not-yet-valid",
+            String::from("TLS certificate is not valid yet"),
+            Severity::Medium,
+            vec![format!("Not valid before {}", not_before)],
+        ));
+    }
+    findings
+}
+
+///Only checked when the host was scanned by name, an IP address is never going to match a certificate for a website.
+///Clients ignore the common name when the certificate has alternative names, so it's only used without them.
+fn certificate_hostname(
+    location: &Location,
+    common_name: &str,
+    alternative_names: Option<&str>,
+) -> Option<Finding> {
+    if location.host.parse::<IpAddr>().is_ok() {
+        return None;
+    }
+    let names: Vec<&str> = match alternative_names {
+        Some(names) => names
+            .split(',')
+            .filter_map(|name| name.trim().strip_prefix("DNS:"))
+            .collect(),
+        None => vec![common_name],
+    };
+    if names
+        .iter()
+        .any(|name| hostname_matches(name, location.host))
+    {
+        return None;
+    }
+    Some(location.finding(
+        "tls-hostname-mismatch",
+        String::from("TLS certificate does not match the hostname"),
+        Severity::Medium,
+        vec![
+            format!("Hostname: {}", location.host),
+            format!("Certificate names: {}", names.join(", ")),
+        ],
+    ))
+}
+
+///A wildcard only covers a single label, '*.example.com' matches 'www.example.com' but not 'example.com'.
+fn hostname_matches(name: &str, host: &str) -> bool {
+    let name = name.trim_end_matches('.').to_lowercase();
+    let host = host.trim_end_matches('.').to_lowercase();
+    match name.strip_prefix("*.") {
+        Some(parent) => host
+            .split_once('.')
+            .is_some_and(|(label, rest)| !label.is_empty() && rest == parent),
+        None => name == host,
+    }
+}
+
+///Keys that can be factored or forged with reasonable effort, and signatures with broken hashes.
+fn certificate_key(script: &ScriptOutput, location: &Location) -> Vec<Finding> {
+    let mut findings = Vec::new();
+    if let Some(pubkey) = script.table("pubkey") {
+        let key_type = find_elem(pubkey, "type").unwrap_or("?");
+        let bits: u32 = find_elem(pubkey, "bits")
+            .and_then(|bits| bits.parse().ok())
+            .unwrap_or_default();
+        let minimum = match key_type {
+            "rsa" | "dsa" | "dh" => 2048,
+            "ec" => 224,
+            _ => 0,
+        };
+        if bits < minimum {
+            findings.push(location.finding(
+                "tls-weak-key",
+                format!("Weak TLS certificate key ({} {} bits)", key_type, bits),
+                Severity::Medium,
+                vec![format!("Key: {} {} bits", key_type, bits)],
+            ));
+        }
+    }
+    if let Some(algorithm) = script.elem("sig_algo") {
+        let lowercase = algorithm.to_lowercase();
+        if lowercase.contains("md5") || lowercase.contains("sha1") {
+            findings.push(location.finding(
+                "tls-weak-signature",
+                String::from("TLS certificate signed with a weak hash"),
+                Severity::Medium,
+                vec![format!("Signature algorithm: {}", algorithm)],
+            ));
+        }
+    }
+    findings
+}
+
+///One table per protocol version, e.g. 'TLSv1.2', each with its ciphers and Nmap's warnings about them.
+fn ssl_enum_ciphers(script: &ScriptOutput, location: &Location) -> Vec<Finding> {
+    let protocols: Vec<&ScriptElement> = script
+        .elements
+        .iter()
+        .filter(|element| !element.elements().is_empty())
+        .collect();
+    if protocols.is_empty() {
+        return raw(script, location);
+    }
+
+    let mut summary = Vec::new();
+    let mut deprecated = Vec::new();
+    let mut weak_ciphers = Vec::new();
+    let mut warnings: Vec<String> = Vec::new();
+    let mut broken = false;
+    for protocol in &protocols {
+        let version = protocol.key().unwrap_or("?");
+        let ciphers = find_table(protocol.elements(), "ciphers").unwrap_or_default();
+        summary.push(format!("{}: {} ciphers", version, ciphers.len()));
+        if version.starts_with("SSL") || version == "TLSv1.0" || version == "TLSv1.1" {
+            deprecated.push(version);
+        }
+
+        for cipher in ciphers {
+            let name = find_elem(cipher.elements(), "name").unwrap_or("?");
+            let strength = find_elem(cipher.elements(), "strength").unwrap_or("?");
+            if let Some(severity) = cipher_weakness(name, strength) {
+                broken |= severity == Severity::High;
+                weak_ciphers.push(format!("{}: {} (grade {})", version, name, strength));
+            }
+        }
+        for warning in find_table(protocol.elements(), "warnings")
+            .unwrap_or_default()
+            .iter()
+            .filter_map(ScriptElement::value)
+        {
+            if !warnings.iter().any(|known| known == warning) {
+                warnings.push(warning.to_string());
+            }
+        }
+    }
+    if let Some(least_strength) = script.elem("least strength") {
+        summary.push(format!("Least strength: {}", least_strength));
+    }
+
+    let mut findings = vec![location.finding(
+        "tls-protocols",
+        format!(
+            "TLS protocols: {}",
+            protocols
+                .iter()
+                .filter_map(|protocol| protocol.key())
+                .collect::<Vec<&str>>()
+                .join(", ")
+        ),
+        Severity::Info,
+        summary,
+    )];
+    if !deprecated.is_empty() {
+        //SSLv3 is broken by POODLE, TLS 1.0 and 1.1 are only deprecated.
+        let severity = if deprecated.iter().any(|version| version.starts_with("SSL")) {
+            Severity::High
+        } else {
+            Severity::Medium
+        };
+        findings.push(
+            location.finding(
+                "tls-deprecated-protocols",
+                format!(
+                    "Deprecated TLS protocols enabled: {}",
+                    deprecated.join(", ")
+                ),
+                severity,
+                deprecated
+                    .iter()
+                    .map(|version| format!("{} supported", version))
+                    .collect(),
+            ),
+        );
+    }
+    if !weak_ciphers.is_empty() {
+        weak_ciphers.extend(warnings);
+        findings.push(location.finding(
+            "tls-weak-ciphers",
+            String::from("Weak TLS ciphers enabled"),
+            if broken {
+                Severity::High
+            } else {
+                Severity::Medium
+            },
+            weak_ciphers,
+        ));
+    }
+    findings
+}
+
+///NULL, export and anonymous ciphers give away the traffic, RC4, DES and friends merely weaken it.
+///Nmap's grade catches the rest, e.g. key exchanges that are too small.
+fn cipher_weakness(name: &str, strength: &str) -> Option<Severity> {
+    if name.contains("_NULL_")
+        || name.contains("EXPORT")
+        || name.contains("_anon_")
+        || strength == "F"
+    {
+        Some(Severity::High)
+    } else if name.contains("RC4")
+        || name.contains("_DES_")
+        || name.contains("3DES")
+        || name.ends_with("_MD5")
+        || matches!(strength, "C" | "D" | "E")
+    {
+        Some(Severity::Medium)
+    } else {
+        None
+    }
+}
+
+fn distinguished_name(elements: &[ScriptElement]) -> String {
+    elements
+        .iter()
+        .filter_map(|element| Some(format!("{}={}", element.key()?, element.value()?)))
+        .collect::<Vec<String>>()
+        .join(", ")
+}
+
+///SMBv1. Reports the signing mode and the account the script managed to log in with.
+fn smb_security_mode(script: &ScriptOutput, location: &Location) -> Vec<Finding> {
+    let Some(message_signing) = script.elem("message_signing") else {
+        return raw(script, location);
+    };
+    let mut findings = Vec::new();
+    if message_signing != "required" {
+        findings.push(location.finding(
+            "smb-signing-not-required",
+            String::from("SMB signing not required"),
+            Severity::Medium,
+            vec![format!("SMBv1 message signing: {}", message_signing)],
+        ));
+    }
+    if script.elem("account_used") == Some("guest") {
+        findings.push(location.finding(
+            "smb-guest-access",
+            String::from("SMB guest access allowed"),
+            Severity::Medium,
+            vec![String::from("smb-security-mode logged in as guest")],
+        ));
+    }
+    findings
+}
+
+///SMBv2/3. Nmap reports one table per dialect, e.g. '3:1:1', each holding a description of the signing mode.
+fn smb2_security_mode(script: &ScriptOutput, location: &Location) -> Vec<Finding> {
+    let mut unsigned_dialects = Vec::new();
+    for dialect in &script.elements {
+        for mode in dialect.elements().iter().filter_map(ScriptElement::value) {
+            if !mode.contains("and required") {
+                unsigned_dialects.push(format!("{}: {}", dialect.key().unwrap_or("?"), mode));
+            }
+        }
+    }
+
+    if script.elements.is_empty() {
+        return raw(script, location);
+    }
+    if unsigned_dialects.is_empty() {
+        return Vec::new();
+    }
+    vec![location.finding(
+        "smb-signing-not-required",
+        String::from("SMB signing not required"),
+        Severity::Medium,
+        unsigned_dialects,
+    )]
+}
+
+///ftp-anon has no structured output. The first line says whether the login worked, the rest is the directory listing.
+fn ftp_anon(script: &ScriptOutput, location: &Location) -> Vec<Finding> {
+    if !script.output.contains("Anonymous FTP login allowed") {
+        return Vec::new();
+    }
+    let mut evidence: Vec<String> = script
+        .output
+        .lines()
+        .map(str::trim)
+        .filter(|line| !line.is_empty())
+        .map(String::from)
+        .collect();
+    evidence.truncate(20);
+
+    let writable = script.output.contains("[NSE: writeable]");
+    vec![location.finding(
+        "ftp-anon",
+        if writable {
+            String::from("Anonymous FTP allowed with a writable directory")
+        } else {
+            String::from("Anonymous FTP allowed")
+        },
+        if writable {
+            Severity::High
+        } else {
+            Severity::Medium
+        },
+        evidence,
+    )]
+}
+
+fn http_methods(script: &ScriptOutput, location: &Location) -> Vec<Finding> {
+    let risky: Vec<String> = script
+        .table("Potentially risky methods")
+        .unwrap_or_default()
+        .iter()
+        .filter_map(ScriptElement::value)
+        .map(String::from)
+        .collect();
+    if risky.is_empty() {
+        return Vec::new();
+    }
+
+    let mut evidence = vec![format!("Potentially risky methods: {}", risky.join(" "))];
+    if let Some(supported) = script.table("Supported Methods") {
+        let supported: Vec<&str> = supported.iter().filter_map(ScriptElement::value).collect();
+        evidence.push(format!("Supported methods: {}", supported.join(" ")));
+    }
+    //PUT and DELETE can allow changing content, TRACE and the rest are mostly informational.
+    let severity = if risky
+        .iter()
+        .any(|method| method == "PUT" || method == "DELETE")
+    {
+        Severity::Medium
+    } else {
+        Severity::Low
+    };
+    vec![location.finding(
+        "http-risky-methods",
+        format!("Potentially risky HTTP methods: {}", risky.join(", ")),
+        severity,
+        evidence,
+    )]
+}
+
+///Every host key is a table with its type, size and fingerprint.
+fn ssh_hostkey(script: &ScriptOutput, location: &Location) -> Vec<Finding> {
+    let mut findings = Vec::new();
+    let mut keys = Vec::new();
+    let mut weak_keys = Vec::new();
+    for key in &script.elements {
+        let key_type = find_elem(key.elements(), "type").unwrap_or("?");
+        let bits: u32 = find_elem(key.elements(), "bits")
+            .and_then(|bits| bits.parse().ok())
+            .unwrap_or_default();
+        let fingerprint = find_elem(key.elements(), "fingerprint").unwrap_or("?");
+        let description = format!("{} {} {}", key_type, bits, fingerprint);
+        if key_type == "ssh-dss" || (key_type == "ssh-rsa" && bits < 2048) {
+            weak_keys.push(description.clone());
+        }
+        keys.push(description);
+    }
+    if keys.is_empty() {
+        return raw(script, location);
+    }
+
+    findings.push(location.finding(
+        "ssh-hostkey",
+        String::from("SSH host keys"),
+        Severity::Info,
+        keys,
+    ));
+    if !weak_keys.is_empty() {
+        findings.push(location.finding(
+            "ssh-weak-hostkey",
+            String::from("Weak SSH host key"),
+            Severity::Low,
+            weak_keys,
+        ));
+    }
+    findings
+}
+
+///Leaks the computer and domain names through the NTLM challenge, useful for further enumeration.
+fn rdp_ntlm_info(script: &ScriptOutput, location: &Location) -> Vec<Finding> {
+    let evidence: Vec<String> = script
+        .elements
+        .iter()
+        .filter_map(|element| Some(format!("{}: {}", element.key()?, element.value()?)))
+        .collect();
+    if evidence.is_empty() {
+        return raw(script, location);
+    }
+    let domain = script
+        .elem("DNS_Domain_Name")
+        .or_else(|| script.elem("NetBIOS_Domain_Name"))
+        .unwrap_or("unknown");
+    vec![location.finding(
+        "rdp-ntlm-info",
+        format!("RDP discloses NTLM information (domain {})", domain),
+        Severity::Info,
+        evidence,
+    )]
+}
+
+///snmp-sysdescr only has output when the agent answered to the community string, which is 'public' unless configured.
+fn snmp_sysdescr(script: &ScriptOutput, location: &Location) -> Vec<Finding> {
+    let description = script.output.trim();
+    if description.is_empty() {
+        return Vec::new();
+    }
+    vec![location.finding(
+        "snmp-readable",
+        String::from("SNMP readable with a guessable community string"),
+        Severity::Medium,
+        vec![description.to_string()],
+    )]
+}
+
+fn snmp_win32_users(script: &ScriptOutput, location: &Location) -> Vec<Finding> {
+    let users: Vec<String> = script
+        .output
+        .lines()
+        .map(str::trim)
+        .filter(|line| !line.is_empty())
+        .map(String::from)
+        .collect();
+    if users.is_empty() {
+        return Vec::new();
+    }
+    vec![location.finding(
+        "snmp-win32-users",
+        format!("SNMP discloses {} Windows user accounts", users.len()),
+        Severity::Medium,
+        users,
+    )]
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn elem(key: &str, value: &str) -> ScriptElement {
+        ScriptElement::Elem {
+            key: Some(key.to_string()),
+            value: value.to_string(),
+        }
+    }
+
+    fn table(key: Option<&str>, elements: Vec<ScriptElement>) -> ScriptElement {
+        ScriptElement::Table {
+            key: key.map(String::from),
+            elements,
+        }
+    }
+
+    fn script(id: &str, elements: Vec<ScriptElement>) -> ScriptOutput {
+        ScriptOutput {
+            id: id.to_string(),
+            output: String::from("raw output"),
+            elements,
+        }
+    }
+
+    fn date(days_from_now: i64) -> String {
+        (Utc::now() + TimeDelta::days(days_from_now))
+            .format("%Y-%m-%dT%H:%M:%S")
+            .to_string()
+    }
+
+    ///A certificate for www.example.com, issued by a CA, valid for another year. `elements` override parts of it.
+    fn certificate(elements: Vec<ScriptElement>) -> ScriptOutput {
+        let mut all = vec![
+            table(Some("subject"), vec![elem("commonName", "www.example.com")]),
+            table(
+                Some("issuer"),
+                vec![
+                    elem("commonName", "Example CA"),
+                    elem("organizationName", "Example"),
+                ],
+            ),
+            table(
+                Some("pubkey"),
+                vec![elem("type", "rsa"), elem("bits", "2048")],
+            ),
+            table(
+                Some("validity"),
+                vec![elem("notBefore", &date(-30)), elem("notAfter", &date(365))],
+            ),
+            elem("sig_algo", "sha256WithRSAEncryption"),
+        ];
+        //The lookups take the first match, so the overrides go in front.
+        all.splice(0..0, elements);
+        script("ssl-cert", all)
+    }
+
+    fn ids(findings: &[Finding]) -> Vec<&str> {
+        findings.iter().map(|finding| finding.id.as_str()).collect()
+    }
+
+    fn cert_findings(host: &str, elements: Vec<ScriptElement>) -> Vec<Finding> {
+        findings(&certificate(elements), host, Some(443))
+    }
+
+    #[test]
+    fn valid_certificate_is_only_informational() {
+        let findings = cert_findings("www.example.com", Vec::new());
+        assert_eq!(ids(&findings), ["nmap:ssl-cert"]);
+        assert_eq!(findings[0].title, "TLS certificate for www.example.com");
+        assert_eq!(findings[0].port, Some(443));
+    }
+
+    #[test]
+    fn expired_expiring_and_not_yet_valid() {
+        let validity = |not_before: String, not_after: String| {
+            vec![table(
+                Some("validity"),
+                vec![elem("notBefore", &not_before), elem("notAfter", &not_after)],
+            )]
+        };
+        let expired = cert_findings("www.example.com", validity(date(-400), date(-1)));
+        assert_eq!(ids(&expired), ["nmap:ssl-cert", "nmap:tls-cert-expired"]);
+        assert_eq!(expired[1].severity, Severity::High);
+
+        let expiring = cert_findings("www.example.com", validity(date(-400), date(29)));
+        assert_eq!(ids(&expiring), ["nmap:ssl-cert", "nmap:tls-cert-expiring"]);
+        assert_eq!(expiring[1].severity, Severity::Low);
+
+        let fine = cert_findings("www.example.com", validity(date(-400), date(31)));
+        assert_eq!(ids(&fine), ["nmap:ssl-cert"]);
+
+        let future = cert_findings("www.example.com", validity(date(1), date(400)));
+        assert_eq!(
+            ids(&future),
+            ["nmap:ssl-cert", "nmap:tls-cert-not-yet-valid"]
+        );
+    }
+
+    #[test]
+    fn validity_dates_with_an_offset() {
+        let findings = cert_findings(
+            "www.example.com",
+            vec![table(
+                Some("validity"),
+                vec![
+                    elem("notBefore", "2001-01-01T00:00:00+00:00"),
+                    elem("notAfter", "2002-01-01T00:00:00Z"),
+                ],
+            )],
+        );
+        assert_eq!(ids(&findings), ["nmap:ssl-cert", "nmap:tls-cert-expired"]);
+    }
+
+    #[test]
+    fn unparseable_validity_dates_are_ignored() {
+        let findings = cert_findings(
+            "www.example.com",
+            vec![table(
+                Some("validity"),
+                vec![
+                    elem("notBefore", "Can't parse; string is \"\""),
+                    elem("notAfter", "2049-13-45"),
+                ],
+            )],
+        );
+        assert_eq!(ids(&findings), ["nmap:ssl-cert"]);
+    }
+
+    #[test]
+    fn self_signed_certificate() {
+        let findings = cert_findings(
+            "www.example.com",
+            vec![table(
+                Some("issuer"),
+                vec![elem("commonName", "www.example.com")],
+            )],
+        );
+        assert_eq!(ids(&findings), ["nmap:ssl-cert", "nmap:tls-self-signed"]);
+        assert_eq!(
+            findings[1].evidence,
+            ["Subject and issuer: commonName=www.example.com"]
+        );
+    }
+
+    #[test]
+    fn hostname_matching() {
+        let cases = [
+            ("www.example.com", "www.example.com", true),
+            ("WWW.Example.COM", "www.example.com.", true),
+            ("*.example.com", "www.example.com", true),
+            ("*.example.com", "example.com", false),
+            ("*.example.com", "a.b.example.com", false),
+            ("*.example.com", ".example.com", false),
+            ("www.example.com", "example.com", false),
+            ("example.com", "www.example.com", false),
+        ];
+        for (name, host, expected) in cases {
+            assert_eq!(hostname_matches(name, host), expected, "{} {}", name, host);
+        }
+    }
+
+    #[test]
+    fn alternative_names_replace_the_common_name() {
+        let extensions = |names: &str| {
+            table(
+                Some("extensions"),
+                vec![table(
+                    None,
+                    vec![
+                        elem("name", "X509v3 Subject Alternative Name"),
+                        elem("value", names),
+                    ],
+                )],
+            )
+        };
+        let matching = cert_findings(
+            "api.example.com",
+            vec![extensions(
+                "DNS:example.com, DNS:*.example.com, IP Address:10.0.0.5",
+            )],
+        );
+        assert_eq!(ids(&matching), ["nmap:ssl-cert"]);
+
+        //The common name matches, but clients don't look at it with alternative names present.
+        let mismatch = cert_findings("www.example.com", vec![extensions("DNS:example.org")]);
+        assert_eq!(
+            ids(&mismatch),
+            ["nmap:ssl-cert", "nmap:tls-hostname-mismatch"]
+        );
+        assert_eq!(
+            mismatch[1].evidence,
+            [
+                "Hostname: www.example.com",
+                "Certificate names: example.org"
+            ]
+        );
+
+        let common_name_only = cert_findings("mail.example.com", Vec::new());
+        assert_eq!(
+            ids(&common_name_only),
+            ["nmap:ssl-cert", "nmap:tls-hostname-mismatch"]
+        );
+    }
+
+    #[test]
+    fn hostname_is_not_checked_for_ip_addresses() {
+        assert_eq!(
+            ids(&cert_findings("10.0.0.5", Vec::new())),
+            ["nmap:ssl-cert"]
+        );
+        assert_eq!(
+            ids(&cert_findings("2001:db8::1", Vec::new())),
+            ["nmap:ssl-cert"]
+        );
+    }
+
+    #[test]
+    fn weak_keys_and_signatures() {
+        let pubkey = |key_type: &str, bits: &str| {
+            vec![table(
+                Some("pubkey"),
+                vec![elem("type", key_type), elem("bits", bits)],
+            )]
+        };
+        let weak = |elements| {
+            cert_findings("www.example.com", elements)
+                .iter()
+                .any(|finding| finding.id == "nmap:tls-weak-key")
+        };
+        assert!(weak(pubkey("rsa", "1024")));
+        assert!(!weak(pubkey("rsa", "2048")));
+        assert!(weak(pubkey("dh", "1024")));
+        assert!(!weak(pubkey("ec", "256")));
+        assert!(weak(pubkey("ec", "160")));
+        assert!(!weak(pubkey("ed25519", "256")));
+
+        for (algorithm, expected) in [
+            ("sha1WithRSAEncryption", true),
+            ("md5WithRSAEncryption", true),
+            ("ecdsa-with-SHA1", true),
+            ("sha256WithRSAEncryption", false),
+            ("ecdsa-with-SHA384", false),
+        ] {
+            let findings = cert_findings("www.example.com", vec![elem("sig_algo", algorithm)]);
+            assert_eq!(
+                findings
+                    .iter()
+                    .any(|finding| finding.id == "nmap:tls-weak-signature"),
+                expected,
+                "{}",
+                algorithm
+            );
+        }
+    }
+
+    fn protocol(version: &str, ciphers: &[(&str, &str)], warnings: &[&str]) -> ScriptElement {
+        table(
+            Some(version),
+            vec![
+                table(
+                    Some("ciphers"),
+                    ciphers
+                        .iter()
+                        .map(|(name, strength)| {
+                            table(None, vec![elem("name", name), elem("strength", strength)])
+                        })
+                        .collect(),
+                ),
+                table(
+                    Some("warnings"),
+                    warnings
+                        .iter()
+                        .map(|warning| ScriptElement::Elem {
+                            key: None,
+                            value: warning.to_string(),
+                        })
+                        .collect(),
+                ),
+                elem("cipher preference", "server"),
+            ],
+        )
+    }
+
+    #[test]
+    fn modern_ciphers_are_only_informational() {
+        let script = script(
+            "ssl-enum-ciphers",
+            vec![
+                protocol(
+                    "TLSv1.2",
+                    &[("TLS_ECDHE_RSA_WITH_AES_128_GCM_SHA256", "A")],
+                    &[],
+                ),
+                protocol("TLSv1.3", &[("TLS_AKE_WITH_AES_256_GCM_SHA384", "A")], &[]),
+                elem("least strength", "A"),
+            ],
+        );
+        let findings = findings(&script, "www.example.com", Some(443));
+        assert_eq!(ids(&findings), ["nmap:tls-protocols"]);
+        assert_eq!(findings[0].title, "TLS protocols: TLSv1.2, TLSv1.3");
+        assert_eq!(
+            findings[0].evidence,
+            [
+                "TLSv1.2: 1 ciphers",
+                "TLSv1.3: 1 ciphers",
+                "Least strength: A"
+            ]
+        );
+    }
+
+    #[test]
+    fn deprecated_protocols_and_weak_ciphers() {
+        let script = script(
+            "ssl-enum-ciphers",
+            vec![
+                protocol(
+                    "TLSv1.0",
+                    &[
+                        ("TLS_RSA_WITH_RC4_128_SHA", "C"),
+                        ("TLS_RSA_WITH_AES_128_CBC_SHA", "A"),
+                    ],
+                    &["Broken cipher RC4 is deprecated by RFC 7465"],
+                ),
+                protocol(
+                    "TLSv1.2",
+                    &[("TLS_RSA_WITH_RC4_128_SHA", "C")],
+                    &["Broken cipher RC4 is deprecated by RFC 7465"],
+                ),
+            ],
+        );
+        let findings = findings(&script, "www.example.com", Some(443));
+        assert_eq!(
+            ids(&findings),
+            [
+                "nmap:tls-protocols",
+                "nmap:tls-deprecated-protocols",
+                "nmap:tls-weak-ciphers"
+            ]
+        );
+        assert_eq!(findings[1].severity, Severity::Medium);
+        assert_eq!(
+            findings[1].title,
+            "Deprecated TLS protocols enabled: TLSv1.0"
+        );
+        assert_eq!(findings[2].severity, Severity::Medium);
+        //The same warning of several protocols is listed once.
+        assert_eq!(
+            findings[2].evidence,
+            [
+                "TLSv1.0: TLS_RSA_WITH_RC4_128_SHA (grade C)",
+                "TLSv1.2: TLS_RSA_WITH_RC4_128_SHA (grade C)",
+                "Broken cipher RC4 is deprecated by RFC 7465"
+            ]
+        );
+    }
+
+    #[test]
+    fn sslv3_and_export_ciphers_are_high() {
+        let script = script(
+            "ssl-enum-ciphers",
+            vec![protocol(
+                "SSLv3",
+                &[("TLS_RSA_EXPORT_WITH_RC4_40_MD5", "E")],
+                &[],
+            )],
+        );
+        let findings = findings(&script, "10.0.0.5", Some(443));
+        assert_eq!(findings[1].id, "nmap:tls-deprecated-protocols");
+        assert_eq!(findings[1].severity, Severity::High);
+        assert_eq!(findings[2].id, "nmap:tls-weak-ciphers");
+        assert_eq!(findings[2].severity, Severity::High);
+    }
+
+    #[test]
+    fn cipher_weakness_grades() {
+        let cases = [
+            ("TLS_RSA_WITH_NULL_SHA256", "A", Some(Severity::High)),
+            (
+                "TLS_DH_anon_WITH_AES_128_CBC_SHA",
+                "A",
+                Some(Severity::High),
+            ),
+            (
+                "TLS_RSA_EXPORT1024_WITH_DES_CBC_SHA",
+                "E",
+                Some(Severity::High),
+            ),
+            (
+                "TLS_ECDHE_RSA_WITH_AES_128_GCM_SHA256",
+                "F",
+                Some(Severity::High),
+            ),
+            ("TLS_RSA_WITH_RC4_128_MD5", "C", Some(Severity::Medium)),
+            ("TLS_RSA_WITH_3DES_EDE_CBC_SHA", "C", Some(Severity::Medium)),
+            ("TLS_RSA_WITH_DES_CBC_SHA", "C", Some(Severity::Medium)),
+            (
+                "TLS_DHE_RSA_WITH_AES_128_CBC_SHA",
+                "D",
+                Some(Severity::Medium),
+            ),
+            ("TLS_ECDHE_RSA_WITH_AES_256_GCM_SHA384", "A", None),
+            ("TLS_RSA_WITH_AES_128_CBC_SHA", "B", None),
+        ];
+        for (name, strength, expected) in cases {
+            assert_eq!(cipher_weakness(name, strength), expected, "{}", name);
+        }
+    }
+
+    #[test]
+    fn scripts_without_structured_output_stay_raw() {
+        let findings = findings(&script("ssl-cert", Vec::new()), "10.0.0.5", Some(443));
+        assert_eq!(ids(&findings), ["nmap:script:ssl-cert"]);
+        assert_eq!(findings[0].evidence, ["raw output"]);
+    }
+}
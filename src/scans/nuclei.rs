@@ -1,106 +1,196 @@
 use crate::logger;
+use chrono::{DateTime, Utc};
 use comfy_table::{modifiers::UTF8_ROUND_CORNERS, presets::UTF8_FULL, *};
 use serde::{Deserialize, Serialize};
 use std::{
-    fs::{self, File},
+    fs::File,
     io::{BufRead, BufReader},
     path::PathBuf,
-    process::{Command, Stdio},
+    time::Duration,
 };
+use url::Url;
 use which::which;
 
-use super::{error::ScanError, scan::Scan};
+use super::{
+    error::ScanError,
+    finding::{Finding, Severity},
+    process,
+    scan::Scan,
+};
+
+///Nuclei runs the full template set, which takes a while even against a single URL.
+const DEFAULT_TIMEOUT: Duration = Duration::from_secs(60 * 60);
 
 #[derive(Serialize, Deserialize, Debug)]
 pub struct NucleiScanResult {
     info: Info,
     #[serde(rename = "template-id")]
-    template_id: String, //template-id
+    pub template_id: String, //template-id
     #[serde(rename = "type")]
     template_type: String, //type
     #[serde(rename = "extracted-results", default)]
     finding_values: Vec<String>, //extracted-results
     #[serde(rename = "matcher-name", default)]
-    matcher_name: String,
+    pub matcher_name: String,
     #[serde(rename = "matched-at")]
     location: String, //matched-at
+    ///The target Nuclei was pointed at, e.g. 'http://10.0.0.1:80'.
+    #[serde(default)]
+    pub host: String,
+    ///The request that matched, as a curl command line.
+    #[serde(rename = "curl-command", default)]
+    curl_command: String,
+    //The raw HTTP traffic stays in Nuclei's output file, it would only bloat report.json.
+    #[serde(default, skip_serializing)]
+    request: String,
+    #[serde(default, skip_serializing)]
+    response: String,
+    //Kept as text, a timestamp in an unexpected format shouldn't cost the whole result.
+    #[serde(default)]
+    timestamp: String,
 }
 
 #[derive(Serialize, Deserialize, Debug)]
 struct Info {
+    #[serde(default)]
+    name: String,
     severity: String,
 }
 
+impl NucleiScanResult {
+    ///Human readable name of the template, falling back to its id for templates without one.
+    pub fn title(&self) -> &str {
+        if self.info.name.is_empty() {
+            &self.template_id
+        } else {
+            &self.info.name
+        }
+    }
+
+    pub fn severity(&self) -> Severity {
+        Severity::parse(&self.info.severity)
+    }
+
+    ///Where the template matched, e.g. 'https://10.0.0.1/wp-login.php'.
+    pub fn matched_at(&self) -> &str {
+        &self.location
+    }
+
+    pub fn extracted_results(&self) -> &[String] {
+        &self.finding_values
+    }
+
+    ///Empty when Nuclei ran with -omit-raw.
+    pub fn curl_command(&self) -> &str {
+        &self.curl_command
+    }
+
+    ///The raw request, empty when Nuclei ran with -omit-raw or the result came from report.json.
+    pub fn request(&self) -> &str {
+        &self.request
+    }
+
+    ///The raw response, including the headers. Empty like `request()`.
+    pub fn response(&self) -> &str {
+        &self.response
+    }
+
+    ///When Nuclei sent the request.
+    pub fn timestamp(&self) -> Option<DateTime<Utc>> {
+        DateTime::parse_from_rfc3339(&self.timestamp)
+            .ok()
+            .map(|timestamp| timestamp.with_timezone(&Utc))
+    }
+}
+
 pub struct NucleiScan {
     output_file: PathBuf,
     scan_args: Vec<String>,
+    timeout: Duration,
 }
 
 impl NucleiScan {
-    pub fn new(mut output_dir: PathBuf, target: String) -> NucleiScan {
-        output_dir.push("nuclei");
+    ///`tags` restricts the templates that run to the given Nuclei tags. All templates run when it's empty.
+    pub fn new(mut output_dir: PathBuf, target: String, tags: &[String]) -> NucleiScan {
+        //Multiple Nuclei scans can run at the same time, so every target/tag combination gets its own output file.
+        let file_name: String = format!("{} {}", target, tags.join(" "))
+            .trim_end()
+            .chars()
+            .map(|c| if c.is_ascii_alphanumeric() { c } else { '_' })
+            .collect();
+        output_dir.push(format!("nuclei_{}", file_name));
+        let mut scan_args = vec![
+            String::from("-u"),
+            target,
+            String::from("-ni"), //Don't use Interact.sh server to test for OOB interactions. Might miss some vulns.
+            String::from("-fr"), //Follow redirects
+            String::from("-o"),
+            String::from(output_dir.to_str().unwrap()),
+            String::from("-json"),
+        ];
+        if !tags.is_empty() {
+            scan_args.push(String::from("-tags"));
+            scan_args.push(tags.join(","));
+        }
+
+        NucleiScan {
+            output_file: output_dir,
+            scan_args,
+            timeout: DEFAULT_TIMEOUT,
+        }
+    }
+
+    ///Only parses an existing output file, e.g. from a previous run. Calling `run()` on this is pointless.
+    pub fn from_output_file(output_file: PathBuf) -> NucleiScan {
         NucleiScan {
-            output_file: output_dir.clone(),
-            scan_args: vec![
-                String::from("-u"),
-                String::from(target),
-                String::from("-ni"), //Don't use Interact.sh server to test for OOB interactions. Might miss some vulns.
-                String::from("-fr"), //Follow redirects
-                String::from("-o"),
-                String::from(output_dir.to_str().unwrap()),
-                String::from("-json"),
-            ],
+            output_file,
+            scan_args: Vec::new(),
+            timeout: DEFAULT_TIMEOUT,
+        }
+    }
+
+    ///Overrides the default timeout, if one is given.
+    pub fn with_timeout(mut self, timeout: Option<Duration>) -> NucleiScan {
+        if let Some(timeout) = timeout {
+            self.timeout = timeout;
         }
+        self
     }
 }
 
 impl Scan for NucleiScan {
     type ScanResult = NucleiScanResult;
 
-    fn run(&self) -> Result<Vec<Self::ScanResult>, super::error::ScanError> {
+    fn run(&self) -> Result<Vec<Self::ScanResult>, ScanError> {
         if !self.is_installed() {
             logger::print_err("Nuclei is not installed. Skipping scan.");
-            return Err(ScanError::NotInstalled("feroxbuster".to_string()));
+            return Err(ScanError::NotInstalled("nuclei".to_string()));
         }
         logger::print_ok("Running Nuclei ...");
         self.print_command();
 
-        // match Command::new("nuclei")
-        //     .stdout(Stdio::null())
-        //     .stderr(Stdio::null())
-        //     .args(&self.scan_args)
-        //     .spawn()
-        // {
-        //     Ok(mut child) => {
-        //         if let Ok(_) = child.wait() {
+        let output = process::execute("nuclei", &self.scan_args, &self.output_file, self.timeout)?;
+        logger::print_ok(&format!(
+            "Nuclei finished in {}s.",
+            output.duration.as_secs()
+        ));
         let results = self.parse_output();
         self.print_results(&results);
-        return Ok(results);
-        // } else {
-        //     return Err(ScanError::Runtime("nuclei".to_string()));
-        // }
-        // }
-        // Err(err) => {
-        //     logger::print_err(&err.to_string());
-        //     return Err(ScanError::Runtime("nuclei".to_string()));
-        // }
-        // }
+        Ok(results)
     }
 
     fn parse_output(&self) -> Vec<Self::ScanResult> {
         let mut results: Vec<NucleiScanResult> = Vec::new();
         if let Ok(file_handle) = File::open(&self.output_file) {
             let reader = BufReader::new(file_handle);
-            for line in reader.lines() {
-                if let Ok(line_read) = line {
-                    let json_result = serde_json::from_str(&line_read);
-                    match json_result {
-                        Ok(nuclei_scan_result) => {
-                            results.push(nuclei_scan_result);
-                        }
-                        Err(err) => {
-                            println!("Fuck! Can't unpack the nuclei results. {}", err.to_string())
-                        }
+            for line_read in reader.lines().map_while(Result::ok) {
+                let json_result = serde_json::from_str(&line_read);
+                match json_result {
+                    Ok(nuclei_scan_result) => {
+                        results.push(nuclei_scan_result);
+                    }
+                    Err(err) => {
+                        logger::print_err(&format!("Can't unpack the nuclei results. {}", err))
                     }
                 }
             }
@@ -108,11 +198,11 @@ impl Scan for NucleiScan {
             logger::print_err("Something went wrong when reading the nuclei output.");
         }
 
-        return results;
+        results
     }
 
     fn print_results(&self, scan_results: &[Self::ScanResult]) {
-        if scan_results.len() < 1 {
+        if scan_results.is_empty() {
             logger::print_warn("Nuclei found nothing."); //Will never happen lol
             return;
         }
@@ -142,21 +232,9 @@ impl Scan for NucleiScan {
                 template_id_string.push_str(&result.matcher_name);
             }
 
-            //Can be done more elegantly with a Hashmap but I dont care atm.
-            let cell: Cell = Cell::new(&result.info.severity);
-            let mut severity_color = Color::AnsiValue(33); //Make this default for an 'info' finding.
-            if result.info.severity == "low" {
-                severity_color = Color::AnsiValue(28);
-            } else if result.info.severity == "medium" {
-               severity_color = Color::AnsiValue(214);
-            } else if result.info.severity == "high" {
-                severity_color = Color::AnsiValue(196);
-            } else if result.info.severity == "critical" {
-                severity_color = Color::AnsiValue(196);
-            }
-            //c
+            let severity = result.severity();
             table.add_row(vec![
-                cell.fg(severity_color),
+                Cell::new(severity).fg(severity.color()),
                 Cell::from(&result.template_type),
                 Cell::from(&template_id_string),
                 Cell::from(&result.location),
@@ -167,6 +245,37 @@ impl Scan for NucleiScan {
         println!("{}", table);
     }
 
+    fn to_findings(&self, scan_results: &[Self::ScanResult]) -> Vec<Finding> {
+        scan_results
+            .iter()
+            .map(|result| {
+                let mut id = format!("nuclei:{}", result.template_id);
+                if !result.matcher_name.is_empty() {
+                    id.push(':');
+                    id.push_str(&result.matcher_name);
+                }
+                let url = Url::parse(&result.location).ok();
+                let host = url
+                    .as_ref()
+                    .and_then(|url| url.host_str().map(String::from))
+                    .unwrap_or_else(|| result.host.clone());
+
+                let mut finding = Finding::new(
+                    id,
+                    result.title().to_string(),
+                    result.severity(),
+                    host,
+                    "nuclei",
+                );
+                finding.port = url.as_ref().and_then(Url::port_or_known_default);
+                finding.url = Some(result.location.clone());
+                finding.evidence = result.finding_values.clone();
+                finding.raw_reference = Some(self.output_file.display().to_string());
+                finding
+            })
+            .collect()
+    }
+
     fn print_command(&self) {
         logger::print_ok(&format!(
             "Command used: nuclei {}",
@@ -175,9 +284,6 @@ impl Scan for NucleiScan {
     }
 
     fn is_installed(&self) -> bool {
-        if let Ok(_) = which("nuclei") {
-            return true;
-        }
-        false
+        which("nuclei").is_ok()
     }
-}
\ No newline at end of file
+}
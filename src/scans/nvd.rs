@@ -0,0 +1,195 @@
+use comfy_table::{modifiers::UTF8_ROUND_CORNERS, presets::UTF8_FULL, *};
+use std::sync::Arc;
+
+use super::{
+    cve::{self, CveScanResult},
+    error::ScanError,
+    finding::{Finding, Severity},
+    nmap::{NmapHost, PortState},
+    scan::Scan,
+};
+use crate::{logger, nvd::NvdIndex};
+
+///Matches the versions Nmap detected against the local NVD index. Nothing is sent over the network.
+pub struct NvdMatch {
+    host: String,
+    services: Vec<Service>,
+    index: Arc<NvdIndex>,
+}
+
+///What Nmap's version detection said about a port.
+struct Service {
+    port: u16,
+    ///e.g. 'OpenSSH 8.9p1 Ubuntu 3ubuntu0.1'.
+    name: String,
+    ///CPE 2.2 URIs of applications, e.g. 'cpe:/a:openbsd:openssh:8.9p1'.
+    cpes: Vec<String>,
+    product: String,
+    version: String,
+}
+
+impl NvdMatch {
+    pub fn new(target: &str, hosts: &[NmapHost], index: Arc<NvdIndex>) -> NvdMatch {
+        let services = hosts
+            .iter()
+            .flat_map(|host| &host.ports)
+            .filter(|result| matches!(result.port.state, PortState::Open))
+            .map(|result| Service {
+                port: result.port.num,
+                name: format!("{} {}", result.service_product, result.service_version)
+                    .trim()
+                    .to_string(),
+                cpes: result
+                    .service_cpes
+                    .iter()
+                    .filter(|cpe| cpe.starts_with("cpe:/a:"))
+                    .cloned()
+                    .collect(),
+                product: result.service_product.clone(),
+                version: result.service_version.clone(),
+            })
+            .filter(|service| !service.cpes.is_empty() || !service.product.is_empty())
+            .collect();
+
+        NvdMatch {
+            host: target.to_string(),
+            services,
+            index,
+        }
+    }
+
+    ///Whether any service has something to match.
+    pub fn has_services(&self) -> bool {
+        !self.services.is_empty()
+    }
+
+    fn matches(&self, service: &Service) -> Vec<CveScanResult> {
+        //The CPE names the vendor, which makes it far more precise than Nmap's product name.
+        let lookups: Vec<(Option<&str>, String, String, String)> = if service.cpes.is_empty() {
+            //e.g. 'Apache Tomcat' -> 'apache_tomcat'. Only the first word of the version, the rest is usually the distribution.
+            let Some(version) = service.version.split_whitespace().next() else {
+                return Vec::new();
+            };
+            vec![(
+                None,
+                service.product.to_lowercase().replace(' ', "_"),
+                version.to_string(),
+                String::new(),
+            )]
+        } else {
+            service
+                .cpes
+                .iter()
+                .filter_map(|cpe| {
+                    let mut fields = cpe.trim_start_matches("cpe:/a:").split(':');
+                    let vendor = fields.next()?;
+                    let product = fields.next()?;
+                    let version = fields.next().filter(|version| !version.is_empty())?;
+                    Some((
+                        Some(vendor),
+                        product.to_string(),
+                        version.to_string(),
+                        cpe.clone(),
+                    ))
+                })
+                .collect()
+        };
+
+        let mut results = Vec::new();
+        for (vendor, product, version, cpe) in lookups {
+            for candidate in self.index.candidates(vendor, &product, &version) {
+                let reason = if vendor.is_some() {
+                    candidate.reason
+                } else {
+                    format!(
+                        "{}, matched by the product name '{}' without a CPE",
+                        candidate.reason, product
+                    )
+                };
+                results.push(CveScanResult {
+                    host: self.host.clone(),
+                    port: Some(service.port),
+                    service: service.name.clone(),
+                    cpe: cpe.clone(),
+                    id: candidate.id.to_string(),
+                    cvss: candidate.cve.cvss,
+                    kind: String::from("cve"),
+                    exploit: false,
+                    script: String::from("nvd"),
+                    reason: format!("{} ({})", reason, candidate.criteria.criteria),
+                });
+            }
+        }
+        results
+    }
+}
+
+impl Scan for NvdMatch {
+    type ScanResult = CveScanResult;
+
+    fn run(&self) -> Result<Vec<CveScanResult>, ScanError> {
+        logger::print_ok(&format!(
+            "Matching {} services against the local NVD index...",
+            self.services.len()
+        ));
+        let results = self.parse_output();
+        self.print_results(&results);
+        Ok(results)
+    }
+
+    ///Sorted from the highest to the lowest score, like the results of `CveScan`.
+    fn parse_output(&self) -> Vec<CveScanResult> {
+        let mut results: Vec<CveScanResult> = self
+            .services
+            .iter()
+            .flat_map(|service| self.matches(service))
+            .collect();
+        results.sort_by(|a, b| b.cvss.unwrap_or(-1.0).total_cmp(&a.cvss.unwrap_or(-1.0)));
+        results
+    }
+
+    fn print_results(&self, results: &[CveScanResult]) {
+        if results.is_empty() {
+            logger::print_ok("No CVE candidates in the local NVD index.");
+            return;
+        }
+
+        let mut table = Table::new();
+        table
+            .load_preset(UTF8_FULL)
+            .apply_modifier(UTF8_ROUND_CORNERS)
+            .set_content_arrangement(ContentArrangement::DynamicFullWidth)
+            .set_header(vec!["CVSS", "ID", "Port/service", "Why"]);
+        for result in results {
+            let severity = result
+                .cvss
+                .map(Severity::from_cvss)
+                .unwrap_or(Severity::Info);
+            table.add_row(vec![
+                Cell::new(
+                    result
+                        .cvss
+                        .map(|cvss| format!("{:.1}", cvss))
+                        .unwrap_or_default(),
+                )
+                .fg(severity.color()),
+                Cell::new(&result.id),
+                Cell::new(result.location()),
+                Cell::new(&result.reason),
+            ]);
+        }
+        println!("{}", table);
+    }
+
+    ///The same findings as `CveScan`, so a CVE both report is merged into one.
+    fn to_findings(&self, results: &[CveScanResult]) -> Vec<Finding> {
+        cve::findings(results, "nvd", None)
+    }
+
+    fn print_command(&self) {}
+
+    ///Nothing to install, only the index has to be imported.
+    fn is_installed(&self) -> bool {
+        true
+    }
+}
@@ -0,0 +1,112 @@
+use std::{path::PathBuf, time::Duration};
+
+use super::{
+    error::ScanError,
+    finding::Finding,
+    nmap::{self, NmapHost},
+    process,
+    scan::Scan,
+};
+use crate::logger;
+
+///Host discovery doesn't touch any ports, but a few /16s still take a while.
+const DEFAULT_TIMEOUT: Duration = Duration::from_secs(60 * 60);
+
+///Nmap host discovery (-sn) over address ranges, to find out which hosts are worth a full scan.
+pub struct PingSweep {
+    output_file: PathBuf,
+    scan_args: Vec<String>,
+    timeout: Duration,
+}
+
+impl PingSweep {
+    ///With `list_only` no packets are sent and every address in the ranges is returned (-sL), for hosts that don't answer pings.
+    ///`excludes` are addresses or ranges within the ranges that are skipped, e.g. because they're out of scope.
+    pub fn new(
+        output_dir: PathBuf,
+        ranges: &[String],
+        excludes: &[String],
+        list_only: bool,
+    ) -> PingSweep {
+        let output_file = output_dir.join("nmap_sweep_xml");
+        let mut scan_args = if list_only {
+            //-n, otherwise a list scan does a reverse lookup of every single address.
+            vec![String::from("-sL"), String::from("-n")]
+        } else {
+            vec![String::from("-sn")]
+        };
+        if !excludes.is_empty() {
+            scan_args.push(String::from("--exclude"));
+            scan_args.push(excludes.join(","));
+        }
+        scan_args.push(String::from("-oX"));
+        scan_args.push(output_file.to_str().unwrap().to_string());
+        //-6 applies to all ranges, Nmap can't mix IPv4 and IPv6 in a single scan anyway.
+        for range in ranges {
+            scan_args.extend(nmap::target_args(range));
+        }
+
+        PingSweep {
+            output_file,
+            scan_args,
+            timeout: DEFAULT_TIMEOUT,
+        }
+    }
+
+    ///Overrides the default timeout, if one is given.
+    pub fn with_timeout(mut self, timeout: Option<Duration>) -> PingSweep {
+        if let Some(timeout) = timeout {
+            self.timeout = timeout;
+        }
+        self
+    }
+}
+
+impl Scan for PingSweep {
+    type ScanResult = NmapHost;
+
+    ///Returns only the hosts that are up, or all listed hosts for a list scan.
+    fn run(&self) -> Result<Vec<NmapHost>, ScanError> {
+        logger::print_ok("Running Nmap host discovery...");
+        self.print_command();
+
+        let output = process::execute("nmap", &self.scan_args, &self.output_file, self.timeout)?;
+        let results = self.parse_output();
+        logger::print_ok(&format!(
+            "Host discovery finished in {}s, {} hosts to scan.",
+            output.duration.as_secs(),
+            results.len()
+        ));
+        self.print_results(&results);
+        Ok(results)
+    }
+
+    fn parse_output(&self) -> Vec<NmapHost> {
+        nmap::parse_xml_file(&self.output_file)
+            .hosts
+            .into_iter()
+            //A list scan reports every host as 'unknown'.
+            .filter(|host| host.status.state != "down")
+            .collect()
+    }
+
+    fn print_results(&self, hosts: &[NmapHost]) {
+        for host in hosts {
+            logger::print_debug(&format!("Host found: {}", host.name()));
+        }
+    }
+
+    ///Hosts being up isn't a finding. The per-host scans report what matters.
+    fn to_findings(&self, _hosts: &[NmapHost]) -> Vec<Finding> {
+        Vec::new()
+    }
+
+    fn print_command(&self) {
+        logger::print_ok(&format!("Command used: nmap {}", self.scan_args.join(" ")));
+    }
+
+    ///Nmap's installation is checked in main.rs, see `NmapScan`.
+    fn is_installed(&self) -> bool {
+        true
+    }
+}
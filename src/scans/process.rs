@@ -0,0 +1,239 @@
+use chrono::{DateTime, Utc};
+use serde::{Deserialize, Serialize};
+use std::{
+    fs::{File, OpenOptions},
+    io::{self, BufRead, BufReader, Read, Write},
+    path::{Path, PathBuf},
+    process::{Child, Command, ExitStatus, Stdio},
+    thread,
+    time::{Duration, Instant},
+};
+
+use super::error::ScanError;
+use crate::logger;
+
+///How often the child process is polled for completion.
+const POLL_INTERVAL: Duration = Duration::from_millis(250);
+///Number of stderr lines that are attached to a `ScanError`.
+const STDERR_TAIL_LINES: usize = 20;
+///Asking for the version should be instant. Anything that takes longer is waiting for input or hanging.
+const VERSION_TIMEOUT: Duration = Duration::from_secs(10);
+
+///Result of a tool that ran to completion with a zero exit status.
+pub struct ProcessOutput {
+    pub duration: Duration,
+    ///For tools that only report on stdout.
+    pub stdout_log: PathBuf,
+}
+
+///Name of the file in the output directory that every executed command is appended to, one JSON object per line.
+pub const COMMAND_LOG: &str = "commands.jsonl";
+
+///A command that was executed during a run, as recorded in `COMMAND_LOG`.
+#[derive(Serialize, Deserialize, Debug, Clone)]
+pub struct ExecutedCommand {
+    pub tool: String,
+    pub args: Vec<String>,
+    pub started: DateTime<Utc>,
+    pub duration_secs: u64,
+    ///'success', 'exit <code>', 'timeout' or 'error'.
+    pub outcome: String,
+}
+
+///Shared process-execution layer for all `Scan` implementations.
+///Spawns `tool` with `args`, streams stdout/stderr to `<log_base>.stdout.log` and `<log_base>.stderr.log`
+///and kills the child when it runs longer than `timeout`.
+///Every invocation is recorded in the `COMMAND_LOG` next to the log files.
+pub fn execute(
+    tool: &str,
+    args: &[String],
+    log_base: &Path,
+    timeout: Duration,
+) -> Result<ProcessOutput, ScanError> {
+    let started = Utc::now();
+    let started_instant = Instant::now();
+    let result = spawn_and_wait(tool, args, log_base, timeout);
+
+    let outcome = match &result {
+        Ok(_) => String::from("success"),
+        Err(ScanError::NonZeroExit {
+            status: Some(code), ..
+        }) => format!("exit {}", code),
+        Err(ScanError::Timeout { .. }) => String::from("timeout"),
+        Err(_) => String::from("error"),
+    };
+    let command = ExecutedCommand {
+        tool: tool.to_string(),
+        args: args.to_vec(),
+        started,
+        duration_secs: started_instant.elapsed().as_secs(),
+        outcome,
+    };
+    if let Some(output_dir) = log_base.parent() {
+        if let Err(err) = record_command(&output_dir.join(COMMAND_LOG), &command) {
+            logger::print_err(&format!("{}: Unable to record command: {}", tool, err));
+        }
+    }
+
+    result
+}
+
+fn record_command(command_log: &Path, command: &ExecutedCommand) -> io::Result<()> {
+    let mut line = serde_json::to_string(command)?;
+    line.push('\n');
+    //Scans run in parallel. A single append of a single line keeps the lines from interleaving.
+    OpenOptions::new()
+        .create(true)
+        .append(true)
+        .open(command_log)?
+        .write_all(line.as_bytes())
+}
+
+///Reads all commands recorded in an output directory.
+pub fn executed_commands(output_dir: &Path) -> Vec<ExecutedCommand> {
+    let Ok(file) = File::open(output_dir.join(COMMAND_LOG)) else {
+        return Vec::new();
+    };
+    BufReader::new(file)
+        .lines()
+        .map_while(Result::ok)
+        .filter_map(|line| serde_json::from_str(&line).ok())
+        .collect()
+}
+
+fn spawn_and_wait(
+    tool: &str,
+    args: &[String],
+    log_base: &Path,
+    timeout: Duration,
+) -> Result<ProcessOutput, ScanError> {
+    let stdout_log = log_path(log_base, "stdout.log");
+    let stderr_log = log_path(log_base, "stderr.log");
+    let (stdout_file, stderr_file) = match (File::create(&stdout_log), File::create(&stderr_log)) {
+        (Ok(stdout_file), Ok(stderr_file)) => (stdout_file, stderr_file),
+        (Err(err), _) | (_, Err(err)) => {
+            logger::print_err(&format!("{}: Unable to create log files: {}", tool, err));
+            return Err(ScanError::Runtime(tool.to_string()));
+        }
+    };
+
+    logger::print_debug(&format!(
+        "{}: Logging output to {} and {}",
+        tool,
+        stdout_log.display(),
+        stderr_log.display()
+    ));
+    let started = Instant::now();
+    let mut child = match Command::new(tool)
+        .args(args)
+        .stdin(Stdio::null())
+        .stdout(Stdio::from(stdout_file))
+        .stderr(Stdio::from(stderr_file))
+        .spawn()
+    {
+        Ok(child) => child,
+        Err(err) => {
+            logger::print_err(&format!("{}: {}", tool, err));
+            return Err(ScanError::Runtime(tool.to_string()));
+        }
+    };
+
+    let status = match wait_with_timeout(&mut child, timeout) {
+        Ok(Some(status)) => status,
+        Ok(None) => {
+            return Err(ScanError::Timeout {
+                scan: tool.to_string(),
+                timeout,
+                stderr_tail: read_tail(&stderr_log, STDERR_TAIL_LINES),
+            })
+        }
+        Err(err) => {
+            logger::print_err(&format!("{}: {}", tool, err));
+            return Err(ScanError::Runtime(tool.to_string()));
+        }
+    };
+
+    if !status.success() {
+        return Err(ScanError::NonZeroExit {
+            scan: tool.to_string(),
+            status: status.code(),
+            stderr_tail: read_tail(&stderr_log, STDERR_TAIL_LINES),
+        });
+    }
+
+    Ok(ProcessOutput {
+        duration: started.elapsed(),
+        stdout_log,
+    })
+}
+
+///Waits for the child to exit, killing it once it runs longer than `timeout`. Returns `None` if it was killed.
+fn wait_with_timeout(child: &mut Child, timeout: Duration) -> io::Result<Option<ExitStatus>> {
+    let started = Instant::now();
+    //Poll instead of blocking on wait() so we can enforce the wall-clock timeout.
+    loop {
+        match child.try_wait() {
+            Ok(Some(status)) => return Ok(Some(status)),
+            Ok(None) if started.elapsed() >= timeout => {
+                //The child might have exited between try_wait() and kill(), so errors are ignored here.
+                let _ = child.kill();
+                let _ = child.wait();
+                return Ok(None);
+            }
+            Ok(None) => thread::sleep(POLL_INTERVAL),
+            Err(err) => {
+                let _ = child.kill();
+                return Err(err);
+            }
+        }
+    }
+}
+
+///Returns the first line of the tool's version output, or `None` if the tool can't be run or doesn't answer in time.
+pub fn tool_version(tool: &str) -> Option<String> {
+    //Not every tool agrees on how to ask for the version.
+    let flag = match tool {
+        "nuclei" => "-version",
+        _ => "--version",
+    };
+    let mut child = Command::new(tool)
+        .arg(flag)
+        .stdin(Stdio::null())
+        .stdout(Stdio::piped())
+        .stderr(Stdio::piped())
+        .spawn()
+        .ok()?;
+    //A few lines of version output fit in the pipe buffers, so the child can't block on writing them.
+    wait_with_timeout(&mut child, VERSION_TIMEOUT).ok()??;
+    let (mut stdout, mut stderr) = (Vec::new(), Vec::new());
+    child.stdout.take()?.read_to_end(&mut stdout).ok()?;
+    child.stderr.take()?.read_to_end(&mut stderr).ok()?;
+    //Nuclei prints its version to stderr.
+    let text = if stdout.is_empty() {
+        String::from_utf8_lossy(&stderr).to_string()
+    } else {
+        String::from_utf8_lossy(&stdout).to_string()
+    };
+    text.lines()
+        .map(str::trim)
+        .find(|line| !line.is_empty())
+        .map(String::from)
+}
+
+///Appends `suffix` to the file name of `base`, e.g. `nmap_xml` -> `nmap_xml.stdout.log`.
+pub fn log_path(base: &Path, suffix: &str) -> PathBuf {
+    let mut path = base.as_os_str().to_owned();
+    path.push(".");
+    path.push(suffix);
+    PathBuf::from(path)
+}
+
+///Returns the last `lines` lines of a file, or an empty string if it can't be read.
+fn read_tail(path: &Path, lines: usize) -> String {
+    let Ok(file) = File::open(path) else {
+        return String::new();
+    };
+    let all_lines: Vec<String> = BufReader::new(file).lines().map_while(Result::ok).collect();
+    let start = all_lines.len().saturating_sub(lines);
+    all_lines[start..].join("\n")
+}
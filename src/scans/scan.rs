@@ -1,4 +1,4 @@
-use super::error::{self, ScanError};
+use super::{error::ScanError, finding::Finding};
 
 //Not entirely happy about the fact that all the functions in this trait are also available from outside the implementing struct.\
 //In an ideal case only the 'run()' function is public to the caller. Currently all functions are visible to the caller.
@@ -7,7 +7,7 @@ pub trait Scan {
     //This is awesome lol. This is called an "Associated type" and it functions as a polymorphic type.
     //Each struct that implements this trait must define this type. The scan "NmapScan" can for example define the following:
     //  type ScanResult = NmapScanResult;
-//While the scan "FeroxbusterScan" can define the following:
+    //While the scan "FeroxbusterScan" can define the following:
     //  type ScanResult = FeroxbusterScanResult;
     //This allows me to define a polymorphic return type.
     //https://doc.rust-lang.org/rust-by-example/generics/assoc_items/types.html
@@ -16,11 +16,14 @@ pub trait Scan {
     type ScanResult;
 
     fn run(&self) -> Result<Vec<Self::ScanResult>, ScanError>;
-    
+
     ///Parses scan output from a file (e.g. XML, JSON, etc.) to a Vec of `ScanResult`.
     fn parse_output(&self) -> Vec<Self::ScanResult>;
 
     fn print_results(&self, scan_results: &[Self::ScanResult]);
+
+    ///Converts the tool specific results to the common `Finding` type.
+    fn to_findings(&self, scan_results: &[Self::ScanResult]) -> Vec<Finding>;
     fn print_command(&self);
     fn is_installed(&self) -> bool;
 }
@@ -0,0 +1,851 @@
+use comfy_table::{modifiers::UTF8_ROUND_CORNERS, presets::UTF8_FULL, Table};
+use serde::{Deserialize, Serialize};
+use serde_json::Value;
+use std::{
+    fs,
+    path::{Path, PathBuf},
+    time::Duration,
+};
+use which::which;
+
+use super::{
+    error::ScanError,
+    finding::{Finding, Severity},
+    nmap::{NmapHost, Protocol, ScriptElement, ScriptOutput},
+    nmap_script::NmapScriptScan,
+    process,
+    scan::Scan,
+};
+use crate::{logger, targets};
+
+///Enumeration only. smb-brute and smb-flood don't belong in an automated run.
+const SMB_SCRIPTS: &[&str] = &[
+    "smb-protocols",
+    "smb-security-mode",
+    "smb2-security-mode",
+    "smb-os-discovery",
+    "smb-enum-shares",
+];
+///enum4linux-ng tries a lot of RPC calls, which can be slow against domain controllers.
+const DEFAULT_TIMEOUT: Duration = Duration::from_secs(20 * 60);
+
+///Ordered from weakest to strongest, so the weakest mode any dialect allows can be kept.
+#[derive(Serialize, Deserialize, Debug, Clone, Copy, PartialEq, Eq, PartialOrd, Ord)]
+#[serde(rename_all = "lowercase")]
+pub enum Signing {
+    Disabled,
+    Enabled,
+    Required,
+}
+
+#[derive(Serialize, Deserialize, Debug, Default)]
+pub struct SmbShare {
+    pub name: String,
+    ///e.g. 'Disk', 'IPC' or 'STYPE_DISKTREE', depending on the tool that found it.
+    pub kind: String,
+    pub comment: String,
+    ///e.g. 'READ' or 'READ/WRITE'. `None` when no tool could tell.
+    pub anonymous_access: Option<String>,
+    ///Access of the account Nmap ended up using, usually guest.
+    pub user_access: Option<String>,
+}
+
+///Everything the SMB tools found out about a single SMB service, merged.
+#[derive(Serialize, Deserialize, Debug, Default)]
+pub struct SmbScanResult {
+    pub host: String,
+    pub port: u16,
+    ///Named like enum4linux-ng does, e.g. 'SMB 1.0' or 'SMB 3.1.1'.
+    pub dialects: Vec<String>,
+    pub smbv1: bool,
+    ///The weakest signing mode of all dialects. `None` when no tool could tell.
+    pub signing: Option<Signing>,
+    pub null_session: bool,
+    pub guest_access: bool,
+    pub shares: Vec<SmbShare>,
+    pub os: Option<String>,
+    pub computer_name: Option<String>,
+    pub domain: Option<String>,
+    pub fqdn: Option<String>,
+}
+
+///Nmap's SMB scripts, plus smbclient and enum4linux-ng when they're installed.
+pub struct SmbScan {
+    host: String,
+    port: u16,
+    nmap_scan: NmapScriptScan,
+    smbclient_log_base: PathBuf,
+    enum4linux_log_base: PathBuf,
+    timeout: Duration,
+}
+
+impl SmbScan {
+    pub fn new(output_dir: PathBuf, target: &str, port: u16) -> SmbScan {
+        let host_file: String = target
+            .chars()
+            .map(|c| if c.is_ascii_alphanumeric() { c } else { '_' })
+            .collect();
+        let nmap_scan = NmapScriptScan::new(
+            output_dir.clone(),
+            "smb",
+            target,
+            port,
+            Protocol::Tcp,
+            SMB_SCRIPTS,
+            None,
+        );
+        SmbScan {
+            host: target.to_string(),
+            port,
+            nmap_scan,
+            smbclient_log_base: output_dir.join(format!("smbclient_{}_{}", port, host_file)),
+            enum4linux_log_base: output_dir.join(format!("enum4linux-ng_{}", host_file)),
+            timeout: DEFAULT_TIMEOUT,
+        }
+    }
+
+    ///Overrides the default timeout, if one is given. Applies to every tool separately.
+    pub fn with_timeout(mut self, timeout: Option<Duration>) -> SmbScan {
+        if let Some(timeout) = timeout {
+            self.timeout = timeout;
+        }
+        self.nmap_scan = self.nmap_scan.with_timeout(timeout);
+        self
+    }
+
+    ///Lists the shares with an empty username and password, i.e. a null session. -g makes the output parseable.
+    fn smbclient_args(&self) -> Vec<String> {
+        vec![
+            String::from("-L"),
+            format!("//{}", targets::host_in_url(&self.host)),
+            String::from("-U"),
+            String::from("%"),
+            String::from("-g"),
+            String::from("-p"),
+            self.port.to_string(),
+        ]
+    }
+
+    ///enum4linux-ng has no option for the port, it always tries 445 and 139.
+    fn enum4linux_args(&self) -> Vec<String> {
+        vec![
+            String::from("-A"),
+            String::from("-oJ"),
+            self.enum4linux_log_base.to_str().unwrap().to_string(),
+            self.host.clone(),
+        ]
+    }
+
+    ///The optional tools don't fail the scan, whatever Nmap found is still worth reporting.
+    fn run_optional(&self, tool: &str, args: &[String], log_base: &Path) {
+        if which(tool).is_err() {
+            logger::print_debug(&format!("{} is not installed, skipping it.", tool));
+            return;
+        }
+        logger::print_ok(&format!("Command used: {} {}", tool, args.join(" ")));
+        if let Err(err) = process::execute(tool, args, log_base, self.timeout) {
+            logger::print_warn(&err.to_string());
+        }
+    }
+}
+
+impl Scan for SmbScan {
+    type ScanResult = SmbScanResult;
+
+    fn run(&self) -> Result<Vec<SmbScanResult>, ScanError> {
+        logger::print_ok(&format!(
+            "Running SMB enumeration against {}:{}...",
+            self.host, self.port
+        ));
+        self.nmap_scan.run()?;
+        self.run_optional(
+            "smbclient",
+            &self.smbclient_args(),
+            &self.smbclient_log_base,
+        );
+        if self.port == 445 {
+            self.run_optional(
+                "enum4linux-ng",
+                &self.enum4linux_args(),
+                &self.enum4linux_log_base,
+            );
+        }
+
+        let results = self.parse_output();
+        self.print_results(&results);
+        Ok(results)
+    }
+
+    fn parse_output(&self) -> Vec<SmbScanResult> {
+        let mut result = SmbScanResult {
+            host: self.host.clone(),
+            port: self.port,
+            ..Default::default()
+        };
+        let hosts: Vec<NmapHost> = self.nmap_scan.parse_output();
+        for script in hosts.iter().flat_map(|host| {
+            host.ports
+                .iter()
+                .flat_map(|port| &port.scripts)
+                .chain(&host.scripts)
+        }) {
+            apply_script(&mut result, script);
+        }
+        //Only there when the tools ran, e.g. not when they aren't installed.
+        let smbclient_log = process::log_path(&self.smbclient_log_base, "stdout.log");
+        if let Ok(output) = fs::read_to_string(smbclient_log) {
+            apply_smbclient(&mut result, &output);
+        }
+        let enum4linux_json = process::log_path(&self.enum4linux_log_base, "json");
+        if let Some(json) = fs::read_to_string(enum4linux_json)
+            .ok()
+            .and_then(|contents| serde_json::from_str(&contents).ok())
+        {
+            apply_enum4linux(&mut result, &json);
+        }
+        vec![result]
+    }
+
+    fn print_results(&self, results: &[SmbScanResult]) {
+        for result in results {
+            let yes_no = |value: bool| if value { "yes" } else { "no" };
+            let unknown = |value: &Option<String>| value.clone().unwrap_or_else(|| "?".into());
+            let mut table = Table::new();
+            table
+                .load_preset(UTF8_FULL)
+                .apply_modifier(UTF8_ROUND_CORNERS)
+                .set_header(vec![
+                    format!("SMB {}:{}", result.host, result.port),
+                    String::new(),
+                ]);
+            table.add_row(vec!["Dialects", &result.dialects.join(", ")]);
+            table.add_row(vec!["SMBv1", yes_no(result.smbv1)]);
+            table.add_row(vec![
+                "Signing",
+                match result.signing {
+                    Some(Signing::Required) => "required",
+                    Some(Signing::Enabled) => "enabled, not required",
+                    Some(Signing::Disabled) => "disabled",
+                    None => "?",
+                },
+            ]);
+            table.add_row(vec!["Null session", yes_no(result.null_session)]);
+            table.add_row(vec!["Guest access", yes_no(result.guest_access)]);
+            table.add_row(vec!["OS".to_string(), unknown(&result.os)]);
+            table.add_row(vec!["Computer".to_string(), unknown(&result.computer_name)]);
+            table.add_row(vec!["Domain".to_string(), unknown(&result.domain)]);
+            table.add_row(vec!["FQDN".to_string(), unknown(&result.fqdn)]);
+            println!("{table}");
+
+            if result.shares.is_empty() {
+                logger::print_warn("No SMB shares found.");
+                continue;
+            }
+            let mut table = Table::new();
+            table
+                .load_preset(UTF8_FULL)
+                .apply_modifier(UTF8_ROUND_CORNERS)
+                .set_header(vec!["Share", "Type", "Anonymous", "Guest/user", "Comment"]);
+            for share in &result.shares {
+                table.add_row(vec![
+                    share.name.clone(),
+                    share.kind.clone(),
+                    unknown(&share.anonymous_access),
+                    unknown(&share.user_access),
+                    share.comment.clone(),
+                ]);
+            }
+            println!("{table}");
+        }
+    }
+
+    fn to_findings(&self, results: &[SmbScanResult]) -> Vec<Finding> {
+        let mut findings = Vec::new();
+        for result in results {
+            let finding = |id: &str, title: &str, severity, evidence: Vec<String>| {
+                let mut finding = Finding::new(
+                    id.to_string(),
+                    title.to_string(),
+                    severity,
+                    result.host.clone(),
+                    "smb",
+                );
+                finding.port = Some(result.port);
+                finding.evidence = evidence;
+                finding
+            };
+
+            if result.smbv1 {
+                findings.push(finding(
+                    "smb:smbv1-enabled",
+                    "SMBv1 enabled",
+                    Severity::Medium,
+                    vec![format!("Dialects: {}", result.dialects.join(", "))],
+                ));
+            }
+            //Same ids and location as the smb-security-mode interpretation of -sC in nse.rs, so both end up as one finding.
+            if let Some(signing @ (Signing::Enabled | Signing::Disabled)) = result.signing {
+                let mut signing_finding = finding(
+                    "nmap:smb-signing-not-required",
+                    "SMB signing not required",
+                    Severity::Medium,
+                    vec![match signing {
+                        Signing::Disabled => String::from("Message signing disabled"),
+                        _ => String::from("Message signing enabled but not required"),
+                    }],
+                );
+                signing_finding.port = None;
+                findings.push(signing_finding);
+            }
+            if result.guest_access {
+                let mut guest_finding = finding(
+                    "nmap:smb-guest-access",
+                    "SMB guest access allowed",
+                    Severity::Medium,
+                    vec![String::from("Logged in as guest")],
+                );
+                guest_finding.port = None;
+                findings.push(guest_finding);
+            }
+            if result.null_session {
+                findings.push(finding(
+                    "smb:null-session",
+                    "SMB null session allowed",
+                    Severity::Medium,
+                    vec![String::from(
+                        "Logged in with an empty username and password",
+                    )],
+                ));
+            }
+
+            //IPC$ is readable without credentials on most hosts and holds no files.
+            let open_shares: Vec<&SmbShare> = result
+                .shares
+                .iter()
+                .filter(|share| !share.name.eq_ignore_ascii_case("IPC$"))
+                .filter(|share| {
+                    share
+                        .anonymous_access
+                        .as_deref()
+                        .is_some_and(|access| access.contains("READ") || access.contains("WRITE"))
+                })
+                .collect();
+            if !open_shares.is_empty() {
+                let writable = open_shares.iter().any(|share| {
+                    share
+                        .anonymous_access
+                        .as_deref()
+                        .is_some_and(|access| access.contains("WRITE"))
+                });
+                findings.push(finding(
+                    "smb:anonymous-shares",
+                    if writable {
+                        "SMB shares writable without credentials"
+                    } else {
+                        "SMB shares readable without credentials"
+                    },
+                    if writable {
+                        Severity::High
+                    } else {
+                        Severity::Medium
+                    },
+                    open_shares
+                        .iter()
+                        .map(|share| {
+                            format!(
+                                "{} ({}) {}",
+                                share.name,
+                                share.anonymous_access.as_deref().unwrap_or_default(),
+                                share.comment
+                            )
+                            .trim()
+                            .to_string()
+                        })
+                        .collect(),
+                ));
+            }
+            if !result.shares.is_empty() {
+                findings.push(finding(
+                    "smb:shares",
+                    &format!(
+                        "SMB shares: {}",
+                        result
+                            .shares
+                            .iter()
+                            .map(|share| share.name.as_str())
+                            .collect::<Vec<&str>>()
+                            .join(", ")
+                    ),
+                    Severity::Info,
+                    result
+                        .shares
+                        .iter()
+                        .map(|share| format!("{} {} {}", share.name, share.kind, share.comment))
+                        .map(|line| line.trim().to_string())
+                        .collect(),
+                ));
+            }
+
+            let mut host_info = Vec::new();
+            for (label, value) in [
+                ("OS", &result.os),
+                ("Computer name", &result.computer_name),
+                ("Domain", &result.domain),
+                ("FQDN", &result.fqdn),
+            ] {
+                if let Some(value) = value {
+                    host_info.push(format!("{}: {}", label, value));
+                }
+            }
+            if !host_info.is_empty() {
+                findings.push(finding(
+                    "smb:host-info",
+                    &format!(
+                        "SMB discloses host information (domain {})",
+                        result.domain.as_deref().unwrap_or("unknown")
+                    ),
+                    Severity::Info,
+                    host_info,
+                ));
+            }
+        }
+
+        let raw_reference = self.nmap_scan.output_file().display().to_string();
+        for finding in &mut findings {
+            finding.raw_reference = Some(raw_reference.clone());
+        }
+        findings
+    }
+
+    fn print_command(&self) {
+        self.nmap_scan.print_command();
+    }
+
+    ///Nmap's installation is checked in main.rs, the other tools are optional.
+    fn is_installed(&self) -> bool {
+        true
+    }
+}
+
+fn apply_script(result: &mut SmbScanResult, script: &ScriptOutput) {
+    match script.id.as_str() {
+        "smb-protocols" => {
+            for dialect in script
+                .table("dialects")
+                .unwrap_or_default()
+                .iter()
+                .filter_map(ScriptElement::value)
+            {
+                add_dialect(result, dialect);
+            }
+        }
+        //SMBv1 only.
+        "smb-security-mode" => {
+            if let Some(mode) = script.elem("message_signing") {
+                let signing = match mode {
+                    "required" => Signing::Required,
+                    "disabled" => Signing::Disabled,
+                    _ => Signing::Enabled,
+                };
+                add_signing(result, signing);
+            }
+            apply_account(result, script.elem("account_used"));
+        }
+        //One table per dialect, e.g. '3:1:1', holding a description of the signing mode.
+        "smb2-security-mode" => {
+            for mode in script
+                .elements
+                .iter()
+                .flat_map(ScriptElement::elements)
+                .filter_map(ScriptElement::value)
+            {
+                let signing = if mode.contains("and required") {
+                    Signing::Required
+                } else if mode.contains("disabled") {
+                    Signing::Disabled
+                } else {
+                    Signing::Enabled
+                };
+                add_signing(result, signing);
+            }
+        }
+        "smb-os-discovery" => {
+            let elem = |key| script.elem(key).map(String::from);
+            result.os = result.os.take().or_else(|| elem("os"));
+            result.computer_name = result.computer_name.take().or_else(|| elem("server"));
+            result.domain = result
+                .domain
+                .take()
+                .or_else(|| elem("domain_dns"))
+                .or_else(|| elem("workgroup"));
+            result.fqdn = result.fqdn.take().or_else(|| elem("fqdn"));
+        }
+        //One table per share, keyed by its UNC path, e.g. '\\10.0.0.5\ADMIN$'.
+        "smb-enum-shares" => {
+            apply_account(result, script.elem("account_used"));
+            for share in script
+                .elements
+                .iter()
+                .filter(|element| !element.elements().is_empty())
+            {
+                let Some(path) = share.key() else {
+                    continue;
+                };
+                let elem = |key| {
+                    share
+                        .elements()
+                        .iter()
+                        .find(|element| element.key() == Some(key))
+                        .and_then(ScriptElement::value)
+                };
+                add_share(
+                    result,
+                    SmbShare {
+                        name: path.rsplit('\\').next().unwrap_or(path).to_string(),
+                        kind: elem("Type").unwrap_or_default().to_string(),
+                        comment: elem("Comment").unwrap_or_default().to_string(),
+                        anonymous_access: elem("Anonymous access").map(String::from),
+                        user_access: elem("Current user access").map(String::from),
+                    },
+                );
+            }
+        }
+        _ => {}
+    }
+}
+
+///Which account Nmap's SMB library managed to log in with: '<blank>' is a null session.
+fn apply_account(result: &mut SmbScanResult, account: Option<&str>) {
+    match account {
+        Some("guest") => result.guest_access = true,
+        Some("<blank>") => result.null_session = true,
+        _ => {}
+    }
+}
+
+///Output of `smbclient -L -g`: 'Disk|share|comment' lines. Any share at all means the null session worked.
+fn apply_smbclient(result: &mut SmbScanResult, output: &str) {
+    for line in output.lines() {
+        let mut fields = line.splitn(3, '|');
+        let (Some(kind), Some(name)) = (fields.next(), fields.next()) else {
+            continue;
+        };
+        if !matches!(kind, "Disk" | "IPC" | "Printer") {
+            continue;
+        }
+        result.null_session = true;
+        add_share(
+            result,
+            SmbShare {
+                name: name.to_string(),
+                kind: kind.to_string(),
+                comment: fields.next().unwrap_or_default().to_string(),
+                ..Default::default()
+            },
+        );
+    }
+}
+
+///The JSON file enum4linux-ng writes with -oJ. Everything is optional, it only contains what it managed to find.
+fn apply_enum4linux(result: &mut SmbScanResult, json: &Value) {
+    let text = |value: &Value, key: &str| value.get(key).and_then(Value::as_str).map(String::from);
+    let flag = |value: &Value, key: &str| value.get(key).and_then(Value::as_bool);
+
+    if let Some(sessions) = json.get("sessions") {
+        result.null_session |= flag(sessions, "null") == Some(true);
+        //A random username only works when the server maps unknown users to guest.
+        result.guest_access |= flag(sessions, "random_user") == Some(true);
+    }
+    if let Some(smb_dialects) = json.get("smb_dialects") {
+        //Older versions put the dialects next to the other keys.
+        let supported = smb_dialects
+            .get("Supported dialects")
+            .unwrap_or(smb_dialects);
+        for (dialect, _) in
+            supported
+                .as_object()
+                .into_iter()
+                .flatten()
+                .filter(|(dialect, enabled)| {
+                    //Not 'SMB signing required', which sits next to them in older versions.
+                    dialect
+                        .strip_prefix("SMB ")
+                        .is_some_and(|version| version.starts_with(|c: char| c.is_ascii_digit()))
+                        && enabled.as_bool() == Some(true)
+                })
+        {
+            add_dialect(result, dialect);
+        }
+        //Nmap tells the weakest mode apart, enum4linux-ng only whether it's required.
+        if result.signing.is_none() {
+            result.signing = flag(smb_dialects, "SMB signing required").map(|required| {
+                if required {
+                    Signing::Required
+                } else {
+                    Signing::Enabled
+                }
+            });
+        }
+    }
+    for (name, share) in json
+        .get("shares")
+        .and_then(Value::as_object)
+        .into_iter()
+        .flatten()
+    {
+        let listing = share
+            .get("access")
+            .and_then(|access| access.get("listing"))
+            .and_then(Value::as_str);
+        add_share(
+            result,
+            SmbShare {
+                name: name.clone(),
+                kind: text(share, "type").unwrap_or_default(),
+                comment: text(share, "comment").unwrap_or_default(),
+                anonymous_access: listing.map(|listing| {
+                    if listing == "ok" {
+                        String::from("READ")
+                    } else {
+                        String::from("<none>")
+                    }
+                }),
+                user_access: None,
+            },
+        );
+    }
+    if let Some(os_info) = json.get("os_info") {
+        result.os = result.os.take().or_else(|| text(os_info, "OS"));
+    }
+    if let Some(domain_info) = json.get("smb_domain_info") {
+        result.computer_name = result
+            .computer_name
+            .take()
+            .or_else(|| text(domain_info, "NetBIOS computer name"));
+        result.domain = result
+            .domain
+            .take()
+            .or_else(|| text(domain_info, "DNS domain"))
+            .or_else(|| text(domain_info, "NetBIOS domain name"));
+        result.fqdn = result.fqdn.take().or_else(|| text(domain_info, "FQDN"));
+    }
+}
+
+///Nmap calls the dialects '2:1:0' or '2.10' depending on its version. Both become enum4linux-ng's 'SMB 2.1'.
+fn add_dialect(result: &mut SmbScanResult, dialect: &str) {
+    let name = match dialect {
+        "2:0:2" | "2.02" => String::from("SMB 2.02"),
+        "2:1:0" | "2.10" => String::from("SMB 2.1"),
+        "3:0:0" | "3.00" => String::from("SMB 3.0"),
+        "3:0:2" | "3.02" => String::from("SMB 3.0.2"),
+        "3:1:1" | "3.11" => String::from("SMB 3.1.1"),
+        dialect if dialect.contains("SMBv1") || dialect.starts_with("NT LM") => {
+            String::from("SMB 1.0")
+        }
+        dialect => dialect.to_string(),
+    };
+    result.smbv1 |= name == "SMB 1.0";
+    if !result.dialects.contains(&name) {
+        result.dialects.push(name);
+    }
+}
+
+fn add_signing(result: &mut SmbScanResult, signing: Signing) {
+    result.signing = Some(result.signing.map_or(signing, |known| known.min(signing)));
+}
+
+///Shares found by several tools are merged. What a tool knows fills in what the others didn't.
+fn add_share(result: &mut SmbScanResult, share: SmbShare) {
+    match result
+        .shares
+        .iter_mut()
+        .find(|known| known.name.eq_ignore_ascii_case(&share.name))
+    {
+        Some(known) => {
+            if known.kind.is_empty() {
+                known.kind = share.kind;
+            }
+            if known.comment.is_empty() {
+                known.comment = share.comment;
+            }
+            known.anonymous_access = known.anonymous_access.take().or(share.anonymous_access);
+            known.user_access = known.user_access.take().or(share.user_access);
+        }
+        None => result.shares.push(share),
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    ///Trimmed from the output of `enum4linux-ng -A -oJ` against a Windows file server.
+    const ENUM4LINUX_JSON: &str = r#"{
+        "sessions": {"sessions_possible": true, "null": true, "random_user": false},
+        "smb_dialects": {
+            "Supported dialects": {"SMB 1.0": true, "SMB 2.02": true, "SMB 2.1": true, "SMB 3.0": false, "SMB 3.1.1": true},
+            "SMB signing required": false
+        },
+        "shares": {
+            "Backup": {"type": "Disk", "comment": "", "access": {"mapping": "ok", "listing": "ok"}},
+            "C$": {"type": "Disk", "comment": "Default share", "access": {"mapping": "denied", "listing": "n/a"}}
+        },
+        "os_info": {"OS": "Windows 10, Windows Server 2019, Windows Server 2016"},
+        "smb_domain_info": {
+            "NetBIOS computer name": "FS01",
+            "NetBIOS domain name": "CORP",
+            "DNS domain": "corp.local",
+            "FQDN": "fs01.corp.local"
+        }
+    }"#;
+
+    ///`smbclient -L //fs01 -U % -g`.
+    const SMBCLIENT_OUTPUT: &str =
+        "Disk|Public|Public files\nDisk|Backup|\nIPC|IPC$|Remote IPC\nWorkgroup|CORP|FS01\n";
+
+    fn enum4linux_result() -> SmbScanResult {
+        let mut result = SmbScanResult::default();
+        apply_enum4linux(&mut result, &serde_json::from_str(ENUM4LINUX_JSON).unwrap());
+        result
+    }
+
+    fn share<'a>(result: &'a SmbScanResult, name: &str) -> &'a SmbShare {
+        result
+            .shares
+            .iter()
+            .find(|share| share.name == name)
+            .unwrap()
+    }
+
+    #[test]
+    fn enum4linux_sessions_dialects_and_signing() {
+        let result = enum4linux_result();
+        assert!(result.null_session);
+        assert!(!result.guest_access);
+        assert_eq!(
+            result.dialects,
+            ["SMB 1.0", "SMB 2.02", "SMB 2.1", "SMB 3.1.1"]
+        );
+        assert!(result.smbv1);
+        assert_eq!(result.signing, Some(Signing::Enabled));
+    }
+
+    #[test]
+    fn enum4linux_shares_and_host_info() {
+        let result = enum4linux_result();
+        assert_eq!(result.shares.len(), 2);
+        let backup = share(&result, "Backup");
+        assert_eq!(backup.kind, "Disk");
+        assert_eq!(backup.anonymous_access.as_deref(), Some("READ"));
+        let admin = share(&result, "C$");
+        assert_eq!(admin.comment, "Default share");
+        assert_eq!(admin.anonymous_access.as_deref(), Some("<none>"));
+
+        assert_eq!(
+            result.os.as_deref(),
+            Some("Windows 10, Windows Server 2019, Windows Server 2016")
+        );
+        assert_eq!(result.computer_name.as_deref(), Some("FS01"));
+        assert_eq!(result.domain.as_deref(), Some("corp.local"));
+        assert_eq!(result.fqdn.as_deref(), Some("fs01.corp.local"));
+    }
+
+    #[test]
+    fn enum4linux_without_supported_dialects_key() {
+        let mut result = SmbScanResult::default();
+        let json = serde_json::json!({
+            "smb_dialects": {"SMB 1.0": false, "SMB 2.1": true, "SMB signing required": true}
+        });
+        apply_enum4linux(&mut result, &json);
+        assert_eq!(result.dialects, ["SMB 2.1"]);
+        assert!(!result.smbv1);
+        assert_eq!(result.signing, Some(Signing::Required));
+    }
+
+    #[test]
+    fn smbclient_shares_mean_a_null_session() {
+        let mut result = SmbScanResult::default();
+        apply_smbclient(&mut result, SMBCLIENT_OUTPUT);
+        assert!(result.null_session);
+        let names: Vec<&str> = result
+            .shares
+            .iter()
+            .map(|share| share.name.as_str())
+            .collect();
+        assert_eq!(names, ["Public", "Backup", "IPC$"]);
+        assert_eq!(share(&result, "Public").comment, "Public files");
+        assert_eq!(share(&result, "IPC$").kind, "IPC");
+    }
+
+    #[test]
+    fn smbclient_without_shares() {
+        let mut result = SmbScanResult::default();
+        apply_smbclient(
+            &mut result,
+            "session setup failed: NT_STATUS_ACCESS_DENIED\n",
+        );
+        assert!(!result.null_session);
+        assert!(result.shares.is_empty());
+    }
+
+    #[test]
+    fn shares_of_both_tools_are_merged() {
+        let mut result = SmbScanResult::default();
+        apply_smbclient(&mut result, SMBCLIENT_OUTPUT);
+        apply_enum4linux(&mut result, &serde_json::from_str(ENUM4LINUX_JSON).unwrap());
+        assert_eq!(result.shares.len(), 4);
+        let backup = share(&result, "Backup");
+        assert_eq!(backup.kind, "Disk");
+        assert_eq!(backup.anonymous_access.as_deref(), Some("READ"));
+    }
+
+    #[test]
+    fn nmap_dialect_names() {
+        let mut result = SmbScanResult::default();
+        for dialect in [
+            "2:0:2",
+            "2.10",
+            "3:1:1",
+            "3.11",
+            "NT LM 0.12 (SMBv1) [dangerous, but default]",
+        ] {
+            add_dialect(&mut result, dialect);
+        }
+        assert_eq!(
+            result.dialects,
+            ["SMB 2.02", "SMB 2.1", "SMB 3.1.1", "SMB 1.0"]
+        );
+        assert!(result.smbv1);
+    }
+
+    #[test]
+    fn weakest_signing_mode_wins() {
+        let mut result = SmbScanResult::default();
+        add_signing(&mut result, Signing::Required);
+        add_signing(&mut result, Signing::Disabled);
+        add_signing(&mut result, Signing::Enabled);
+        assert_eq!(result.signing, Some(Signing::Disabled));
+
+        //enum4linux-ng can't tell disabled from enabled, so it doesn't overrule Nmap.
+        apply_enum4linux(&mut result, &serde_json::from_str(ENUM4LINUX_JSON).unwrap());
+        assert_eq!(result.signing, Some(Signing::Disabled));
+    }
+
+    #[test]
+    fn nmap_account_used() {
+        let script = |id: &str, account: &str| ScriptOutput {
+            id: id.to_string(),
+            output: String::new(),
+            elements: vec![ScriptElement::Elem {
+                key: Some(String::from("account_used")),
+                value: account.to_string(),
+            }],
+        };
+        let mut result = SmbScanResult::default();
+        apply_script(&mut result, &script("smb-security-mode", "guest"));
+        assert!(result.guest_access);
+        assert!(!result.null_session);
+        apply_script(&mut result, &script("smb-enum-shares", "<blank>"));
+        assert!(result.null_session);
+    }
+}
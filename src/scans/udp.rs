@@ -0,0 +1,93 @@
+use std::{path::PathBuf, time::Duration};
+
+use super::{
+    error::ScanError,
+    finding::Finding,
+    nmap::{self, NmapHost},
+    process,
+    scan::Scan,
+};
+use crate::logger;
+
+///UDP scans are slow by nature: closed ports are only detected through rate limited ICMP replies.
+const DEFAULT_TIMEOUT: Duration = Duration::from_secs(60 * 60);
+
+///Nmap UDP scan of the most common UDP ports. Runs after the TCP scan, its open ports go through the same triggers.
+pub struct UdpScan {
+    output_file: PathBuf,
+    scan_args: Vec<String>,
+    timeout: Duration,
+}
+
+impl UdpScan {
+    pub fn new(output_dir: PathBuf, target: String, top_ports: u16) -> UdpScan {
+        let output_file = output_dir.join("nmap_udp_xml");
+        let mut scan_args = vec![
+            String::from("-sU"),
+            String::from("--top-ports"),
+            top_ports.to_string(),
+            //Without version detection most UDP ports end up as 'open|filtered'. The lowest intensity keeps it bearable.
+            String::from("-sV"),
+            String::from("--version-intensity"),
+            String::from("0"),
+            String::from("-oX"),
+            output_file.to_str().unwrap().to_string(),
+            //The TCP scan already decided whether the host is up.
+            String::from("-Pn"),
+        ];
+        scan_args.extend(nmap::target_args(&target));
+
+        UdpScan {
+            output_file,
+            scan_args,
+            timeout: DEFAULT_TIMEOUT,
+        }
+    }
+
+    ///Overrides the default timeout, if one is given.
+    pub fn with_timeout(mut self, timeout: Option<Duration>) -> UdpScan {
+        if let Some(timeout) = timeout {
+            self.timeout = timeout;
+        }
+        self
+    }
+}
+
+impl Scan for UdpScan {
+    type ScanResult = NmapHost;
+
+    fn run(&self) -> Result<Vec<NmapHost>, ScanError> {
+        logger::print_ok("Running Nmap UDP scan...");
+        self.print_command();
+
+        let output = process::execute("nmap", &self.scan_args, &self.output_file, self.timeout)?;
+        logger::print_ok(&format!(
+            "Nmap UDP scan finished in {}s.",
+            output.duration.as_secs()
+        ));
+        let results = self.parse_output();
+        self.print_results(&results);
+        Ok(results)
+    }
+
+    fn parse_output(&self) -> Vec<NmapHost> {
+        nmap::parse_xml_file(&self.output_file).hosts
+    }
+
+    fn print_results(&self, hosts: &[NmapHost]) {
+        nmap::print_hosts(hosts);
+    }
+
+    fn to_findings(&self, hosts: &[NmapHost]) -> Vec<Finding> {
+        nmap::host_findings(hosts, &self.output_file)
+    }
+
+    fn print_command(&self) {
+        logger::print_ok(&format!("Command used: nmap {}", self.scan_args.join(" ")));
+    }
+
+    ///Nmap's installation is checked in main.rs, see `NmapScan`.
+    fn is_installed(&self) -> bool {
+        true
+    }
+}
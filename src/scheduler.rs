@@ -0,0 +1,521 @@
+use std::{
+    collections::{HashMap, HashSet, VecDeque},
+    panic::{self, AssertUnwindSafe},
+    sync::{
+        mpsc::{self, RecvTimeoutError},
+        Arc, Mutex, MutexGuard, PoisonError,
+    },
+    thread,
+    time::{Duration, Instant},
+};
+
+use serde::{Deserialize, Serialize};
+
+use crate::{
+    logger,
+    scans::{
+        cve::CveScanResult, error::ScanError, feroxbuster::FeroxbusterScanResult, finding::Finding,
+        headers::HeaderCheckResult, nmap::NmapHost, nuclei::NucleiScanResult, scan::Scan,
+        smb::SmbScanResult,
+    },
+};
+
+///Wraps the result types of the different scans so they can travel through a single channel.
+#[derive(Serialize, Deserialize)]
+#[serde(tag = "tool", content = "results", rename_all = "lowercase")]
+pub enum ScanOutput {
+    Nuclei(Vec<NucleiScanResult>),
+    Feroxbuster(Vec<FeroxbusterScanResult>),
+    ///Nmap runs with specific NSE scripts, e.g. SNMP enumeration.
+    Nmap(Vec<NmapHost>),
+    Smb(Vec<SmbScanResult>),
+    Headers(Vec<HeaderCheckResult>),
+    Cve(Vec<CveScanResult>),
+}
+
+impl ScanOutput {
+    pub fn len(&self) -> usize {
+        match self {
+            ScanOutput::Nuclei(results) => results.len(),
+            ScanOutput::Feroxbuster(results) => results.len(),
+            ScanOutput::Smb(results) => results.len(),
+            ScanOutput::Headers(results) => results.len(),
+            ScanOutput::Cve(results) => results.len(),
+            //The number of scripts that had something to say.
+            ScanOutput::Nmap(hosts) => hosts
+                .iter()
+                .map(|host| {
+                    host.scripts.len()
+                        + host
+                            .ports
+                            .iter()
+                            .map(|port| port.scripts.len())
+                            .sum::<usize>()
+                })
+                .sum(),
+        }
+    }
+}
+
+///How often jobs waiting for a slot check whether another host freed one.
+const RETRY_INTERVAL: Duration = Duration::from_millis(250);
+
+type Task = Box<dyn FnOnce() -> Result<(ScanOutput, Vec<Finding>), ScanError> + Send>;
+
+///A single scan waiting to be executed by the `Scheduler`.
+pub struct Job {
+    pub tool: &'static str,
+    pub target: String,
+    ///The tool limit the job counts against, see `SchedulerConfig::tool_limits`. Usually the tool itself.
+    limit: &'static str,
+    ///Identifies the scan for loop protection. Two jobs with the same key never both run.
+    key: String,
+    ///Number of follow-up steps between this job and the Nmap results that started it all.
+    depth: usize,
+    task: Task,
+}
+
+impl Job {
+    ///Wraps a scan in a job. `output` wraps the scan's results, e.g. `ScanOutput::Nuclei`.
+    pub fn new<S>(
+        tool: &'static str,
+        target: &str,
+        scan: S,
+        output: fn(Vec<S::ScanResult>) -> ScanOutput,
+    ) -> Job
+    where
+        S: Scan + Send + 'static,
+    {
+        Job {
+            tool,
+            target: target.to_string(),
+            limit: tool,
+            key: format!("{} {}", tool, target),
+            depth: 0,
+            task: Box::new(move || {
+                let results = scan.run()?;
+                let findings = scan.to_findings(&results);
+                Ok((output(results), findings))
+            }),
+        }
+    }
+
+    ///Distinguishes this job from other jobs of the same tool against the same target, e.g. Nuclei with different tags.
+    pub fn with_variant(mut self, variant: &str) -> Job {
+        self.key = format!("{} {} {}", self.tool, self.target, variant);
+        self
+    }
+
+    ///Counts the job against another tool's limit, e.g. 'nmap' for scans that run Nmap under a scanner name of their own.
+    pub fn with_limit(mut self, limit: &'static str) -> Job {
+        self.limit = limit;
+        self
+    }
+}
+
+///Outcome of a `Job`, handed back to the engine once the job finishes.
+pub struct JobResult {
+    pub tool: &'static str,
+    pub target: String,
+    pub depth: usize,
+    pub duration: Duration,
+    pub result: Result<ScanOutput, ScanError>,
+    pub findings: Vec<Finding>,
+}
+
+///Clones share the running scans, so the limits hold across all hosts that are scanned at the same time.
+#[derive(Clone)]
+pub struct SchedulerConfig {
+    ///Maximum number of scans running at the same time, regardless of the tool.
+    pub max_concurrent: usize,
+    ///Maximum number of scans running at the same time per tool, see `Job::with_limit`. Tools that are not listed are only bound by `max_concurrent`.
+    pub tool_limits: HashMap<String, usize>,
+    ///Maximum number of follow-up steps. Follow-up jobs beyond this depth are dropped.
+    pub max_chain_depth: usize,
+    running: Arc<Mutex<Running>>,
+}
+
+///Scans running right now, in total and per tool limit.
+#[derive(Default)]
+struct Running {
+    total: usize,
+    per_limit: HashMap<&'static str, usize>,
+}
+
+impl Running {
+    fn release(&mut self, limit: &str) {
+        self.total -= 1;
+        if let Some(running) = self.per_limit.get_mut(limit) {
+            *running -= 1;
+        }
+    }
+}
+
+///Jobs catch their own panics, so a poisoned lock still holds correct counts.
+fn lock(running: &Mutex<Running>) -> MutexGuard<'_, Running> {
+    running.lock().unwrap_or_else(PoisonError::into_inner)
+}
+
+impl Default for SchedulerConfig {
+    fn default() -> SchedulerConfig {
+        SchedulerConfig {
+            max_concurrent: 4,
+            tool_limits: HashMap::from([
+                (String::from("nmap"), 1),
+                (String::from("nuclei"), 4),
+                (String::from("feroxbuster"), 2),
+            ]),
+            max_chain_depth: 2,
+            running: Arc::default(),
+        }
+    }
+}
+
+pub struct Scheduler {
+    config: SchedulerConfig,
+}
+
+impl Scheduler {
+    pub fn new(config: SchedulerConfig) -> Scheduler {
+        Scheduler { config }
+    }
+
+    ///Runs all jobs in parallel within the configured limits and blocks until every job has finished.
+    ///Jobs are started in the order they were queued, skipping over jobs whose tool is at its limit.
+    ///Every finished job is handed to `follow_ups`, which can queue more jobs based on its results.
+    pub fn run(
+        &self,
+        jobs: Vec<Job>,
+        mut follow_ups: impl FnMut(&JobResult) -> Vec<Job>,
+    ) -> Vec<JobResult> {
+        let mut seen = HashSet::new();
+        let mut pending = VecDeque::new();
+        for job in jobs {
+            if seen.insert(job.key.clone()) {
+                pending.push_back(job);
+            }
+        }
+        //Only the jobs of this scheduler, the shared counts include other hosts' jobs too.
+        let mut running_here = 0;
+        let mut results = Vec::new();
+        let (sender, receiver) = mpsc::channel();
+
+        loop {
+            let mut i = 0;
+            while i < pending.len() {
+                if !self.try_start(pending[i].limit) {
+                    i += 1;
+                    continue;
+                }
+
+                //Unwrap is safe because i < pending.len().
+                let job = pending.remove(i).unwrap();
+                running_here += 1;
+                let sender = sender.clone();
+                let running = Arc::clone(&self.config.running);
+                thread::spawn(move || {
+                    let started = Instant::now();
+                    //A panicking scan must still report back, otherwise the scheduler waits forever.
+                    let (result, findings) = match panic::catch_unwind(AssertUnwindSafe(job.task))
+                        .unwrap_or_else(|_| Err(ScanError::Runtime(job.tool.to_string())))
+                    {
+                        Ok((output, findings)) => (Ok(output), findings),
+                        Err(err) => (Err(err), Vec::new()),
+                    };
+                    //Released right away rather than by the scheduler, other hosts may be waiting for the slot.
+                    lock(&running).release(job.limit);
+                    //The receiver only goes away when the scheduler is gone, nothing to do in that case.
+                    let _ = sender.send(JobResult {
+                        tool: job.tool,
+                        target: job.target,
+                        depth: job.depth,
+                        duration: started.elapsed(),
+                        result,
+                        findings,
+                    });
+                });
+            }
+
+            if running_here == 0 && pending.is_empty() {
+                break;
+            }
+
+            //Every job reports back, even when it panics. Slots freed by other hosts don't, so waiting jobs are retried regularly.
+            //The scheduler holds a sender itself, so the channel never disconnects.
+            let job_result = match receiver.recv_timeout(RETRY_INTERVAL) {
+                Ok(job_result) => job_result,
+                Err(RecvTimeoutError::Timeout) => continue,
+                Err(RecvTimeoutError::Disconnected) => break,
+            };
+            running_here -= 1;
+
+            for mut job in follow_ups(&job_result) {
+                job.depth = job_result.depth + 1;
+                //The same scan can be requested by several results, or by a result of itself. Only run it once.
+                if !seen.insert(job.key.clone()) {
+                    continue;
+                }
+                if job.depth > self.config.max_chain_depth {
+                    logger::print_warn(&format!(
+                        "Not chaining {} against {}: maximum chain depth of {} reached.",
+                        job.tool, job.target, self.config.max_chain_depth
+                    ));
+                    continue;
+                }
+                logger::print_ok(&format!(
+                    "Chaining {} against {} (triggered by {} against {}).",
+                    job.tool, job.target, job_result.tool, job_result.target
+                ));
+                pending.push_back(job);
+            }
+            results.push(job_result);
+        }
+
+        results
+    }
+
+    ///Takes a slot for a job that counts against `limit`, if both the overall and the tool limit allow it.
+    fn try_start(&self, limit: &'static str) -> bool {
+        let mut running = lock(&self.config.running);
+        let running_limit = running.per_limit.get(limit).copied().unwrap_or(0);
+        if running.total >= self.config.max_concurrent.max(1)
+            || running_limit >= self.tool_limit(limit)
+        {
+            return false;
+        }
+        running.total += 1;
+        *running.per_limit.entry(limit).or_insert(0) += 1;
+        true
+    }
+
+    fn tool_limit(&self, tool: &str) -> usize {
+        self.config
+            .tool_limits
+            .get(tool)
+            .copied()
+            .unwrap_or(usize::MAX)
+            .max(1)
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use std::sync::atomic::{AtomicUsize, Ordering};
+
+    use crate::scans::finding::Severity;
+
+    ///Counts the scans running at the same time and the most there ever were.
+    #[derive(Default)]
+    struct Gauge {
+        running: AtomicUsize,
+        peak: AtomicUsize,
+    }
+
+    enum Outcome {
+        Finding,
+        Error,
+        Panic,
+    }
+
+    struct TestScan {
+        gauges: Vec<Arc<Gauge>>,
+        outcome: Outcome,
+    }
+
+    impl Scan for TestScan {
+        type ScanResult = NucleiScanResult;
+
+        fn run(&self) -> Result<Vec<NucleiScanResult>, ScanError> {
+            for gauge in &self.gauges {
+                let running = gauge.running.fetch_add(1, Ordering::SeqCst) + 1;
+                gauge.peak.fetch_max(running, Ordering::SeqCst);
+            }
+            thread::sleep(Duration::from_millis(30));
+            for gauge in &self.gauges {
+                gauge.running.fetch_sub(1, Ordering::SeqCst);
+            }
+            match self.outcome {
+                Outcome::Finding => Ok(Vec::new()),
+                Outcome::Error => Err(ScanError::NonZeroExit {
+                    scan: String::from("test"),
+                    status: Some(2),
+                    stderr_tail: String::new(),
+                }),
+                Outcome::Panic => panic!("test scan panicked"),
+            }
+        }
+
+        fn parse_output(&self) -> Vec<NucleiScanResult> {
+            Vec::new()
+        }
+
+        fn print_results(&self, _: &[NucleiScanResult]) {}
+
+        fn to_findings(&self, _: &[NucleiScanResult]) -> Vec<Finding> {
+            vec![Finding::new(
+                String::from("test:finding"),
+                String::from("Test finding"),
+                Severity::Low,
+                String::from("10.0.0.1"),
+                "test",
+            )]
+        }
+
+        fn print_command(&self) {}
+
+        fn is_installed(&self) -> bool {
+            true
+        }
+    }
+
+    fn job(tool: &'static str, target: &str, gauges: &[&Arc<Gauge>]) -> Job {
+        job_with_outcome(tool, target, gauges, Outcome::Finding)
+    }
+
+    fn job_with_outcome(
+        tool: &'static str,
+        target: &str,
+        gauges: &[&Arc<Gauge>],
+        outcome: Outcome,
+    ) -> Job {
+        let scan = TestScan {
+            gauges: gauges.iter().map(|gauge| Arc::clone(gauge)).collect(),
+            outcome,
+        };
+        Job::new(tool, target, scan, ScanOutput::Nuclei)
+    }
+
+    fn config(max_concurrent: usize, tool_limits: &[(&str, usize)]) -> SchedulerConfig {
+        SchedulerConfig {
+            max_concurrent,
+            tool_limits: tool_limits
+                .iter()
+                .map(|(tool, limit)| (tool.to_string(), *limit))
+                .collect(),
+            ..SchedulerConfig::default()
+        }
+    }
+
+    fn no_follow_ups(_: &JobResult) -> Vec<Job> {
+        Vec::new()
+    }
+
+    #[test]
+    fn global_limit() {
+        let all = Arc::new(Gauge::default());
+        let jobs = (0..6)
+            .map(|i| job("nuclei", &format!("10.0.0.{}", i), &[&all]))
+            .collect();
+        let results = Scheduler::new(config(2, &[])).run(jobs, no_follow_ups);
+        assert_eq!(results.len(), 6);
+        assert_eq!(all.peak.load(Ordering::SeqCst), 2);
+    }
+
+    #[test]
+    fn tool_limit_includes_jobs_counted_against_it() {
+        let all = Arc::new(Gauge::default());
+        let nmap = Arc::new(Gauge::default());
+        let mut jobs = Vec::new();
+        for i in 0..3 {
+            let target = format!("10.0.0.{}", i);
+            jobs.push(job("nmap", &target, &[&all, &nmap]));
+            jobs.push(job("smb", &target, &[&all, &nmap]).with_limit("nmap"));
+            jobs.push(job("nuclei", &target, &[&all]));
+        }
+        let results = Scheduler::new(config(8, &[("nmap", 1)])).run(jobs, no_follow_ups);
+        assert_eq!(results.len(), 9);
+        assert_eq!(nmap.peak.load(Ordering::SeqCst), 1);
+        //The Nuclei scans aren't held up by the Nmap limit.
+        assert!(all.peak.load(Ordering::SeqCst) > 1);
+    }
+
+    #[test]
+    fn limits_are_shared_between_schedulers() {
+        let all = Arc::new(Gauge::default());
+        let nmap = Arc::new(Gauge::default());
+        let config = config(3, &[("nmap", 1)]);
+        thread::scope(|scope| {
+            for host in 0..3 {
+                let config = config.clone();
+                let (all, nmap) = (&all, &nmap);
+                scope.spawn(move || {
+                    let jobs = (0..3)
+                        .map(|i| {
+                            let target = format!("10.0.{}.{}", host, i);
+                            if i == 0 {
+                                job("nmap", &target, &[all, nmap])
+                            } else {
+                                job("nuclei", &target, &[all])
+                            }
+                        })
+                        .collect();
+                    Scheduler::new(config).run(jobs, no_follow_ups)
+                });
+            }
+        });
+        assert_eq!(nmap.peak.load(Ordering::SeqCst), 1);
+        assert_eq!(all.peak.load(Ordering::SeqCst), 3);
+    }
+
+    #[test]
+    fn jobs_with_the_same_key_run_once() {
+        let all = Arc::new(Gauge::default());
+        let jobs = vec![
+            job("nuclei", "http://10.0.0.1", &[&all]),
+            job("nuclei", "http://10.0.0.1", &[&all]),
+            job("nuclei", "http://10.0.0.1", &[&all]).with_variant("wordpress"),
+            job("feroxbuster", "http://10.0.0.1", &[&all]),
+        ];
+        let results = Scheduler::new(SchedulerConfig::default()).run(jobs, no_follow_ups);
+        assert_eq!(results.len(), 3);
+    }
+
+    #[test]
+    fn follow_ups_stop_at_the_chain_depth() {
+        let all = Arc::new(Gauge::default());
+        let mut config = config(4, &[]);
+        config.max_chain_depth = 2;
+        let results = Scheduler::new(config).run(vec![job("nuclei", "0", &[&all])], |result| {
+            //The same scan again is never chained, the next one only up to the maximum depth.
+            let next = (result.target.parse::<usize>().unwrap() + 1).to_string();
+            vec![
+                job("nuclei", &result.target, &[&all]),
+                job("nuclei", &next, &[&all]),
+            ]
+        });
+        let depths: Vec<(&str, usize)> = results
+            .iter()
+            .map(|result| (result.target.as_str(), result.depth))
+            .collect();
+        assert_eq!(depths, [("0", 0), ("1", 1), ("2", 2)]);
+    }
+
+    #[test]
+    fn errors_and_panics_are_reported() {
+        let all = Arc::new(Gauge::default());
+        let jobs = vec![
+            job_with_outcome("nuclei", "error", &[&all], Outcome::Error),
+            job_with_outcome("feroxbuster", "panic", &[&all], Outcome::Panic),
+            job("nmap", "ok", &[&all]),
+        ];
+        let mut results = Scheduler::new(config(1, &[])).run(jobs, no_follow_ups);
+        results.sort_by_key(|result| result.target.clone());
+
+        //With a single slot, the last job only runs if the panicking one gave its slot back.
+        assert_eq!(results.len(), 3);
+        assert!(matches!(
+            results[0].result,
+            Err(ScanError::NonZeroExit {
+                status: Some(2),
+                ..
+            })
+        ));
+        assert!(results[0].findings.is_empty());
+        assert!(results[1].result.is_ok());
+        assert_eq!(results[1].findings.len(), 1);
+        assert!(
+            matches!(&results[2].result, Err(ScanError::Runtime(tool)) if tool == "feroxbuster")
+        );
+    }
+}
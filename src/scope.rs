@@ -0,0 +1,442 @@
+use serde::{Deserialize, Serialize};
+use std::{fmt, fs, net::IpAddr, path::Path};
+
+use crate::targets::host_of;
+
+///The engagement's scope, loaded from the file given with --scope. Without a scope file everything is allowed.
+#[derive(Default)]
+pub struct Scope {
+    ///When empty, everything that isn't denied is allowed.
+    allow: Vec<Entry>,
+    ///Wins over `allow`.
+    deny: Vec<Entry>,
+}
+
+#[derive(Deserialize)]
+#[serde(deny_unknown_fields)]
+struct ScopeFile {
+    #[serde(default)]
+    allow: Vec<String>,
+    #[serde(default)]
+    deny: Vec<String>,
+}
+
+///A single line of the scope, e.g. '10.0.0.0/24', 'www.example.com' or '*.example.com'.
+#[derive(Debug, Clone)]
+enum Entry {
+    ///A single IP address is a network with the maximum prefix length.
+    Network(IpAddr, u8),
+    Hostname(String),
+    ///Matches every subdomain of the domain, not the domain itself.
+    Wildcard(String),
+}
+
+///A scan that was not run because its target is out of scope.
+#[derive(Serialize, Deserialize, Debug, Clone)]
+pub struct Refusal {
+    pub tool: String,
+    pub target: String,
+    pub reason: String,
+}
+
+impl Scope {
+    pub fn from_file(path: &Path) -> Result<Scope, String> {
+        let contents = fs::read_to_string(path)
+            .map_err(|err| format!("Unable to read scope file {}: {}", path.display(), err))?;
+        let file: ScopeFile = toml::from_str(&contents)
+            .map_err(|err| format!("Unable to parse scope file {}: {}", path.display(), err))?;
+        let parse = |specs: &[String]| {
+            specs
+                .iter()
+                .map(|spec| {
+                    Entry::parse(spec).map_err(|reason| {
+                        format!(
+                            "Invalid entry '{}' in scope file {}: {}",
+                            spec,
+                            path.display(),
+                            reason
+                        )
+                    })
+                })
+                .collect::<Result<Vec<Entry>, String>>()
+        };
+        Ok(Scope {
+            allow: parse(&file.allow)?,
+            deny: parse(&file.deny)?,
+        })
+    }
+
+    ///Checks a host, IP address or URL. The error is the reason it's out of scope.
+    pub fn check(&self, target: &str) -> Result<(), String> {
+        let host = Host::parse(&host_of(target))?;
+        if let Some(entry) = self.deny.iter().find(|entry| entry.matches(&host)) {
+            return Err(format!("denied by '{}'", entry));
+        }
+        if self.allow.is_empty() || self.allow.iter().any(|entry| entry.matches(&host)) {
+            return Ok(());
+        }
+        //Chained scans mostly find hosts next to the target, e.g. through certificate names or redirects.
+        //Whether such a host belongs to the same organization tells whether the scope may simply be missing it.
+        match self.related_entry(&host) {
+            Some((entry, domain)) => Err(format!(
+                "not in the allowed scope, though it shares the registrable domain '{}' with '{}'",
+                domain, entry
+            )),
+            None => Err(String::from("not in the allowed scope")),
+        }
+    }
+
+    ///The first allowed hostname or wildcard under the same registrable domain as the host, and that domain.
+    ///A related host isn't in scope, it only hints that it may belong there.
+    fn related_entry(&self, host: &Host) -> Option<(&Entry, String)> {
+        let Host::Domain(domain) = host else {
+            return None;
+        };
+        let domain = registrable_domain(domain)?;
+        self.allow
+            .iter()
+            .find(|entry| match entry {
+                Entry::Hostname(name) | Entry::Wildcard(name) => {
+                    registrable_domain(name).as_ref() == Some(&domain)
+                }
+                Entry::Network(..) => false,
+            })
+            .map(|entry| (entry, domain))
+    }
+
+    ///Checks a CIDR range before it is swept. The whole range has to be allowed.
+    ///Returns the denied parts of the range, which have to be excluded from the sweep.
+    pub fn check_range(&self, range: &str) -> Result<Vec<String>, String> {
+        let Entry::Network(addr, prefix) = Entry::parse(range)? else {
+            return Err(String::from("not a CIDR range"));
+        };
+        let mut excludes = Vec::new();
+        for entry in &self.deny {
+            if let Entry::Network(deny_addr, deny_prefix) = entry {
+                if network_contains(*deny_addr, *deny_prefix, addr, prefix) {
+                    return Err(format!("denied by '{}'", entry));
+                }
+                if network_contains(addr, prefix, *deny_addr, *deny_prefix) {
+                    excludes.push(entry.to_string());
+                }
+            }
+        }
+        let allowed = self.allow.is_empty()
+            || self.allow.iter().any(|entry| match entry {
+                Entry::Network(allow_addr, allow_prefix) => {
+                    network_contains(*allow_addr, *allow_prefix, addr, prefix)
+                }
+                _ => false,
+            });
+        if !allowed {
+            return Err(String::from("not completely in the allowed scope"));
+        }
+        Ok(excludes)
+    }
+}
+
+///A target, after taking it apart.
+enum Host {
+    Ip(IpAddr),
+    ///Lowercase and without the trailing dot.
+    Domain(String),
+}
+
+impl Host {
+    fn parse(host: &str) -> Result<Host, String> {
+        match host.parse() {
+            Ok(addr) => Ok(Host::Ip(addr)),
+            Err(_) => Ok(Host::Domain(parse_domain(host)?)),
+        }
+    }
+}
+
+impl Entry {
+    fn parse(spec: &str) -> Result<Entry, String> {
+        if let Some(domain) = spec.strip_prefix("*.") {
+            let domain = parse_domain(domain)?;
+            //'*.com' or '*.co.uk' would put every domain under the suffix in scope.
+            if registrable_domain(&domain).is_none() {
+                return Err(format!(
+                    "'{}' is a public suffix, the wildcard has to be on a registrable domain",
+                    domain
+                ));
+            }
+            return Ok(Entry::Wildcard(domain));
+        }
+
+        let (addr, prefix) = match spec.split_once('/') {
+            Some((addr, prefix)) => (addr, Some(prefix)),
+            None => (spec, None),
+        };
+        match addr.parse::<IpAddr>() {
+            Ok(addr) => {
+                let max_prefix = max_prefix(&addr);
+                let prefix = match prefix {
+                    Some(prefix) => prefix
+                        .parse()
+                        .ok()
+                        .filter(|prefix| *prefix <= max_prefix)
+                        .ok_or_else(|| {
+                            format!("the prefix length must be between 0 and {}", max_prefix)
+                        })?,
+                    None => max_prefix,
+                };
+                Ok(Entry::Network(addr, prefix))
+            }
+            Err(_) if prefix.is_some() => Err(format!("'{}' is not an IP address", addr)),
+            Err(_) => Ok(Entry::Hostname(parse_domain(spec)?)),
+        }
+    }
+
+    ///IP addresses only match networks and domains only match hostnames, nothing is resolved.
+    fn matches(&self, host: &Host) -> bool {
+        match (self, host) {
+            (Entry::Network(network, prefix), Host::Ip(addr)) => {
+                network_contains(*network, *prefix, *addr, max_prefix(addr))
+            }
+            (Entry::Hostname(hostname), Host::Domain(domain)) => hostname == domain,
+            (Entry::Wildcard(parent), Host::Domain(domain)) => {
+                domain.ends_with(&format!(".{}", parent))
+            }
+            _ => false,
+        }
+    }
+}
+
+impl fmt::Display for Entry {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        match self {
+            Entry::Network(addr, prefix) if *prefix == max_prefix(addr) => write!(f, "{}", addr),
+            Entry::Network(addr, prefix) => write!(f, "{}/{}", addr, prefix),
+            Entry::Hostname(hostname) => write!(f, "{}", hostname),
+            Entry::Wildcard(parent) => write!(f, "*.{}", parent),
+        }
+    }
+}
+
+///Lowercases the domain and drops the trailing dot, so names compare equal however they were written.
+fn parse_domain(domain: &str) -> Result<String, String> {
+    let domain = domain.trim_end_matches('.').to_lowercase();
+    match addr::parse_domain_name(&domain) {
+        Ok(_) => Ok(domain),
+        Err(_) => Err(format!("'{}' is not a valid domain name", domain)),
+    }
+}
+
+///The domain right below the public suffix, e.g. 'example.co.uk' for 'www.example.co.uk'. `None` for public suffixes.
+fn registrable_domain(domain: &str) -> Option<String> {
+    addr::parse_domain_name(domain)
+        .ok()?
+        .root()
+        .map(String::from)
+}
+
+fn max_prefix(addr: &IpAddr) -> u8 {
+    if addr.is_ipv4() {
+        32
+    } else {
+        128
+    }
+}
+
+///Whether the network `inner_addr/inner_prefix` lies completely within `addr/prefix`.
+fn network_contains(addr: IpAddr, prefix: u8, inner_addr: IpAddr, inner_prefix: u8) -> bool {
+    if inner_prefix < prefix {
+        return false;
+    }
+    let (bits, inner_bits) = match (addr, inner_addr) {
+        (IpAddr::V4(addr), IpAddr::V4(inner_addr)) => {
+            //Shifted to the top so both families can be masked the same way.
+            (
+                (u32::from(addr) as u128) << 96,
+                (u32::from(inner_addr) as u128) << 96,
+            )
+        }
+        (IpAddr::V6(addr), IpAddr::V6(inner_addr)) => (u128::from(addr), u128::from(inner_addr)),
+        _ => return false,
+    };
+    let mask = u128::MAX.checked_shl(128 - prefix as u32).unwrap_or(0);
+    bits & mask == inner_bits & mask
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn scope(allow: &[&str], deny: &[&str]) -> Scope {
+        let parse = |specs: &[&str]| {
+            specs
+                .iter()
+                .map(|spec| Entry::parse(spec).unwrap())
+                .collect()
+        };
+        Scope {
+            allow: parse(allow),
+            deny: parse(deny),
+        }
+    }
+
+    fn network(spec: &str) -> (IpAddr, u8) {
+        match Entry::parse(spec).unwrap() {
+            Entry::Network(addr, prefix) => (addr, prefix),
+            entry => panic!("'{}' is not a network", entry),
+        }
+    }
+
+    fn contains(outer: &str, inner: &str) -> bool {
+        let (addr, prefix) = network(outer);
+        let (inner_addr, inner_prefix) = network(inner);
+        network_contains(addr, prefix, inner_addr, inner_prefix)
+    }
+
+    #[test]
+    fn ipv4_prefix_containment() {
+        assert!(contains("10.0.0.0/8", "10.1.2.0/24"));
+        assert!(contains("10.0.0.0/24", "10.0.0.0/24"));
+        assert!(contains("10.0.0.0/24", "10.0.0.255"));
+        assert!(!contains("10.0.0.0/24", "10.0.1.0"));
+        assert!(!contains("10.0.0.0/24", "10.0.0.0/16"));
+        assert!(contains("0.0.0.0/0", "192.168.1.1"));
+        assert!(contains("10.0.0.5", "10.0.0.5"));
+        assert!(!contains("10.0.0.5", "10.0.0.6"));
+    }
+
+    #[test]
+    fn ipv6_prefix_containment() {
+        assert!(contains("2001:db8::/32", "2001:db8:1::/48"));
+        assert!(contains("2001:db8::/32", "2001:db8:ffff::1"));
+        assert!(!contains("2001:db8::/32", "2001:db9::1"));
+        assert!(!contains("2001:db8::/48", "2001:db8::/32"));
+        assert!(contains("::/0", "fe80::1"));
+    }
+
+    #[test]
+    fn families_never_contain_each_other() {
+        assert!(!contains("0.0.0.0/0", "::1"));
+        assert!(!contains("::/0", "10.0.0.1"));
+    }
+
+    #[test]
+    fn check_range_excludes_denied_parts() {
+        let scope = scope(
+            &["10.0.0.0/16"],
+            &["10.0.5.0/24", "10.0.9.9", "192.168.0.0/16"],
+        );
+        assert_eq!(
+            scope.check_range("10.0.0.0/16"),
+            Ok(vec![String::from("10.0.5.0/24"), String::from("10.0.9.9")])
+        );
+        assert_eq!(scope.check_range("10.0.1.0/24"), Ok(Vec::new()));
+    }
+
+    #[test]
+    fn check_range_refuses_denied_and_partially_allowed_ranges() {
+        let scope = scope(&["10.0.0.0/16"], &["10.0.5.0/24"]);
+        assert_eq!(
+            scope.check_range("10.0.5.128/25"),
+            Err(String::from("denied by '10.0.5.0/24'"))
+        );
+        assert_eq!(
+            scope.check_range("10.0.0.0/8"),
+            Err(String::from("not completely in the allowed scope"))
+        );
+        assert!(scope.check_range("www.example.com").is_err());
+    }
+
+    #[test]
+    fn check_range_without_allow_entries() {
+        let scope = scope(&[], &["10.0.0.1"]);
+        assert_eq!(
+            scope.check_range("10.0.0.0/24"),
+            Ok(vec![String::from("10.0.0.1")])
+        );
+    }
+
+    #[test]
+    fn check_denies_inside_allowed_network() {
+        let scope = scope(&["10.0.0.0/24", "2001:db8::/32"], &["10.0.0.7"]);
+        assert_eq!(scope.check("10.0.0.5"), Ok(()));
+        assert_eq!(scope.check("http://10.0.0.5:8080/"), Ok(()));
+        assert_eq!(scope.check("http://[2001:db8::1]/"), Ok(()));
+        assert_eq!(
+            scope.check("10.0.0.7"),
+            Err(String::from("denied by '10.0.0.7'"))
+        );
+        assert_eq!(
+            scope.check("10.0.1.1"),
+            Err(String::from("not in the allowed scope"))
+        );
+    }
+
+    #[test]
+    fn wildcard_matches_subdomains_only() {
+        let scope = scope(&["*.example.com"], &["admin.example.com"]);
+        assert_eq!(scope.check("www.example.com"), Ok(()));
+        assert_eq!(scope.check("https://a.b.EXAMPLE.com./login"), Ok(()));
+        assert!(scope.check("example.com").is_err());
+        assert!(scope.check("notexample.com").is_err());
+        assert_eq!(
+            scope.check("admin.example.com"),
+            Err(String::from("denied by 'admin.example.com'"))
+        );
+    }
+
+    #[test]
+    fn registrable_domain_of_hosts() {
+        assert_eq!(
+            registrable_domain("www.example.com"),
+            Some(String::from("example.com"))
+        );
+        assert_eq!(
+            registrable_domain("a.b.example.co.uk"),
+            Some(String::from("example.co.uk"))
+        );
+        assert_eq!(
+            registrable_domain("example.com"),
+            Some(String::from("example.com"))
+        );
+        assert_eq!(registrable_domain("co.uk"), None);
+    }
+
+    #[test]
+    fn related_hosts_are_flagged_but_not_allowed() {
+        let domain = scope(&["example.com", "10.0.0.0/24"], &[]);
+        assert_eq!(
+            domain.check("https://foo.example.com/"),
+            Err(String::from(
+                "not in the allowed scope, though it shares the registrable domain 'example.com' with 'example.com'"
+            ))
+        );
+        assert_eq!(
+            domain.check("example.org"),
+            Err(String::from("not in the allowed scope"))
+        );
+        //'example.co.uk' and 'other.co.uk' only share the public suffix.
+        let wildcard = scope(&["*.example.co.uk"], &[]);
+        assert_eq!(
+            wildcard.check("example.co.uk"),
+            Err(String::from(
+                "not in the allowed scope, though it shares the registrable domain 'example.co.uk' with '*.example.co.uk'"
+            ))
+        );
+        assert_eq!(
+            wildcard.check("www.other.co.uk"),
+            Err(String::from("not in the allowed scope"))
+        );
+    }
+
+    #[test]
+    fn wildcard_on_public_suffix_is_rejected() {
+        assert!(Entry::parse("*.com").is_err());
+        assert!(Entry::parse("*.co.uk").is_err());
+        assert!(Entry::parse("*.example.co.uk").is_ok());
+    }
+
+    #[test]
+    fn invalid_prefix_is_rejected() {
+        assert!(Entry::parse("10.0.0.0/33").is_err());
+        assert!(Entry::parse("2001:db8::/129").is_err());
+        assert!(Entry::parse("example.com/24").is_err());
+    }
+}
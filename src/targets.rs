@@ -0,0 +1,349 @@
+use std::{
+    fs, io,
+    net::{IpAddr, Ipv6Addr},
+    path::{Path, PathBuf},
+};
+use url::{Host, Url};
+
+///A single entry of the scope.
+#[derive(Debug, Clone, PartialEq, Eq, Hash)]
+pub enum Target {
+    ///An IP address or hostname that goes through the full pipeline.
+    Host(String),
+    ///A single port of a host, e.g. 'example.com:8080'. Nmap only scans that port.
+    Service { host: String, port: u16 },
+    ///A web application, e.g. 'https://example.com:8443/portal'. Nmap is skipped and the web scans run against exactly this URL.
+    Url(Url),
+    ///A CIDR range. Only its live hosts are scanned, after a host discovery pass.
+    Range(String),
+}
+
+impl Target {
+    ///Parses an IP address, hostname, host:port pair, http(s) URL or CIDR range, e.g. '10.0.0.0/24'.
+    pub fn parse(spec: &str) -> Result<Target, String> {
+        if spec.contains("://") {
+            return parse_url(spec);
+        }
+        if let Some((addr, prefix)) = spec.split_once('/') {
+            return parse_range(addr, prefix);
+        }
+        //IPv6 addresses are full of colons, so they have to be recognized before looking for a port.
+        if let Ok(addr) = spec.parse::<IpAddr>() {
+            return Ok(Target::Host(addr.to_string()));
+        }
+        if let Some(bracketed) = spec.strip_prefix('[') {
+            return parse_bracketed(bracketed);
+        }
+        if let Some((host, port)) = spec.rsplit_once(':') {
+            return Ok(Target::Service {
+                host: parse_host(host)?,
+                port: parse_port(port)?,
+            });
+        }
+        Ok(Target::Host(parse_host(spec)?))
+    }
+
+    ///The target as it's shown to the user.
+    pub fn name(&self) -> String {
+        match self {
+            Target::Host(name) | Target::Range(name) => name.clone(),
+            Target::Service { host, port } => format!("{}:{}", host, port),
+            Target::Url(url) => url.to_string(),
+        }
+    }
+
+    ///The host that is scanned. For ranges this is the range itself.
+    pub fn host(&self) -> String {
+        match self {
+            Target::Host(host) | Target::Service { host, .. } | Target::Range(host) => host.clone(),
+            //Without the brackets url puts around IPv6 addresses. parse_url() made sure there is a host.
+            Target::Url(url) => match url.host() {
+                Some(Host::Ipv6(addr)) => addr.to_string(),
+                Some(host) => host.to_string(),
+                None => String::new(),
+            },
+        }
+    }
+
+    ///Name of the target's directory under the output directory. Runs against a single port or URL of a host end up with the host.
+    ///The '/' of ranges would nest directories and the ':' of IPv6 addresses isn't allowed everywhere, so both are replaced.
+    pub fn dir_name(&self) -> String {
+        self.host()
+            .chars()
+            .map(|c| {
+                if c.is_ascii_alphanumeric() || c == '.' || c == '-' {
+                    c
+                } else {
+                    '_'
+                }
+            })
+            .collect()
+    }
+}
+
+///Formats a host for a URL or a host:port pair, which need IPv6 addresses in brackets.
+pub fn host_in_url(host: &str) -> String {
+    match host.parse() {
+        Ok(IpAddr::V6(addr)) => Host::<&str>::Ipv6(addr).to_string(),
+        _ => host.to_string(),
+    }
+}
+
+///The host part of a scan target. Targets can be URLs or bare hosts. IPv6 addresses are returned without brackets.
+pub fn host_of(target: &str) -> String {
+    match Url::parse(target).as_ref().map(Url::host) {
+        Ok(Some(Host::Ipv6(addr))) => addr.to_string(),
+        Ok(Some(host)) => host.to_string(),
+        _ => target
+            .trim_start_matches('[')
+            .trim_end_matches(']')
+            .to_string(),
+    }
+}
+
+fn parse_host(host: &str) -> Result<String, String> {
+    match Host::parse(host) {
+        Ok(host) => Ok(host.to_string()),
+        Err(err) => Err(err.to_string()),
+    }
+}
+
+fn parse_port(port: &str) -> Result<u16, String> {
+    port.parse()
+        .ok()
+        .filter(|port| *port != 0)
+        .ok_or_else(|| format!("'{}' is not a valid port", port))
+}
+
+///Parses '[2001:db8::1]' or '[2001:db8::1]:8080', without the opening bracket.
+fn parse_bracketed(spec: &str) -> Result<Target, String> {
+    let Some((addr, rest)) = spec.split_once(']') else {
+        return Err(String::from("the closing bracket is missing"));
+    };
+    let addr: Ipv6Addr = addr
+        .parse()
+        .map_err(|_| format!("'{}' is not an IPv6 address", addr))?;
+    match rest.strip_prefix(':') {
+        Some(port) => Ok(Target::Service {
+            host: addr.to_string(),
+            port: parse_port(port)?,
+        }),
+        None if rest.is_empty() => Ok(Target::Host(addr.to_string())),
+        None => Err(format!("unexpected '{}' after the address", rest)),
+    }
+}
+
+fn parse_url(spec: &str) -> Result<Target, String> {
+    let mut url = Url::parse(spec).map_err(|err| err.to_string())?;
+    if url.scheme() != "http" && url.scheme() != "https" {
+        return Err(String::from("only http:// and https:// URLs are supported"));
+    }
+    if url.host_str().is_none() {
+        return Err(String::from("the URL has no host"));
+    }
+    //The scans start at the path, a query or fragment means nothing to them.
+    url.set_query(None);
+    url.set_fragment(None);
+    Ok(Target::Url(url))
+}
+
+fn parse_range(addr: &str, prefix: &str) -> Result<Target, String> {
+    let addr: IpAddr = addr
+        .parse()
+        .map_err(|_| format!("'{}' is not an IP address", addr))?;
+    let max_prefix = if addr.is_ipv4() { 32 } else { 128 };
+    let prefix: u8 = prefix
+        .parse()
+        .ok()
+        .filter(|prefix| *prefix <= max_prefix)
+        .ok_or_else(|| format!("the prefix length must be between 0 and {}", max_prefix))?;
+    if prefix == max_prefix {
+        return Ok(Target::Host(addr.to_string()));
+    }
+    Ok(Target::Range(format!("{}/{}", addr, prefix)))
+}
+
+///Reads a list of targets in Nmap's -iL format: separated by whitespace or newlines, '#' starts a comment.
+pub fn read_target_file(path: &Path) -> io::Result<Vec<String>> {
+    let contents = fs::read_to_string(path)?;
+    Ok(contents
+        .lines()
+        .map(|line| line.split('#').next().unwrap_or_default())
+        .flat_map(str::split_whitespace)
+        .map(String::from)
+        .collect())
+}
+
+///Parses the targets from the command line and the target files, in that order and without duplicates.
+///Fails on the first target that can't be parsed, so nothing is scanned when the scope has a typo.
+pub fn collect(specs: &[String], target_files: &[PathBuf]) -> Result<Vec<Target>, String> {
+    let mut all_specs = specs.to_vec();
+    for path in target_files {
+        let specs = read_target_file(path)
+            .map_err(|err| format!("Unable to read target file {}: {}", path.display(), err))?;
+        all_specs.extend(specs);
+    }
+
+    let mut targets = Vec::new();
+    for spec in &all_specs {
+        let target = Target::parse(spec)
+            .map_err(|reason| format!("Unable to parse target '{}': {}", spec, reason))?;
+        if !targets.contains(&target) {
+            targets.push(target);
+        }
+    }
+    Ok(targets)
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn host(host: &str) -> Target {
+        Target::Host(host.to_string())
+    }
+
+    fn service(host: &str, port: u16) -> Target {
+        Target::Service {
+            host: host.to_string(),
+            port,
+        }
+    }
+
+    fn url(url: &str) -> Target {
+        Target::Url(Url::parse(url).unwrap())
+    }
+
+    #[test]
+    fn parses_targets() {
+        let cases = [
+            ("10.0.0.5", host("10.0.0.5")),
+            ("example.com", host("example.com")),
+            ("Example.COM", host("example.com")),
+            ("example.com:8443", service("example.com", 8443)),
+            ("10.0.0.5:445", service("10.0.0.5", 445)),
+            ("10.0.0.0/24", Target::Range(String::from("10.0.0.0/24"))),
+            ("10.0.0.0/0", Target::Range(String::from("10.0.0.0/0"))),
+            //A full-length prefix is a single host.
+            ("10.0.0.5/32", host("10.0.0.5")),
+            (
+                "2001:db8::/64",
+                Target::Range(String::from("2001:db8::/64")),
+            ),
+            ("2001:db8::1/128", host("2001:db8::1")),
+            (
+                "https://example.com:8443/portal",
+                url("https://example.com:8443/portal"),
+            ),
+            ("http://example.com/?page=1#top", url("http://example.com/")),
+            ("https://[2001:db8::1]/", url("https://[2001:db8::1]/")),
+        ];
+        for (spec, expected) in cases {
+            assert_eq!(Target::parse(spec), Ok(expected), "{}", spec);
+        }
+    }
+
+    #[test]
+    fn parses_ipv6_targets() {
+        let cases = [
+            ("2001:db8::1", host("2001:db8::1")),
+            ("2001:0db8:0000::0001", host("2001:db8::1")),
+            ("::1", host("::1")),
+            ("[2001:db8::1]", host("2001:db8::1")),
+            ("[2001:db8::1]:8443", service("2001:db8::1", 8443)),
+            //Without brackets the last group is part of the address, never a port.
+            ("2001:db8::1:8443", host("2001:db8::1:8443")),
+            ("::ffff:10.0.0.5", host("::ffff:10.0.0.5")),
+        ];
+        for (spec, expected) in cases {
+            assert_eq!(Target::parse(spec), Ok(expected), "{}", spec);
+        }
+    }
+
+    #[test]
+    fn rejects_invalid_targets() {
+        let cases = [
+            "example.com:0",
+            "example.com:65536",
+            "example.com:",
+            "example.com:http",
+            "exa mple.com",
+            "10.0.0.0/33",
+            "2001:db8::/129",
+            "10.0.0.0/",
+            "example.com/24",
+            "[2001:db8::1",
+            "[10.0.0.5]",
+            "[2001:db8::1]8443",
+            "[2001:db8::1]:0",
+            "fe80::1%eth0",
+            "ftp://example.com/",
+            "http://",
+            "",
+        ];
+        for spec in cases {
+            assert!(
+                Target::parse(spec).is_err(),
+                "'{}' parsed as {:?}",
+                spec,
+                Target::parse(spec)
+            );
+        }
+    }
+
+    #[test]
+    fn names_hosts_and_directories() {
+        let cases = [
+            ("10.0.0.5", "10.0.0.5", "10.0.0.5", "10.0.0.5"),
+            (
+                "example.com:8443",
+                "example.com:8443",
+                "example.com",
+                "example.com",
+            ),
+            ("10.0.0.0/24", "10.0.0.0/24", "10.0.0.0/24", "10.0.0.0_24"),
+            (
+                "[2001:db8::1]:8443",
+                "2001:db8::1:8443",
+                "2001:db8::1",
+                "2001_db8__1",
+            ),
+            (
+                "https://[2001:db8::1]:8443/app",
+                "https://[2001:db8::1]:8443/app",
+                "2001:db8::1",
+                "2001_db8__1",
+            ),
+        ];
+        for (spec, name, target_host, dir_name) in cases {
+            let target = Target::parse(spec).unwrap();
+            assert_eq!(target.name(), name, "{}", spec);
+            assert_eq!(target.host(), target_host, "{}", spec);
+            assert_eq!(target.dir_name(), dir_name, "{}", spec);
+        }
+    }
+
+    #[test]
+    fn host_of_targets() {
+        let cases = [
+            ("10.0.0.5", "10.0.0.5"),
+            ("example.com", "example.com"),
+            ("2001:db8::1", "2001:db8::1"),
+            ("[2001:db8::1]", "2001:db8::1"),
+            ("https://Example.com:8443/login", "example.com"),
+            ("https://[2001:db8::1]:8443/", "2001:db8::1"),
+            ("smb://10.0.0.5:445", "10.0.0.5"),
+            ("tls://[2001:db8::1]:443", "2001:db8::1"),
+        ];
+        for (target, expected) in cases {
+            assert_eq!(host_of(target), expected, "{}", target);
+        }
+    }
+
+    #[test]
+    fn host_in_url_brackets_ipv6_only() {
+        assert_eq!(host_in_url("2001:db8::1"), "[2001:db8::1]");
+        assert_eq!(host_in_url("10.0.0.5"), "10.0.0.5");
+        assert_eq!(host_in_url("example.com"), "example.com");
+    }
+}